@@ -0,0 +1,35 @@
+//! Alloc + dealloc cost on maps in the pathological states of
+//! `index_mem_alloc::fixtures`
+//!
+//! Run with `cargo bench --bench fixtures`.
+
+use index_mem_alloc::{fixtures, MapType};
+use std::{hint::black_box, time::Instant};
+
+const ROUNDS: usize = 200_000;
+
+fn run(mut fixture: fixtures::Fixture) -> f64 {
+    let mut map = fixture.map().unwrap();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let index = black_box(map.alloc().unwrap());
+        map.dealloc(index).unwrap();
+    }
+    start.elapsed().as_nanos() as f64 / ROUNDS as f64
+}
+
+fn main() {
+    println!("alloc + dealloc, Max map");
+    println!(
+        "  fragmented:  {:.1} ns/op",
+        run(fixtures::worst_case_fragmented(MapType::Max))
+    );
+    println!(
+        "  nearly full: {:.1} ns/op",
+        run(fixtures::nearly_full(MapType::Max))
+    );
+    println!(
+        "  striped/64:  {:.1} ns/op",
+        run(fixtures::striped(MapType::Max, 64))
+    );
+}
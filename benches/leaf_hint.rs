@@ -0,0 +1,54 @@
+//! Compares alloc cost on a nearly full Max map without a header, with the
+//! header hints and with the cached leaf hint (`FLAG_LEAF_HINT`) on top
+//!
+//! Run with `cargo bench --bench leaf_hint`. Time per operation is a proxy for
+//! compute units: both scale with the number of bitmap words touched.
+
+use index_mem_alloc::{MapType, MemoryMap, FLAG_LEAF_HINT, HEADER_SIZE};
+use std::{hint::black_box, time::Instant};
+
+const ROUNDS: usize = 200_000;
+
+/// Header flags to init with, `None` for a bare bitmap without header
+fn run(flags: Option<u8>) -> f64 {
+    let mut words = vec![0u64; (HEADER_SIZE + MapType::Max.required_size()) / 8];
+    // Safety: the byte view covers exactly the u64 buffer
+    let data =
+        unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 8) };
+    let mut map = match flags {
+        Some(flags) => MemoryMap::init_with_flags(data, 0, MapType::Max, flags).unwrap(),
+        None => MemoryMap::new_from_slice(data, 0, MapType::Max).unwrap(),
+    };
+
+    // 99% utilization
+    let filled = MapType::Max.capacity() / 100 * 99;
+    for _ in 0..filled {
+        map.alloc().unwrap();
+    }
+
+    // Churn: free a pseudo-random slot, then allocate one
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        map.dealloc(state as usize % filled).unwrap();
+        black_box(map.alloc().unwrap());
+    }
+    start.elapsed().as_nanos() as f64 / ROUNDS as f64
+}
+
+fn main() {
+    let bare = run(None);
+    let header = run(Some(0));
+    let leaf_hint = run(Some(FLAG_LEAF_HINT));
+    println!("dealloc + alloc, 99% full Max map");
+    println!("  no header:      {bare:.1} ns/op");
+    println!("  header hints:   {header:.1} ns/op");
+    println!("  with leaf hint: {leaf_hint:.1} ns/op");
+    println!(
+        "  leaf hint saving vs no header: {:.1}%",
+        (1.0 - leaf_hint / bare) * 100.0
+    );
+}
@@ -0,0 +1,63 @@
+//! Tiny BPF program measuring the compute units of map operations
+//!
+//! Instruction data is `[map_type, op, count_lo, count_hi]`. The first
+//! account holds a bare bitmap of `map_type`; `op` 0 allocates `count` slots,
+//! `op` 1 frees slots `0..count`. The average cost is logged as
+//! `cu-bench op=<op> map_type=<map_type> per_op=<units>`.
+
+use index_mem_alloc::{MapType, MemoryMap, MemoryMapError};
+use solana_program::{
+    account_info::AccountInfo, compute_units::sol_remaining_compute_units,
+    entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey,
+};
+
+/// Operation byte: allocate `count` slots
+pub const OP_ALLOC: u8 = 0;
+/// Operation byte: free slots `0..count`
+pub const OP_DEALLOC: u8 = 1;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let &[map_type, op, count_lo, count_hi] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let count = u16::from_le_bytes([count_lo, count_hi]);
+    let account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let mut bitmap = account.try_borrow_mut_data()?;
+    let map_type = MapType::try_from(map_type).map_err(map_error)?;
+    let mut map = MemoryMap::new_from_slice(&mut bitmap, 0, map_type).map_err(map_error)?;
+
+    let before = sol_remaining_compute_units();
+    match op {
+        OP_ALLOC => {
+            for _ in 0..count {
+                map.alloc().map_err(map_error)?;
+            }
+        }
+        OP_DEALLOC => {
+            for index in 0..usize::from(count) {
+                map.dealloc(index).map_err(map_error)?;
+            }
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    let used = before - sol_remaining_compute_units();
+
+    msg!(
+        "cu-bench op={} map_type={} per_op={}",
+        op,
+        map_type as u8,
+        used / u64::from(count.max(1))
+    );
+    Ok(())
+}
+
+fn map_error(err: MemoryMapError) -> ProgramError {
+    ProgramError::Custom(err as u32)
+}
@@ -0,0 +1,126 @@
+//! Reports compute units per alloc and dealloc for every map type
+//!
+//! Build the program first, then run the suite:
+//!
+//! ```text
+//! cargo build-sbf --manifest-path cu-bench/Cargo.toml
+//! cargo test-sbf --manifest-path cu-bench/Cargo.toml -- --nocapture
+//! ```
+//!
+//! Set `CU_BENCH_MAX` to fail when any operation averages more units than that.
+
+use index_mem_alloc::MapType;
+use index_mem_alloc_cu_bench::{OP_ALLOC, OP_DEALLOC};
+use solana_program_test::{BanksClient, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+/// Operations per transaction, small enough for the Tiny map
+const OPS: u16 = 64;
+
+const MAP_TYPES: [MapType; 6] = [
+    MapType::Tiny,
+    MapType::Small,
+    MapType::Medium,
+    MapType::Standard,
+    MapType::Max,
+    MapType::Huge,
+];
+
+async fn measure(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    blockhash: Hash,
+    program_id: Pubkey,
+    bitmap: Pubkey,
+    map_type: MapType,
+    op: u8,
+) -> u64 {
+    let [count_lo, count_hi] = OPS.to_le_bytes();
+    let instruction = Instruction::new_with_bytes(
+        program_id,
+        &[map_type as u8, op, count_lo, count_hi],
+        vec![AccountMeta::new(bitmap, false)],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            instruction,
+        ],
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    );
+    let outcome = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+
+    let logs = outcome.metadata.unwrap().log_messages;
+    logs.iter()
+        .find_map(|line| line.split("per_op=").nth(1))
+        .and_then(|units| units.parse().ok())
+        .expect("program did not log its cost")
+}
+
+#[tokio::test]
+async fn test_compute_units_per_op() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("index_mem_alloc_cu_bench", program_id, None);
+    program_test.prefer_bpf(true);
+
+    let bitmaps: Vec<Pubkey> = MAP_TYPES.iter().map(|_| Pubkey::new_unique()).collect();
+    for (&map_type, bitmap) in MAP_TYPES.iter().zip(&bitmaps) {
+        program_test.add_account(
+            *bitmap,
+            Account {
+                lamports: 1_000_000_000_000,
+                data: vec![0; map_type.required_size()],
+                owner: program_id,
+                ..Account::default()
+            },
+        );
+    }
+    let (mut banks_client, payer, blockhash) = program_test.start().await;
+
+    let budget = std::env::var("CU_BENCH_MAX")
+        .ok()
+        .map(|max| max.parse::<u64>().unwrap());
+    println!("{:<10} {:>12} {:>12}", "map type", "alloc CU", "dealloc CU");
+    for (&map_type, &bitmap) in MAP_TYPES.iter().zip(&bitmaps) {
+        let mut costs = [0; 2];
+        for (cost, op) in costs.iter_mut().zip([OP_ALLOC, OP_DEALLOC]) {
+            *cost = measure(
+                &mut banks_client,
+                &payer,
+                blockhash,
+                program_id,
+                bitmap,
+                map_type,
+                op,
+            )
+            .await;
+        }
+        println!(
+            "{:<10} {:>12} {:>12}",
+            format!("{map_type:?}"),
+            costs[0],
+            costs[1]
+        );
+
+        if let Some(max) = budget {
+            assert!(
+                costs.iter().all(|&cost| cost <= max),
+                "{map_type:?} exceeds {max} CU per op: {costs:?}"
+            );
+        }
+    }
+}
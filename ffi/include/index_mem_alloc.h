@@ -0,0 +1,77 @@
+/*
+ * C interface of index-mem-alloc, built by the ffi crate as a static or
+ * shared library. Every function returns IMM_OK or a negative status and
+ * writes its result through an out pointer.
+ */
+
+#ifndef INDEX_MEM_ALLOC_H
+#define INDEX_MEM_ALLOC_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Map types, as stored in the header */
+#define IMM_MAP_MAX 0
+#define IMM_MAP_STANDARD 1
+#define IMM_MAP_SMALL 2
+#define IMM_MAP_HUGE 3
+#define IMM_MAP_TINY 4
+#define IMM_MAP_MEDIUM 5
+
+/* Status codes */
+#define IMM_OK 0
+#define IMM_NULL_ARGUMENT -100
+#define IMM_ERR_INVALID_OFFSET -1
+#define IMM_ERR_NO_AVAILABLE_SLOTS -2
+#define IMM_ERR_ALIGNMENT -3
+#define IMM_ERR_INSUFFICIENT_MEMORY -4
+#define IMM_ERR_INVALID_INDEX -5
+#define IMM_ERR_INDEX_OUT_OF_BOUNDS -6
+#define IMM_ERR_INVALID_MAP_TYPE -7
+#define IMM_ERR_NULL_POINTER -8
+#define IMM_ERR_INVALID_HEADER -9
+#define IMM_ERR_UNSUPPORTED_VERSION -10
+#define IMM_ERR_LENGTH_MISMATCH -11
+#define IMM_ERR_WRITE_VERIFICATION_FAILED -12
+#define IMM_ERR_REGION_OVERLAP -13
+#define IMM_ERR_TAIL_NOT_EMPTY -14
+#define IMM_ERR_PARTIAL_WORD -15
+#define IMM_ERR_SLOT_TAKEN -16
+#define IMM_ERR_OWNER_MISMATCH -17
+#define IMM_ERR_SLOT_PINNED -18
+#define IMM_ERR_CHECKSUM_MISMATCH -19
+#define IMM_ERR_DUPLICATE_KEY -20
+#define IMM_ERR_QUOTA_EXCEEDED -21
+#define IMM_ERR_MAP_FROZEN -22
+
+/* Opaque map handle created by imm_new and released by imm_free */
+typedef struct ImmHandle ImmHandle;
+
+/*
+ * Attach a map_type map to the bitmap at data + offset. data must stay
+ * valid for reads and writes of len bytes until the handle is freed.
+ */
+int32_t imm_new(uint8_t *data, size_t len, size_t offset, uint8_t map_type, ImmHandle **out);
+
+/* Release a handle created by imm_new, NULL is ignored */
+void imm_free(ImmHandle *handle);
+
+/* Allocate a slot and write its index to out */
+int32_t imm_alloc(ImmHandle *handle, size_t *out);
+
+/* Free slot index */
+int32_t imm_dealloc(ImmHandle *handle, size_t index);
+
+/* Write whether slot index is allocated to out */
+int32_t imm_is_allocated(const ImmHandle *handle, size_t index, bool *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* INDEX_MEM_ALLOC_H */
@@ -0,0 +1,7 @@
+//! C library build of the `index-mem-alloc` `ffi` feature
+//!
+//! `cargo build --release` here produces `libindex_mem_alloc_ffi.a` and a
+//! shared library exporting the `imm_*` functions declared in
+//! `include/index_mem_alloc.h`.
+
+pub use index_mem_alloc::ffi::*;
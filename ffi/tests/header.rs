@@ -0,0 +1,66 @@
+use index_mem_alloc::MapType;
+use index_mem_alloc::MemoryMapError::{self, *};
+use index_mem_alloc_ffi::{imm_error_code, IMM_NULL_ARGUMENT, IMM_OK};
+
+const HEADER: &str = include_str!("../include/index_mem_alloc.h");
+
+fn define(name: &str) -> i32 {
+    HEADER
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("#define ")?
+                .strip_prefix(name)?
+                .strip_prefix(' ')
+        })
+        .unwrap_or_else(|| panic!("{name} missing from the header"))
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn test_header_matches_status_codes() {
+    let errors: [(&str, MemoryMapError); 22] = [
+        ("IMM_ERR_INVALID_OFFSET", InvalidOffset),
+        ("IMM_ERR_NO_AVAILABLE_SLOTS", NoAvailableSlots),
+        ("IMM_ERR_ALIGNMENT", AlignmentError),
+        ("IMM_ERR_INSUFFICIENT_MEMORY", InsufficientMemory),
+        ("IMM_ERR_INVALID_INDEX", InvalidIndex),
+        ("IMM_ERR_INDEX_OUT_OF_BOUNDS", IndexOutOfBounds),
+        ("IMM_ERR_INVALID_MAP_TYPE", InvalidMapType),
+        ("IMM_ERR_NULL_POINTER", NullPointer),
+        ("IMM_ERR_INVALID_HEADER", InvalidHeader),
+        ("IMM_ERR_UNSUPPORTED_VERSION", UnsupportedVersion),
+        ("IMM_ERR_LENGTH_MISMATCH", LengthMismatch),
+        ("IMM_ERR_WRITE_VERIFICATION_FAILED", WriteVerificationFailed),
+        ("IMM_ERR_REGION_OVERLAP", RegionOverlap),
+        ("IMM_ERR_TAIL_NOT_EMPTY", TailNotEmpty),
+        ("IMM_ERR_PARTIAL_WORD", PartialWord),
+        ("IMM_ERR_SLOT_TAKEN", SlotTaken),
+        ("IMM_ERR_OWNER_MISMATCH", OwnerMismatch),
+        ("IMM_ERR_SLOT_PINNED", SlotPinned),
+        ("IMM_ERR_CHECKSUM_MISMATCH", ChecksumMismatch),
+        ("IMM_ERR_DUPLICATE_KEY", DuplicateKey),
+        ("IMM_ERR_QUOTA_EXCEEDED", QuotaExceeded),
+        ("IMM_ERR_MAP_FROZEN", MapFrozen),
+    ];
+    for (name, err) in errors {
+        assert_eq!(define(name), imm_error_code(err), "{name}");
+    }
+    assert_eq!(define("IMM_OK"), IMM_OK);
+    assert_eq!(define("IMM_NULL_ARGUMENT"), IMM_NULL_ARGUMENT);
+}
+
+#[test]
+fn test_header_matches_map_types() {
+    let map_types = [
+        ("IMM_MAP_MAX", MapType::Max),
+        ("IMM_MAP_STANDARD", MapType::Standard),
+        ("IMM_MAP_SMALL", MapType::Small),
+        ("IMM_MAP_HUGE", MapType::Huge),
+        ("IMM_MAP_TINY", MapType::Tiny),
+        ("IMM_MAP_MEDIUM", MapType::Medium),
+    ];
+    for (name, map_type) in map_types {
+        assert_eq!(define(name), map_type as i32, "{name}");
+    }
+}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use index_mem_alloc_fuzz::{run, Op};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|ops: Vec<Op>| run::<64, 4>(&ops));
@@ -0,0 +1,178 @@
+//! Model-checked operation sequences shared by the fuzz targets
+//!
+//! Run a target with `cargo +nightly fuzz run small_ops` from the repository
+//! root. Every target replays the fuzzer input as alloc, dealloc and reset
+//! operations against one map type and a `HashSet` model, checking the whole
+//! bitmap hierarchy after every step. Set `FUZZ_CHECK_EVERY=N` to check it
+//! every N steps instead, with only the touched path checked in between, when
+//! the Huge and Max targets run too slowly.
+
+use arbitrary::Arbitrary;
+use index_mem_alloc::{HierMemoryMap, MemoryMapError};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Operations replayed per input, keeps the large map types fast
+const MAX_OPS: usize = 256;
+
+#[derive(Debug, Arbitrary)]
+pub enum Op {
+    Alloc,
+    /// Index modulo twice the capacity, so half of them are out of range
+    Dealloc(u32),
+    /// Zero the bitmap and start over
+    Reset,
+}
+
+/// Steps between full hierarchy checks, from `FUZZ_CHECK_EVERY`, 1 if unset
+fn check_every() -> usize {
+    static CHECK_EVERY: OnceLock<usize> = OnceLock::new();
+    *CHECK_EVERY.get_or_init(|| {
+        std::env::var("FUZZ_CHECK_EVERY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&every| every > 0)
+            .unwrap_or(1)
+    })
+}
+
+/// Replay `ops` against a bare `HierMemoryMap<L1_BITS, LEVELS>` bitmap,
+/// checking the whole hierarchy every [`check_every`] steps
+pub fn run<const L1_BITS: usize, const LEVELS: usize>(ops: &[Op]) {
+    run_checked::<L1_BITS, LEVELS>(ops, check_every());
+}
+
+/// Replay `ops`, checking the whole hierarchy every `check_every` steps and
+/// at the end, and the words on the touched path at every other step
+pub fn run_checked<const L1_BITS: usize, const LEVELS: usize>(ops: &[Op], check_every: usize) {
+    let capacity = HierMemoryMap::<L1_BITS, LEVELS>::CAPACITY;
+    let mut words = vec![0u64; HierMemoryMap::<L1_BITS, LEVELS>::REQUIRED_SIZE / 8];
+    let mut model = HashSet::new();
+    // Lowest index missing from the model, advanced past each allocation
+    let mut lowest = 0;
+
+    for (step, op) in ops.iter().take(MAX_OPS).enumerate() {
+        // Safety: the byte view covers exactly the u64 buffer
+        let data = unsafe {
+            std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 8)
+        };
+        let mut map = HierMemoryMap::<L1_BITS, LEVELS>::new_from_slice(data, 0).unwrap();
+        let touched = match *op {
+            Op::Alloc => match map.alloc() {
+                Ok(index) => {
+                    assert_eq!(index, lowest, "alloc must return the lowest free index");
+                    model.insert(index);
+                    while model.contains(&lowest) {
+                        lowest += 1;
+                    }
+                    Some(index)
+                }
+                Err(MemoryMapError::NoAvailableSlots) => {
+                    assert_eq!(lowest, capacity, "alloc failed with slot {lowest} free");
+                    None
+                }
+                Err(err) => panic!("alloc failed with {err:?}, expected {lowest}"),
+            },
+            Op::Dealloc(index) => {
+                let index = index as usize % (2 * capacity);
+                match map.dealloc(index) {
+                    Ok(()) => {
+                        assert!(index < capacity);
+                        model.remove(&index);
+                        lowest = lowest.min(index);
+                        Some(index)
+                    }
+                    Err(MemoryMapError::InvalidIndex) => {
+                        assert!(index >= capacity);
+                        None
+                    }
+                    Err(err) => panic!("dealloc({index}) failed with {err:?}"),
+                }
+            }
+            Op::Reset => {
+                words.fill(0);
+                model.clear();
+                lowest = 0;
+                continue;
+            }
+        };
+
+        assert_eq!(map.is_full(), model.len() == capacity);
+        if (step + 1) % check_every == 0 {
+            assert_eq!(map.used_count().unwrap(), model.len(), "step {step}");
+            check_hierarchy::<L1_BITS, LEVELS>(&words, &model);
+        } else if let Some(index) = touched {
+            check_path::<L1_BITS, LEVELS>(&words, &model, index);
+        }
+    }
+
+    // Safety: the byte view covers exactly the u64 buffer
+    let data =
+        unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 8) };
+    let map = HierMemoryMap::<L1_BITS, LEVELS>::new_from_slice(data, 0).unwrap();
+    assert_eq!(map.used_count().unwrap(), model.len());
+    check_hierarchy::<L1_BITS, LEVELS>(&words, &model);
+}
+
+/// Leaf bit of `index` matches the model and every summary bit above it is
+/// set iff its child word is full
+fn check_path<const L1_BITS: usize, const LEVELS: usize>(
+    words: &[u64],
+    model: &HashSet<usize>,
+    index: usize,
+) {
+    let leaf_start = HierMemoryMap::<L1_BITS, LEVELS>::level_start(LEVELS - 1);
+    let allocated = words[leaf_start + (index >> 6)] & (1 << (index & 0x3f)) != 0;
+    assert_eq!(
+        allocated,
+        model.contains(&index),
+        "leaf bit {index} disagrees with the model"
+    );
+
+    let mut position = index >> 6;
+    for level in (0..LEVELS - 1).rev() {
+        check_summary_bit::<L1_BITS, LEVELS>(words, level, position);
+        position >>= 6;
+    }
+}
+
+/// Leaf bits match the model and every summary bit is set iff its child word
+/// is full
+fn check_hierarchy<const L1_BITS: usize, const LEVELS: usize>(
+    words: &[u64],
+    model: &HashSet<usize>,
+) {
+    let leaf_start = HierMemoryMap::<L1_BITS, LEVELS>::level_start(LEVELS - 1);
+    let allocated: usize = words[leaf_start..]
+        .iter()
+        .map(|word| word.count_ones() as usize)
+        .sum();
+    assert_eq!(allocated, model.len());
+    for &index in model {
+        assert!(words[leaf_start + (index >> 6)] & (1 << (index & 0x3f)) != 0);
+    }
+
+    let mut positions = L1_BITS;
+    for level in 0..LEVELS - 1 {
+        for position in 0..positions {
+            check_summary_bit::<L1_BITS, LEVELS>(words, level, position);
+        }
+        positions *= 64;
+    }
+}
+
+/// Bit `position` of `level` is set iff its child word is full
+fn check_summary_bit<const L1_BITS: usize, const LEVELS: usize>(
+    words: &[u64],
+    level: usize,
+    position: usize,
+) {
+    let start = HierMemoryMap::<L1_BITS, LEVELS>::level_start(level);
+    let child_start = HierMemoryMap::<L1_BITS, LEVELS>::level_start(level + 1);
+    let bit = words[start + (position >> 6)] & (1 << (position & 0x3f)) != 0;
+    let full = words[child_start + position] == u64::MAX;
+    assert_eq!(
+        bit, full,
+        "level {level} bit {position} disagrees with its child"
+    );
+}
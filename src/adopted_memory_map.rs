@@ -0,0 +1,252 @@
+use crate::{
+    bytes::{read_u64, write_u64},
+    get_first_zero_bit::get_first_zero_bit,
+    MapType, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Hierarchical allocator over a plain bitmap owned by another layout
+///
+/// The leaf bits stay where they are; only the summary levels live in a
+/// separate sidecar region. Bits past the end of the existing bitmap are
+/// treated as allocated, so alloc never hands out an index it cannot store.
+pub struct AdoptedMemoryMap<'a> {
+    leaves: &'a mut [u8],
+    summary: &'a mut [u8],
+    map_type: MapType,
+}
+
+impl<'a> AdoptedMemoryMap<'a> {
+    /// Number of sidecar bytes needed to hold the summary levels of `map_type`
+    pub const fn summary_size(map_type: MapType) -> usize {
+        let leaf_words = map_type.capacity() / 64;
+        map_type.required_size() - leaf_words * size_of::<u64>()
+    }
+
+    /// Wrap `existing_bits` (bit `i` set = slot `i` allocated) and rebuild the
+    /// summary levels from it
+    pub fn adopt(
+        existing_bits: &'a mut [u8],
+        summary: &'a mut [u8],
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        if existing_bits.len() * 8 > map_type.capacity() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        if summary.len() < Self::summary_size(map_type) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        let mut map = Self {
+            leaves: existing_bits,
+            summary,
+            map_type,
+        };
+        map.rebuild_summary();
+
+        Ok(map)
+    }
+
+    /// Number of slots backed by the existing bitmap
+    pub fn slots(&self) -> usize {
+        self.leaves.len() * 8
+    }
+
+    /// Allocate a new slot
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        // Descend the summary levels, `leaf` ends up as the leaf word position
+        let mut leaf = 0;
+        for level in 0..self.map_type.levels() - 1 {
+            let bits = if level == 0 {
+                self.map_type.first_level_bits()
+            } else {
+                64
+            };
+            let word = self.summary_word(self.map_type.level_start(level) + leaf);
+            leaf = (leaf << 6) + get_first_zero_bit(word, bits)?;
+        }
+
+        // Leaf allocation in the adopted bitmap
+        let leaf_word = self.leaf_word(leaf);
+        let bit = get_first_zero_bit(leaf_word, 64)?;
+        let leaf_word = leaf_word | (1 << bit);
+        self.set_leaf_word(leaf, leaf_word);
+
+        if leaf_word == u64::MAX {
+            self.mark_leaf_full(leaf);
+        }
+
+        Ok((leaf << 6) + bit)
+    }
+
+    /// Deallocate a previously allocated slot
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        if index >= self.slots() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+
+        let leaf = index >> 6;
+        let leaf_word = self.leaf_word(leaf) & !(1 << (index & 0x3f));
+        self.set_leaf_word(leaf, leaf_word);
+
+        // Clear the path from the leaf word up to the first level
+        let mut position = leaf;
+        for level in (0..self.map_type.levels() - 1).rev() {
+            let word = self.map_type.level_start(level) + (position >> 6);
+            self.set_summary_word(word, self.summary_word(word) & !(1 << (position & 0x3f)));
+            position >>= 6;
+        }
+
+        Ok(())
+    }
+
+    /// Recompute every summary word from the adopted leaf bits
+    fn rebuild_summary(&mut self) {
+        let summary_words = Self::summary_size(self.map_type) / size_of::<u64>();
+        for word in 0..summary_words {
+            self.set_summary_word(word, 0);
+        }
+
+        for leaf in 0..self.map_type.capacity() / 64 {
+            if self.leaf_word(leaf) == u64::MAX {
+                self.mark_leaf_full(leaf);
+            }
+        }
+    }
+
+    /// Propagate a full leaf word up the summary levels
+    fn mark_leaf_full(&mut self, leaf: usize) {
+        let mut position = leaf;
+        for level in (0..self.map_type.levels() - 1).rev() {
+            let word = self.map_type.level_start(level) + (position >> 6);
+            let value = self.summary_word(word) | (1 << (position & 0x3f));
+            self.set_summary_word(word, value);
+            if value != u64::MAX {
+                return;
+            }
+            position >>= 6;
+        }
+    }
+
+    /// Leaf word `leaf`, with bytes past the adopted bitmap reading as allocated
+    fn leaf_word(&self, leaf: usize) -> u64 {
+        let mut bytes = [0xff; 8];
+        let start = leaf * size_of::<u64>();
+        if start < self.leaves.len() {
+            let end = self.leaves.len().min(start + size_of::<u64>());
+            bytes[..end - start].copy_from_slice(&self.leaves[start..end]);
+        }
+        u64::from_le_bytes(bytes)
+    }
+
+    fn set_leaf_word(&mut self, leaf: usize, value: u64) {
+        let start = leaf * size_of::<u64>();
+        if start < self.leaves.len() {
+            let end = self.leaves.len().min(start + size_of::<u64>());
+            self.leaves[start..end].copy_from_slice(&value.to_le_bytes()[..end - start]);
+        }
+    }
+
+    fn summary_word(&self, word: usize) -> u64 {
+        read_u64(self.summary, word * size_of::<u64>())
+    }
+
+    fn set_summary_word(&mut self, word: usize, value: u64) {
+        write_u64(self.summary, word * size_of::<u64>(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_size() {
+        assert_eq!(AdoptedMemoryMap::summary_size(MapType::Tiny), 0);
+        assert_eq!(AdoptedMemoryMap::summary_size(MapType::Small), 8);
+        assert_eq!(AdoptedMemoryMap::summary_size(MapType::Standard), 5 * 8);
+        assert_eq!(AdoptedMemoryMap::summary_size(MapType::Max), 65 * 8);
+        assert_eq!(AdoptedMemoryMap::summary_size(MapType::Huge), 4161 * 8);
+    }
+
+    #[test]
+    fn test_adopt_existing_bits() {
+        // Legacy bitmap with slots 0..=8 and 10 taken
+        let mut bits = vec![0u8; 64];
+        bits[0] = 0xff;
+        bits[1] = 0b101;
+        let mut summary = vec![0xaa; AdoptedMemoryMap::summary_size(MapType::Small)];
+
+        let mut map = AdoptedMemoryMap::adopt(&mut bits, &mut summary, MapType::Small).unwrap();
+        assert_eq!(map.slots(), 512);
+        assert_eq!(map.alloc().unwrap(), 9);
+        assert_eq!(map.alloc().unwrap(), 11);
+
+        map.dealloc(3).unwrap();
+        assert_eq!(map.alloc().unwrap(), 3);
+
+        // Leaf bits are written back in place
+        assert_eq!(bits[1], 0b1111);
+    }
+
+    #[test]
+    fn test_adopted_bitmap_end_is_unavailable() {
+        // 10 bytes = 80 slots, the rest of the hierarchy must never be handed out
+        let mut bits = vec![0u8; 10];
+        let mut summary = vec![0; AdoptedMemoryMap::summary_size(MapType::Standard)];
+        let mut map = AdoptedMemoryMap::adopt(&mut bits, &mut summary, MapType::Standard).unwrap();
+
+        for expected in 0..80 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+        assert!(matches!(map.dealloc(80), Err(MemoryMapError::InvalidIndex)));
+
+        map.dealloc(70).unwrap();
+        assert_eq!(map.alloc().unwrap(), 70);
+        assert_eq!(bits, vec![0xff; 10]);
+    }
+
+    #[test]
+    fn test_adopt_full_leaf_words() {
+        // First leaf word fully allocated, summary must skip it
+        let mut bits = vec![0u8; 4096 / 8];
+        bits[..8].fill(0xff);
+        let mut summary = vec![0; AdoptedMemoryMap::summary_size(MapType::Max)];
+
+        let mut map = AdoptedMemoryMap::adopt(&mut bits, &mut summary, MapType::Max).unwrap();
+        assert_eq!(map.alloc().unwrap(), 64);
+    }
+
+    #[test]
+    fn test_adopt_rejects_bad_regions() {
+        let mut bits = vec![0u8; 1024];
+        let mut summary = vec![0; 8];
+        assert!(matches!(
+            AdoptedMemoryMap::adopt(&mut bits, &mut summary, MapType::Small),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+
+        let mut bits = vec![0u8; 8];
+        let mut summary = vec![0; 8];
+        assert!(matches!(
+            AdoptedMemoryMap::adopt(&mut bits, &mut summary, MapType::Standard),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_adopt_four_levels() {
+        // Two full leaf words followed by a partially used one
+        let mut bits = vec![0u8; 24];
+        bits[..16].fill(0xff);
+        bits[16] = 0b1;
+        let mut summary = vec![0; AdoptedMemoryMap::summary_size(MapType::Huge)];
+
+        let mut map = AdoptedMemoryMap::adopt(&mut bits, &mut summary, MapType::Huge).unwrap();
+        assert_eq!(map.alloc().unwrap(), 129);
+
+        map.dealloc(5).unwrap();
+        assert_eq!(map.alloc().unwrap(), 5);
+    }
+}
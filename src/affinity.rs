@@ -0,0 +1,144 @@
+use crate::{
+    bytes::{read_u64, write_u64},
+    MemoryMap, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Size of one table entry: tenant id and last-used block
+const ENTRY_SIZE: usize = 2 * size_of::<u64>();
+
+/// Persisted table remembering the last leaf block used by each tenant
+///
+/// The table is direct-mapped (`tenant % entries`), a colliding tenant simply
+/// replaces the previous hint. Block values are stored off by one so an
+/// all-zero region is an empty table.
+pub struct AffinityTable<'a> {
+    data: &'a mut [u8],
+}
+
+impl<'a> AffinityTable<'a> {
+    /// Number of bytes needed for a table with `entries` entries
+    pub const fn required_size(entries: usize) -> usize {
+        entries * ENTRY_SIZE
+    }
+
+    /// Wrap a region previously zeroed or written by this table
+    pub fn new(data: &'a mut [u8]) -> Result<Self, MemoryMapError> {
+        if data.len() < ENTRY_SIZE || !data.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data })
+    }
+
+    /// Number of entries in the table
+    pub fn entries(&self) -> usize {
+        self.data.len() / ENTRY_SIZE
+    }
+
+    /// Last block recorded for `tenant`
+    pub fn block(&self, tenant: u64) -> Option<usize> {
+        let slot = self.slot(tenant);
+        let block = self.word(slot * 2 + 1);
+        (block != 0 && self.word(slot * 2) == tenant).then(|| (block - 1) as usize)
+    }
+
+    /// Remember `block` as the last block used by `tenant`
+    pub fn record(&mut self, tenant: u64, block: usize) {
+        let slot = self.slot(tenant);
+        self.set_word(slot * 2, tenant);
+        self.set_word(slot * 2 + 1, block as u64 + 1);
+    }
+
+    /// Forget the hint stored for `tenant`
+    pub fn clear(&mut self, tenant: u64) {
+        if self.block(tenant).is_some() {
+            let slot = self.slot(tenant);
+            self.set_word(slot * 2, 0);
+            self.set_word(slot * 2 + 1, 0);
+        }
+    }
+
+    fn slot(&self, tenant: u64) -> usize {
+        (tenant % self.entries() as u64) as usize
+    }
+
+    fn word(&self, word: usize) -> u64 {
+        read_u64(self.data, word * size_of::<u64>())
+    }
+
+    fn set_word(&mut self, word: usize, value: u64) {
+        write_u64(self.data, word * size_of::<u64>(), value);
+    }
+}
+
+impl MemoryMap {
+    /// Allocate a new slot for `tenant`, clustering it with the tenant's
+    /// previous allocations when their block still has room
+    pub fn alloc_for_tenant(
+        &mut self,
+        table: &mut AffinityTable,
+        tenant: u64,
+    ) -> Result<usize, MemoryMapError> {
+        let index = match table.block(tenant) {
+            Some(block) => self.alloc_near(block)?,
+            None => self.alloc()?,
+        };
+        table.record(tenant, index >> 6);
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_table_record_and_lookup() {
+        let mut region = vec![0u8; AffinityTable::required_size(4)];
+        let mut table = AffinityTable::new(&mut region).unwrap();
+        assert_eq!(table.entries(), 4);
+        assert_eq!(table.block(7), None);
+
+        table.record(7, 0);
+        table.record(2, 12);
+        assert_eq!(table.block(7), Some(0));
+        assert_eq!(table.block(2), Some(12));
+
+        // Tenant 11 maps to the same entry as 7 and replaces its hint
+        table.record(11, 5);
+        assert_eq!(table.block(7), None);
+        assert_eq!(table.block(11), Some(5));
+
+        table.clear(11);
+        assert_eq!(table.block(11), None);
+
+        assert!(AffinityTable::new(&mut [0u8; 24]).is_err());
+    }
+
+    #[test]
+    fn test_tenant_allocations_cluster() {
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Standard).unwrap();
+        let mut region = vec![0u8; AffinityTable::required_size(8)];
+        let mut table = AffinityTable::new(&mut region).unwrap();
+
+        // Tenant 1 lands in block 0, fill it up with anonymous allocations
+        assert_eq!(map.alloc_for_tenant(&mut table, 1).unwrap(), 0);
+        for _ in 1..70 {
+            map.alloc().unwrap();
+        }
+        // Tenant 2 starts in block 1
+        assert_eq!(map.alloc_for_tenant(&mut table, 2).unwrap(), 70);
+
+        // Free slots in block 0 go back to tenant 1 rather than the first free slot
+        map.dealloc(66).unwrap();
+        map.dealloc(10).unwrap();
+        assert_eq!(map.alloc_for_tenant(&mut table, 2).unwrap(), 66);
+        assert_eq!(map.alloc_for_tenant(&mut table, 1).unwrap(), 10);
+
+        // Once its block is full the tenant moves on to the next free slot
+        assert_eq!(map.alloc_for_tenant(&mut table, 1).unwrap(), 71);
+        assert_eq!(table.block(1), Some(1));
+    }
+}
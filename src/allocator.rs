@@ -0,0 +1,89 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+
+/// Slot allocation shared by every map type, for code generic over them
+pub trait IndexAllocator {
+    /// Allocate a new slot
+    fn alloc(&mut self) -> Result<usize, MemoryMapError>;
+
+    /// Deallocate a previously allocated slot
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError>;
+
+    /// Number of slots the allocator can hand out
+    fn capacity(&self) -> usize;
+
+    /// Check whether slot `index` is allocated
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError>;
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> IndexAllocator
+    for HierMemoryMap<L1_BITS, LEVELS, B>
+{
+    fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        Self::alloc(self)
+    }
+
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        Self::dealloc(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        Self::CAPACITY
+    }
+
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        Self::is_allocated(self, index)
+    }
+}
+
+impl IndexAllocator for MemoryMap {
+    fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        Self::alloc(self)
+    }
+
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        Self::dealloc(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        Self::capacity(self)
+    }
+
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        Self::is_allocated(self, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, TinyMemoryMap};
+
+    /// Allocate until full, then free every other slot
+    fn fill_and_thin(map: &mut impl IndexAllocator) -> usize {
+        while map.alloc().is_ok() {}
+        for index in (0..map.capacity()).step_by(2) {
+            map.dealloc(index).unwrap();
+        }
+        (0..map.capacity())
+            .filter(|&index| map.is_allocated(index).unwrap())
+            .count()
+    }
+
+    #[test]
+    fn test_generic_over_map_types() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(fill_and_thin(&mut map), 2_048);
+
+        let mut buffer = create_aligned_buffer(TinyMemoryMap::REQUIRED_SIZE);
+        let mut map = TinyMemoryMap::new_from_slice(&mut buffer, 0).unwrap();
+        assert_eq!(fill_and_thin(&mut map), 32);
+
+        let dynamic: &mut dyn IndexAllocator = &mut map;
+        assert_eq!(dynamic.alloc().unwrap(), 0);
+        assert!(matches!(
+            dynamic.is_allocated(64),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+}
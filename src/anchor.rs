@@ -0,0 +1,61 @@
+use crate::{MapType, MemoryMap, MemoryMapError};
+use solana_program::account_info::AccountInfo;
+
+/// Length of the discriminator Anchor puts in front of account data
+pub const ANCHOR_DISCRIMINATOR_LEN: usize = 8;
+
+impl MemoryMap {
+    /// Create a map right after the Anchor discriminator of `account`
+    ///
+    /// Fails with `InvalidHeader` if the first 8 bytes differ from
+    /// `discriminator`, e.g. the account belongs to another type. The bitmap
+    /// starts at offset [`ANCHOR_DISCRIMINATOR_LEN`].
+    pub fn new_anchor(
+        account: &AccountInfo,
+        discriminator: &[u8; ANCHOR_DISCRIMINATOR_LEN],
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        let mut data = account.data.borrow_mut();
+        let stored = data
+            .get(..ANCHOR_DISCRIMINATOR_LEN)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        if stored != discriminator {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        Self::new_from_slice(&mut data, ANCHOR_DISCRIMINATOR_LEN, map_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+    use solana_program::pubkey::Pubkey;
+
+    const DISCRIMINATOR: [u8; 8] = *b"orderbk\0";
+
+    #[test]
+    fn test_new_anchor() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data =
+            create_aligned_buffer(ANCHOR_DISCRIMINATOR_LEN + MapType::Small.required_size());
+        data[..8].copy_from_slice(&DISCRIMINATOR);
+        let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &key, false, 0);
+
+        let mut map = MemoryMap::new_anchor(&account, &DISCRIMINATOR, MapType::Small).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+        // First leaf word, after the discriminator and the first-level word
+        assert_eq!(account.data.borrow()[16], 1);
+        assert_eq!(&account.data.borrow()[..8], &DISCRIMINATOR);
+
+        assert!(matches!(
+            MemoryMap::new_anchor(&account, b"otherdsc", MapType::Small),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert!(matches!(
+            MemoryMap::new_anchor(&account, &DISCRIMINATOR, MapType::Standard),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
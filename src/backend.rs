@@ -0,0 +1,275 @@
+use crate::{get_u64, get_u64_mut, word_offset, MemoryMapError};
+use core::ptr::NonNull;
+
+/// Word storage used by [`HierMemoryMap`](crate::HierMemoryMap)
+///
+/// The allocator only ever touches the bitmap through these two calls, so an
+/// alternative backend (copy-on-write overlay, instrumentation, snapshot of a
+/// remote account) can be swapped in without changing the allocation logic.
+pub trait Backend {
+    /// Number of bytes available to the map
+    fn size(&self) -> usize;
+
+    /// Read the u64 word at word index `index`
+    fn read_word(&self, index: usize) -> Result<u64, MemoryMapError>;
+
+    /// Write the u64 word at word index `index`
+    fn write_word(&mut self, index: usize, value: u64) -> Result<(), MemoryMapError>;
+}
+
+/// How [`RawBackend`] loads and stores a bitmap word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordAccess {
+    /// Single u64 access, memory must be 8-byte aligned
+    U64,
+    /// Two u32 accesses, memory only needs to be 4-byte aligned
+    U32Pair,
+}
+
+/// Default backend, reading and writing words in place in account bytes
+#[derive(Clone)]
+pub struct RawBackend {
+    memory: NonNull<u8>,
+    size: usize,
+    access: WordAccess,
+}
+
+impl RawBackend {
+    /// Wrap `size` bytes of u64-aligned memory at `memory`
+    ///
+    /// # Safety
+    /// `memory` must be valid for reads and writes of `size` bytes for as long
+    /// as the backend or a map built on it is used, and nothing else may
+    /// access those bytes meanwhile.
+    pub const unsafe fn new(memory: NonNull<u8>, size: usize) -> Self {
+        Self {
+            memory,
+            size,
+            access: WordAccess::U64,
+        }
+    }
+
+    /// Wrap `size` bytes of u32-aligned memory at `memory`
+    ///
+    /// Each word is split into two u32 halves in native byte order, so the
+    /// bitmap bytes are identical to the ones written by [`RawBackend::new`].
+    ///
+    /// # Safety
+    /// Same requirements as [`RawBackend::new`].
+    pub const unsafe fn new_u32(memory: NonNull<u8>, size: usize) -> Self {
+        Self {
+            memory,
+            size,
+            access: WordAccess::U32Pair,
+        }
+    }
+
+    /// Limit the region to its first `size` bytes
+    ///
+    /// Map constructors clamp to the required size of the map type, so words
+    /// past the bitmap are unreachable even with a wrong word index.
+    pub const fn clamped(mut self, size: usize) -> Self {
+        if size < self.size {
+            self.size = size;
+        }
+        self
+    }
+
+    pub const fn access(&self) -> WordAccess {
+        self.access
+    }
+
+    /// Pointer to the first byte of the region
+    pub const fn memory(&self) -> NonNull<u8> {
+        self.memory
+    }
+}
+
+impl Backend for RawBackend {
+    #[inline]
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    fn read_word(&self, index: usize) -> Result<u64, MemoryMapError> {
+        match self.access {
+            WordAccess::U64 => get_u64(self.memory, self.size, index).copied(),
+            WordAccess::U32Pair => {
+                let [first, second] = self.halves(index)?;
+                // Safety: `halves` checked the bounds, memory is u32-aligned
+                let (first, second) = unsafe { (*first, *second) };
+                Ok(join_halves(first, second))
+            }
+        }
+    }
+
+    #[inline]
+    fn write_word(&mut self, index: usize, value: u64) -> Result<(), MemoryMapError> {
+        match self.access {
+            WordAccess::U64 => *get_u64_mut(self.memory, self.size, index)? = value,
+            WordAccess::U32Pair => {
+                let [first, second] = self.halves(index)?;
+                let (low, high) = (value as u32, (value >> 32) as u32);
+                // Safety: `halves` checked the bounds, memory is u32-aligned
+                unsafe {
+                    if cfg!(target_endian = "little") {
+                        (*first, *second) = (low, high);
+                    } else {
+                        (*first, *second) = (high, low);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RawBackend {
+    /// Pointers to the two u32 halves of word `index`, in memory order
+    #[inline]
+    fn halves(&self, index: usize) -> Result<[*mut u32; 2], MemoryMapError> {
+        let start = word_offset(self.size, index)?;
+        let first = unsafe { self.memory.as_ptr().add(start) } as *mut u32;
+        Ok([first, unsafe { first.add(1) }])
+    }
+}
+
+/// Combine two u32 halves read in memory order into a native u64
+#[inline]
+const fn join_halves(first: u32, second: u32) -> u64 {
+    if cfg!(target_endian = "little") {
+        (second as u64) << 32 | first as u64
+    } else {
+        (first as u64) << 32 | second as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, HierMemoryMap, MapType, MemoryMap, SmallMemoryMap};
+    use std::collections::BTreeMap;
+
+    /// Copy-on-write overlay over a read-only snapshot, counting writes
+    struct OverlayBackend<'a> {
+        base: &'a [u64],
+        overlay: BTreeMap<usize, u64>,
+        writes: usize,
+    }
+
+    impl Backend for OverlayBackend<'_> {
+        fn size(&self) -> usize {
+            self.base.len() * 8
+        }
+
+        fn read_word(&self, index: usize) -> Result<u64, MemoryMapError> {
+            match self.overlay.get(&index) {
+                Some(&value) => Ok(value),
+                None => self
+                    .base
+                    .get(index)
+                    .copied()
+                    .ok_or(MemoryMapError::IndexOutOfBounds),
+            }
+        }
+
+        fn write_word(&mut self, index: usize, value: u64) -> Result<(), MemoryMapError> {
+            if index >= self.base.len() {
+                return Err(MemoryMapError::IndexOutOfBounds);
+            }
+            self.overlay.insert(index, value);
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_overlay_backend() {
+        // Snapshot with the first leaf word full
+        let mut snapshot = vec![0u64; SmallMemoryMap::REQUIRED_SIZE / 8];
+        snapshot[1] = u64::MAX;
+        snapshot[0] = 1;
+
+        let backend = OverlayBackend {
+            base: &snapshot,
+            overlay: BTreeMap::new(),
+            writes: 0,
+        };
+        let mut map = HierMemoryMap::<64, 2, _>::with_backend(backend).unwrap();
+        assert_eq!(map.alloc().unwrap(), 64);
+        map.dealloc(3).unwrap();
+        assert_eq!(map.alloc().unwrap(), 3);
+
+        let backend = map.into_backend();
+        assert_eq!(backend.writes, 5);
+        assert_eq!(backend.overlay[&1], u64::MAX);
+        assert_eq!(backend.overlay[&2], 1);
+        // The snapshot itself is untouched
+        assert_eq!(snapshot[2], 0);
+    }
+
+    #[test]
+    fn test_backend_too_small() {
+        let snapshot = vec![0u64; 4];
+        let backend = OverlayBackend {
+            base: &snapshot,
+            overlay: BTreeMap::new(),
+            writes: 0,
+        };
+        assert!(matches!(
+            HierMemoryMap::<64, 2, _>::with_backend(backend),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_u32_access_matches_u64() {
+        let mut buffer = create_aligned_buffer(SmallMemoryMap::REQUIRED_SIZE + 4);
+
+        // Offset 4 is only u32-aligned
+        assert!(matches!(
+            SmallMemoryMap::new_from_slice(&mut buffer, 4),
+            Err(MemoryMapError::AlignmentError)
+        ));
+        let mut map = SmallMemoryMap::new_from_slice_u32(&mut buffer, 4).unwrap();
+        for expected in 0..70 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        map.dealloc(33).unwrap();
+
+        let word = |bytes: &[u8], index: usize| {
+            u64::from_ne_bytes(bytes[index * 8..index * 8 + 8].try_into().unwrap())
+        };
+        assert_eq!(word(&buffer[4..], 1), !(1 << 33));
+        assert_eq!(word(&buffer[4..], 2), 0b11_1111);
+
+        // A u64-backed map over the same bytes sees the same state
+        let mut copy = create_aligned_buffer(SmallMemoryMap::REQUIRED_SIZE);
+        copy[..SmallMemoryMap::REQUIRED_SIZE]
+            .copy_from_slice(&buffer[4..4 + SmallMemoryMap::REQUIRED_SIZE]);
+        let mut reference = SmallMemoryMap::new_from_slice(&mut copy, 0).unwrap();
+        assert_eq!(reference.alloc().unwrap(), 33);
+        assert_eq!(map.alloc().unwrap(), 33);
+    }
+
+    #[test]
+    fn test_constructors_clamp_to_bitmap() {
+        let size = SmallMemoryMap::REQUIRED_SIZE;
+        let mut buffer = create_aligned_buffer(size + 64);
+        let map = SmallMemoryMap::new_from_slice(&mut buffer, 0).unwrap();
+        let backend = map.into_backend();
+        assert_eq!(backend.size(), size);
+        assert!(matches!(
+            backend.read_word(size / 8),
+            Err(MemoryMapError::IndexOutOfBounds)
+        ));
+
+        // Same through the enum and the u32 access path
+        let map = MemoryMap::new_from_slice_u32(&mut buffer, 4, MapType::Tiny).unwrap();
+        let MemoryMap::Tiny(map) = map else {
+            unreachable!()
+        };
+        assert_eq!(map.into_backend().size(), MapType::Tiny.required_size());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    backend::Backend, header::FLAG_FROZEN_DEALLOC, hier_memory_map::HierMemoryMap, MemoryMap,
+    MemoryMapError,
+};
+
+/// Largest buddy order, a whole leaf word of 64 slots
+pub const MAX_BUDDY_ORDER: u32 = 6;
+
+/// Bits at multiples of `1 << order` within a leaf word
+const fn aligned_bits(order: u32) -> u64 {
+    let mut mask = 0;
+    let mut bit = 0;
+    while bit < 64 {
+        mask |= 1 << bit;
+        bit += 1 << order;
+    }
+    mask
+}
+
+/// Starts of the fully free, aligned `1 << order` runs of a leaf word
+const fn free_runs(free: u64, order: u32) -> u64 {
+    let mut runs = free;
+    let mut width = 0;
+    while width < order {
+        runs &= runs >> (1 << width);
+        width += 1;
+    }
+    runs & aligned_bits(order)
+}
+
+/// Mask of the `1 << order` slots starting at bit `bit`
+const fn run_mask(bit: usize, order: u32) -> u64 {
+    (u64::MAX >> (64 - (1 << order))) << bit
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Allocate `1 << order` slots aligned to their size
+    ///
+    /// Within the first leaf word that fits, the smallest free buddy is used so
+    /// larger free buddies are only split when nothing smaller is left. Slots
+    /// in the reserved range are treated as allocated.
+    pub(crate) fn alloc_buddy(&mut self, order: u32) -> Result<usize, MemoryMapError> {
+        if order > MAX_BUDDY_ORDER {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+
+        let reserved = self.reserved_range();
+        let mut leaf = 0;
+        while let Some(open) = self.next_open_leaf(leaf)? {
+            let free = !self.leaf_word(open)?
+                & Self::LEAF_MASK
+                & !Self::reserved_bits(open, reserved.as_ref());
+            for size in order..=MAX_BUDDY_ORDER {
+                // Free buddies of this size whose parent is not free as a whole
+                let runs = free_runs(free, size);
+                let merged = if size < MAX_BUDDY_ORDER {
+                    let parents = free_runs(free, size + 1);
+                    parents | parents << (1 << size)
+                } else {
+                    0
+                };
+                let unsplit = runs & !merged;
+                if unsplit != 0 {
+                    let bit = unsplit.trailing_zeros() as usize;
+                    self.claim_bits(open, run_mask(bit, order))?;
+                    return Ok((open << 6) + bit);
+                }
+            }
+            leaf = open + 1;
+        }
+
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+
+    /// Free the `1 << order` slots starting at `index`, merging them back
+    /// with their buddies
+    ///
+    /// The run must lie below the slot limit, the reserved tail stays allocated.
+    pub(crate) fn dealloc_buddy(&mut self, index: usize, order: u32) -> Result<(), MemoryMapError> {
+        if order > MAX_BUDDY_ORDER || !index.is_multiple_of(1 << order) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let limit = self.slot_limit();
+        if index >= limit || (limit < Self::CAPACITY && index + (1 << order) > limit) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.check_frozen(FLAG_FROZEN_DEALLOC)?;
+        self.release_bits(index >> 6, run_mask(index & 0x3f, order))
+    }
+}
+
+impl MemoryMap {
+    pub(crate) fn alloc_buddy(&mut self, order: u32) -> Result<usize, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.alloc_buddy(order),
+            Self::Standard(map) => map.alloc_buddy(order),
+            Self::Small(map) => map.alloc_buddy(order),
+            Self::Huge(map) => map.alloc_buddy(order),
+            Self::Tiny(map) => map.alloc_buddy(order),
+            Self::Medium(map) => map.alloc_buddy(order),
+        }
+    }
+
+    pub(crate) fn dealloc_buddy(&mut self, index: usize, order: u32) -> Result<(), MemoryMapError> {
+        match self {
+            Self::Max(map) => map.dealloc_buddy(index, order),
+            Self::Standard(map) => map.dealloc_buddy(index, order),
+            Self::Small(map) => map.dealloc_buddy(index, order),
+            Self::Huge(map) => map.dealloc_buddy(index, order),
+            Self::Tiny(map) => map.dealloc_buddy(index, order),
+            Self::Medium(map) => map.dealloc_buddy(index, order),
+        }
+    }
+}
+
+/// Map handing out runs of 1, 2, 4, ..., 64 slots aligned to their size
+///
+/// A run of `1 << order` slots is a buddy, two free buddies of the same order
+/// form the free buddy of the next order. Runs never cross a leaf word, and
+/// the caller passes the order back on dealloc, like with any buddy allocator.
+#[derive(Clone)]
+pub struct BuddyMemoryMap {
+    map: MemoryMap,
+}
+
+impl BuddyMemoryMap {
+    /// Hand out buddies from the slots of `map`
+    ///
+    /// Single slots allocated through `map` before stay allocated.
+    pub const fn new(map: MemoryMap) -> Self {
+        Self { map }
+    }
+
+    /// Smallest order whose runs hold `slots` slots
+    pub const fn order_for(slots: usize) -> Option<u32> {
+        if slots > 1 << MAX_BUDDY_ORDER {
+            return None;
+        }
+        Some(slots.next_power_of_two().trailing_zeros())
+    }
+
+    /// Allocate `1 << order` consecutive slots and return the first index
+    pub fn alloc(&mut self, order: u32) -> Result<usize, MemoryMapError> {
+        self.map.alloc_buddy(order)
+    }
+
+    /// Allocate a run big enough for `slots` slots, rounded up to a power of two
+    pub fn alloc_slots(&mut self, slots: usize) -> Result<usize, MemoryMapError> {
+        let order = Self::order_for(slots).ok_or(MemoryMapError::InsufficientMemory)?;
+        self.map.alloc_buddy(order)
+    }
+
+    /// Free the run of `1 << order` slots starting at `index`
+    pub fn dealloc(&mut self, index: usize, order: u32) -> Result<(), MemoryMapError> {
+        self.map.dealloc_buddy(index, order)
+    }
+
+    /// Underlying map
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Give back the underlying map
+    pub fn into_inner(self) -> MemoryMap {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_free_runs() {
+        assert_eq!(aligned_bits(0), u64::MAX);
+        assert_eq!(aligned_bits(6), 1);
+        assert_eq!(free_runs(0b1111_0110, 1), 0b0101_0000 & aligned_bits(1));
+        assert_eq!(free_runs(0b1111_0110, 2), 0b0001_0000);
+        assert_eq!(free_runs(u64::MAX, 6), 1);
+        assert_eq!(run_mask(8, 3), 0xff00);
+        assert_eq!(run_mask(0, 6), u64::MAX);
+    }
+
+    #[test]
+    fn test_buddy_split_and_merge() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+
+        // Splitting the first word: 1 at 0, then its buddy, then a fresh pair
+        assert_eq!(buddy.alloc(0).unwrap(), 0);
+        assert_eq!(buddy.alloc(1).unwrap(), 2);
+        assert_eq!(buddy.alloc(0).unwrap(), 1);
+        assert_eq!(buddy.alloc(2).unwrap(), 4);
+        assert_eq!(buddy.alloc(3).unwrap(), 8);
+        assert_eq!(buddy.map().used_count().unwrap(), 16);
+
+        // Freed buddies merge back into the larger run
+        buddy.dealloc(2, 1).unwrap();
+        buddy.dealloc(0, 0).unwrap();
+        buddy.dealloc(1, 0).unwrap();
+        assert_eq!(buddy.alloc(2).unwrap(), 0);
+
+        // A whole word, then the next one once the first is split
+        assert_eq!(buddy.alloc(6).unwrap(), 64);
+        assert_eq!(buddy.alloc_slots(5).unwrap(), 16);
+        assert_eq!(buddy.map().used_count().unwrap(), 16 + 64 + 8);
+    }
+
+    #[test]
+    fn test_buddy_prefers_split_runs() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+
+        assert_eq!(buddy.alloc(4).unwrap(), 0);
+        assert_eq!(buddy.alloc(1).unwrap(), 16);
+        assert_eq!(buddy.alloc(4).unwrap(), 32);
+        buddy.dealloc(0, 4).unwrap();
+        // The free half at 18..32 is used before splitting the free run at 0
+        assert_eq!(buddy.alloc(1).unwrap(), 18);
+        assert_eq!(buddy.alloc(3).unwrap(), 24);
+        assert_eq!(buddy.alloc(4).unwrap(), 0);
+        assert_eq!(buddy.alloc(4).unwrap(), 48);
+        assert!(matches!(
+            buddy.alloc(3),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert_eq!(buddy.alloc(2).unwrap(), 20);
+        assert!(buddy.map().is_full());
+    }
+
+    #[test]
+    fn test_buddy_bounds() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+
+        assert_eq!(BuddyMemoryMap::order_for(0), Some(0));
+        assert_eq!(BuddyMemoryMap::order_for(3), Some(2));
+        assert_eq!(BuddyMemoryMap::order_for(64), Some(6));
+        assert_eq!(BuddyMemoryMap::order_for(65), None);
+        assert!(matches!(buddy.alloc(7), Err(MemoryMapError::InvalidIndex)));
+        assert!(matches!(
+            buddy.alloc_slots(65),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        assert!(matches!(
+            buddy.dealloc(2, 2),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            buddy.dealloc(64, 0),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+}
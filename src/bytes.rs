@@ -0,0 +1,33 @@
+//! Little-endian field access for records kept in account bytes
+
+use core::mem::size_of;
+
+pub(crate) fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    let mut word = [0; 2];
+    word.copy_from_slice(&bytes[at..at + size_of::<u16>()]);
+    u16::from_le_bytes(word)
+}
+
+pub(crate) fn write_u16(bytes: &mut [u8], at: usize, value: u16) {
+    bytes[at..at + size_of::<u16>()].copy_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(&bytes[at..at + size_of::<u32>()]);
+    u32::from_le_bytes(word)
+}
+
+pub(crate) fn write_u32(bytes: &mut [u8], at: usize, value: u32) {
+    bytes[at..at + size_of::<u32>()].copy_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(&bytes[at..at + size_of::<u64>()]);
+    u64::from_le_bytes(word)
+}
+
+pub(crate) fn write_u64(bytes: &mut [u8], at: usize, value: u64) {
+    bytes[at..at + size_of::<u64>()].copy_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,172 @@
+use crate::{IndexAllocator, MemoryMap, MemoryMapError};
+
+/// Several maps of one type, e.g. one per account, used as a single index
+/// space
+///
+/// Map `k` covers indices `k * cap..(k + 1) * cap` where `cap` is the shared
+/// capacity, and alloc takes from the first map that is not full. Build the
+/// maps with [`MemoryMap::new`] from the accounts in a fixed order; the order
+/// decides the indices, so it must not change between instructions.
+pub struct ChainedMemoryMap<'a> {
+    maps: &'a mut [MemoryMap],
+}
+
+impl<'a> ChainedMemoryMap<'a> {
+    /// Chain `maps` in order, they must all have the same map type
+    pub fn new(maps: &'a mut [MemoryMap]) -> Result<Self, MemoryMapError> {
+        if let Some(first) = maps.first() {
+            let map_type = first.map_type();
+            if maps.iter().any(|map| map.map_type() != map_type) {
+                return Err(MemoryMapError::InvalidMapType);
+            }
+        }
+        Ok(Self { maps })
+    }
+
+    /// Number of chained maps
+    pub fn accounts(&self) -> usize {
+        self.maps.len()
+    }
+
+    /// Capacity of each chained map
+    pub fn account_capacity(&self) -> usize {
+        self.maps.first().map_or(0, MemoryMap::capacity)
+    }
+
+    /// Slots of all chained maps together
+    pub fn capacity(&self) -> usize {
+        self.account_capacity() * self.maps.len()
+    }
+
+    /// Map holding `index` and the index within it
+    pub fn locate(&self, index: usize) -> Result<(usize, usize), MemoryMapError> {
+        if index >= self.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let capacity = self.account_capacity();
+        Ok((index / capacity, index % capacity))
+    }
+
+    /// Chained map `account`
+    pub fn map(&self, account: usize) -> Option<&MemoryMap> {
+        self.maps.get(account)
+    }
+
+    /// Allocate a slot in the first map that is not full
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        let capacity = self.account_capacity();
+        for (account, map) in self.maps.iter_mut().enumerate() {
+            if map.is_full() {
+                continue;
+            }
+            match map.alloc() {
+                Ok(local) => return Ok(account * capacity + local),
+                Err(MemoryMapError::NoAvailableSlots) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+
+    /// Free a global index in the account that owns it
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        let (account, local) = self.locate(index)?;
+        self.maps[account].dealloc(local)
+    }
+
+    /// Check whether a global index is allocated in its account
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        let (account, local) = self.locate(index)?;
+        self.maps[account].is_allocated(local)
+    }
+
+    /// Check whether every chained map is full
+    pub fn is_full(&self) -> bool {
+        self.maps.iter().all(MemoryMap::is_full)
+    }
+
+    /// Allocated slots over all chained maps
+    pub fn used_count(&self) -> Result<usize, MemoryMapError> {
+        self.maps.iter().map(MemoryMap::used_count).sum()
+    }
+}
+
+impl IndexAllocator for ChainedMemoryMap<'_> {
+    fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        Self::alloc(self)
+    }
+
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        Self::dealloc(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        Self::capacity(self)
+    }
+
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        Self::is_allocated(self, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_spans_accounts() {
+        let size = MapType::Tiny.required_size();
+        let mut first = create_aligned_buffer(size);
+        let mut second = create_aligned_buffer(size);
+        let mut maps = [
+            MemoryMap::new_from_slice(&mut first, 0, MapType::Tiny).unwrap(),
+            MemoryMap::new_from_slice(&mut second, 0, MapType::Tiny).unwrap(),
+        ];
+        let mut chain = ChainedMemoryMap::new(&mut maps).unwrap();
+        assert_eq!(chain.capacity(), 128);
+
+        for expected in 0..128 {
+            assert_eq!(chain.alloc().unwrap(), expected);
+        }
+        assert!(chain.is_full());
+        assert!(matches!(
+            chain.alloc(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+
+        chain.dealloc(70).unwrap();
+        assert_eq!(chain.locate(70).unwrap(), (1, 6));
+        assert!(!chain.map(1).unwrap().is_allocated(6).unwrap());
+        chain.dealloc(5).unwrap();
+        // The first account is refilled before the second
+        assert_eq!(chain.alloc().unwrap(), 5);
+        assert_eq!(chain.alloc().unwrap(), 70);
+        assert_eq!(chain.used_count().unwrap(), 128);
+        assert!(matches!(
+            chain.is_allocated(128),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_mixed_types() {
+        let mut tiny = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut small = create_aligned_buffer(MapType::Small.required_size());
+        let mut maps = [
+            MemoryMap::new_from_slice(&mut tiny, 0, MapType::Tiny).unwrap(),
+            MemoryMap::new_from_slice(&mut small, 0, MapType::Small).unwrap(),
+        ];
+        assert!(matches!(
+            ChainedMemoryMap::new(&mut maps),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+
+        let mut chain = ChainedMemoryMap::new(&mut []).unwrap();
+        assert_eq!(chain.capacity(), 0);
+        assert!(matches!(
+            chain.alloc(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    backend::Backend, header::FLAG_CHECKSUM, hier_memory_map::HierMemoryMap, MemoryMap,
+    MemoryMapError,
+};
+
+/// Contribution of bitmap word `index` holding `word` to the checksum
+///
+/// The checksum is the wrapping sum of these terms, so a write only has to
+/// swap one term. Zero words contribute nothing, and both the word mix and
+/// the odd position weight are bijective, so any single changed word always
+/// changes the sum.
+pub(crate) const fn checksum_term(index: usize, word: u64) -> u64 {
+    let mut z = word;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    z.wrapping_mul((index as u64).wrapping_mul(2).wrapping_add(1))
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Checksum of the whole bitmap as it is now
+    pub(crate) fn compute_checksum(&self) -> Result<u64, MemoryMapError> {
+        (0..Self::level_start(LEVELS)).try_fold(0u64, |sum, index| {
+            Ok(sum.wrapping_add(checksum_term(index, self.word_at(index)?)))
+        })
+    }
+}
+
+impl MemoryMap {
+    fn compute_checksum(&self) -> Result<u64, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.compute_checksum(),
+            Self::Standard(map) => map.compute_checksum(),
+            Self::Small(map) => map.compute_checksum(),
+            Self::Huge(map) => map.compute_checksum(),
+            Self::Tiny(map) => map.compute_checksum(),
+            Self::Medium(map) => map.compute_checksum(),
+        }
+    }
+
+    /// Recompute the checksum from the bitmap and keep it up to date from now on
+    ///
+    /// Enables [`FLAG_CHECKSUM`] on maps initialized without it. Only maps
+    /// with a header can record a checksum.
+    pub fn rehash(&mut self) -> Result<u64, MemoryMapError> {
+        let checksum = self.compute_checksum()?;
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        header.flags |= FLAG_CHECKSUM;
+        header.checksum = checksum;
+        Ok(checksum)
+    }
+
+    /// Check the bitmap against the recorded checksum
+    ///
+    /// Fails with `ChecksumMismatch` when something other than this map wrote
+    /// into the bitmap region, and with `InvalidHeader` if no checksum is kept.
+    pub fn validate_checksum(&self) -> Result<(), MemoryMapError> {
+        let header = self
+            .header()
+            .filter(|header| header.has_flag(FLAG_CHECKSUM))
+            .ok_or(MemoryMapError::InvalidHeader)?;
+        if header.checksum != self.compute_checksum()? {
+            return Err(MemoryMapError::ChecksumMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_checksum_follows_mutations() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map =
+            MemoryMap::init_with_flags(&mut buffer, 0, MapType::Standard, FLAG_CHECKSUM).unwrap();
+        map.validate_checksum().unwrap();
+        for _ in 0..5_000 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(17).unwrap();
+        let base = map.alloc_block().unwrap();
+        map.dealloc_block(base).unwrap();
+        map.try_shrink(8_192).unwrap();
+        map.validate_checksum().unwrap();
+
+        // A stray write into the bitmap is caught
+        buffer[HEADER_SIZE + 100] ^= 0x10;
+        let map = MemoryMap::load(&mut buffer, 0, MapType::Standard).unwrap();
+        assert!(matches!(
+            map.validate_checksum(),
+            Err(MemoryMapError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_rehash_enables_checksum() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        map.alloc().unwrap();
+        assert!(matches!(
+            map.validate_checksum(),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+
+        map.rehash().unwrap();
+        map.alloc().unwrap();
+        map.validate_checksum().unwrap();
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert!(matches!(map.rehash(), Err(MemoryMapError::InvalidHeader)));
+    }
+}
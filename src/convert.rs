@@ -0,0 +1,269 @@
+use crate::{
+    header::{self, FLAG_CHECKSUM},
+    MapType, MemoryMap, MemoryMapError, HEADER_SIZE,
+};
+use core::mem::size_of;
+
+impl MemoryMap {
+    /// Convert the bare `from` bitmap at `data[offset..]` into a `to` bitmap in
+    /// place, keeping every allocated index
+    ///
+    /// Used to upgrade capacity after the account was extended, e.g. a full
+    /// Small region into a Standard one, without draining and re-inserting
+    /// records. Fails with `TailNotEmpty` if an index at or past the capacity
+    /// of `to` is allocated, leaving the bitmap unchanged. Maps created with
+    /// [`MemoryMap::init`] go through [`MemoryMap::convert_auto`] instead, so
+    /// the header follows.
+    pub fn convert(
+        data: &mut [u8],
+        offset: usize,
+        from: MapType,
+        to: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        Self::convert_bitmap(data, offset, from, to, None)
+    }
+
+    /// Convert a map created with [`MemoryMap::init`] at `offset` into a `to`
+    /// map in place, taking the current type from the header
+    ///
+    /// The header records the new map type and the current account length,
+    /// so the map loads again with [`MemoryMap::load`] or
+    /// [`MemoryMap::load_auto`]. The allocation hints are reset, the counter is
+    /// recounted and a kept checksum is recomputed. A tail reserved by
+    /// [`MemoryMap::with_capacity`] stays reserved, up to the capacity of `to`,
+    /// and a reserved range is cut to it.
+    pub fn convert_auto(
+        data: &mut [u8],
+        offset: usize,
+        to: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        header.validate()?;
+        let from = header.map_type()?;
+        let limit = (header.slot_limit != 0).then_some(header.slot_limit as usize);
+        let bitmap_offset = offset
+            .checked_add(HEADER_SIZE)
+            .ok_or(MemoryMapError::InvalidOffset)?;
+        Self::convert_bitmap(data, bitmap_offset, from, to, limit)?;
+
+        let header = header::header_mut(data, offset)?;
+        header.map_type = to as u8;
+        header.data_len = data_len as u64;
+        header.first_free_block = 0;
+        header.leaf_hint = 0;
+        header.slot_limit = limit
+            .filter(|&limit| limit < to.capacity())
+            .map_or(0, |limit| limit as u64);
+        header.reserved_start = header.reserved_start.min(to.capacity() as u64);
+        header.reserved_end = header.reserved_end.min(to.capacity() as u64);
+        let checksum = header.has_flag(FLAG_CHECKSUM);
+
+        let mut map = Self::load(data, offset, to)?;
+        map.recount()?;
+        if checksum {
+            map.rehash()?;
+        }
+        Ok(map)
+    }
+
+    /// Convert the bitmap, treating every slot from `limit` on as the
+    /// reserved tail: it does not block shrinking and stays reserved in `to`
+    fn convert_bitmap(
+        data: &mut [u8],
+        offset: usize,
+        from: MapType,
+        to: MapType,
+        limit: Option<usize>,
+    ) -> Result<Self, MemoryMapError> {
+        let end = offset
+            .checked_add(to.required_size())
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        if data.len() < end {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        let source = Self::new_from_slice(data, offset, from)?;
+        let kept = to.capacity().min(from.capacity()) / 64;
+        for block in kept..from.capacity() / 64 {
+            if source.leaf_word(block)? & !tail_bits(block, limit) != 0 {
+                return Err(MemoryMapError::TailNotEmpty);
+            }
+        }
+
+        // Move the kept leaf words to where `to` expects them, then clear
+        // everything else and rebuild the summaries from the leaves
+        let word = size_of::<u64>();
+        let from_leaves = offset + from.level_start(from.levels() - 1) * word;
+        let to_leaves = offset + to.level_start(to.levels() - 1) * word;
+        let leaves_len = kept * word;
+        data.copy_within(from_leaves..from_leaves + leaves_len, to_leaves);
+        data[offset..to_leaves].fill(0);
+        data[to_leaves + leaves_len..end].fill(0);
+
+        let mut map = Self::new_from_slice(data, offset, to)?;
+        for block in 0..kept {
+            let leaf = map.leaf_word(block)?;
+            if leaf != 0 {
+                map.claim_bits(block, leaf)?;
+            }
+        }
+        if let Some(limit) = limit.filter(|&limit| limit < to.capacity()) {
+            map.reserve_tail(limit)?;
+        }
+
+        Ok(map)
+    }
+
+    fn reserve_tail(&mut self, slots: usize) -> Result<(), MemoryMapError> {
+        match self {
+            Self::Max(map) => map.reserve_tail(slots),
+            Self::Standard(map) => map.reserve_tail(slots),
+            Self::Small(map) => map.reserve_tail(slots),
+            Self::Huge(map) => map.reserve_tail(slots),
+            Self::Tiny(map) => map.reserve_tail(slots),
+            Self::Medium(map) => map.reserve_tail(slots),
+        }
+    }
+
+    pub(crate) fn leaf_word(&self, block: usize) -> Result<u64, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.leaf_word(block),
+            Self::Standard(map) => map.leaf_word(block),
+            Self::Small(map) => map.leaf_word(block),
+            Self::Huge(map) => map.leaf_word(block),
+            Self::Tiny(map) => map.leaf_word(block),
+            Self::Medium(map) => map.leaf_word(block),
+        }
+    }
+
+    fn claim_bits(&mut self, block: usize, mask: u64) -> Result<(), MemoryMapError> {
+        match self {
+            Self::Max(map) => map.claim_bits(block, mask),
+            Self::Standard(map) => map.claim_bits(block, mask),
+            Self::Small(map) => map.claim_bits(block, mask),
+            Self::Huge(map) => map.claim_bits(block, mask),
+            Self::Tiny(map) => map.claim_bits(block, mask),
+            Self::Medium(map) => map.claim_bits(block, mask),
+        }
+    }
+}
+
+/// Bits of leaf word `block` at or past `limit`
+fn tail_bits(block: usize, limit: Option<usize>) -> u64 {
+    match limit {
+        Some(limit) if limit <= block << 6 => u64::MAX,
+        Some(limit) if limit < (block + 1) << 6 => u64::MAX << (limit & 0x3f),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, AllocPolicy};
+
+    #[test]
+    fn test_convert_small_to_standard() {
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        while map.alloc().is_ok() {}
+        map.dealloc(1_234).unwrap();
+
+        let mut map =
+            MemoryMap::convert(&mut buffer, 0, MapType::Small, MapType::Standard).unwrap();
+        assert_eq!(map.map_type(), MapType::Standard);
+        assert_eq!(map.used_count().unwrap(), MapType::Small.capacity() - 1);
+        assert!(map.is_allocated(4_095).unwrap());
+        assert!(!map.is_allocated(1_234).unwrap());
+        assert_eq!(map.alloc().unwrap(), 1_234);
+        assert_eq!(map.alloc().unwrap(), MapType::Small.capacity());
+    }
+
+    #[test]
+    fn test_convert_down_checks_tail() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        for _ in 0..10 {
+            map.alloc().unwrap();
+        }
+        map.alloc_near(1).unwrap();
+
+        assert!(matches!(
+            MemoryMap::convert(&mut buffer, 0, MapType::Small, MapType::Tiny),
+            Err(MemoryMapError::TailNotEmpty)
+        ));
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        map.dealloc(64).unwrap();
+
+        let mut map = MemoryMap::convert(&mut buffer, 0, MapType::Small, MapType::Tiny).unwrap();
+        assert_eq!(map.used_count().unwrap(), 10);
+        assert_eq!(map.alloc().unwrap(), 10);
+
+        assert!(matches!(
+            MemoryMap::convert(&mut buffer, 0, MapType::Tiny, MapType::Max),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_convert_auto_rewrites_header() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let small = HEADER_SIZE + MapType::Small.required_size();
+        let mut map =
+            MemoryMap::init_with_flags(&mut buffer[..small], 0, MapType::Small, FLAG_CHECKSUM)
+                .unwrap();
+        while map.alloc().is_ok() {}
+        map.dealloc(1_234).unwrap();
+
+        // The account was extended, the Small header no longer matches
+        assert!(matches!(
+            MemoryMap::load_auto(&mut buffer, 0),
+            Err(MemoryMapError::LengthMismatch)
+        ));
+        let mut map = MemoryMap::convert_auto(&mut buffer, 0, MapType::Standard).unwrap();
+        assert_eq!(map.used_count().unwrap(), MapType::Small.capacity() - 1);
+        map.validate_checksum().unwrap();
+        assert_eq!(map.alloc().unwrap(), 1_234);
+        assert_eq!(map.alloc().unwrap(), MapType::Small.capacity());
+
+        let map = MemoryMap::load(&mut buffer, 0, MapType::Standard).unwrap();
+        assert_eq!(map.used_count().unwrap(), MapType::Small.capacity() + 1);
+        let mut map = MemoryMap::load_auto(&mut buffer, 0).unwrap();
+        assert_eq!(map.map_type(), MapType::Standard);
+        map.validate_checksum().unwrap();
+        assert_eq!(map.alloc().unwrap(), MapType::Small.capacity() + 1);
+    }
+
+    #[test]
+    fn test_convert_auto_keeps_slot_limit() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+        for _ in 0..3 {
+            map.alloc().unwrap();
+        }
+
+        // Up to Standard: the new space lies past the limit and stays reserved
+        let mut map = MemoryMap::convert_auto(&mut buffer, 0, MapType::Standard).unwrap();
+        assert_eq!(map.slot_limit(), 100);
+        assert_eq!(map.used_count().unwrap(), 3);
+        assert_eq!(map.free_count().unwrap(), 97);
+        assert!(matches!(
+            map.dealloc(5_000),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        map.extend_capacity(200).unwrap();
+        map.set_alloc_policy(AllocPolicy::LastFit).unwrap();
+        map.reserve_range(10, 4_000).unwrap();
+        assert_eq!(map.alloc().unwrap(), 9);
+
+        // Down to Tiny: the reserved tail does not block it, the range is cut
+        let mut map = MemoryMap::convert_auto(&mut buffer, 0, MapType::Tiny).unwrap();
+        assert_eq!(map.slot_limit(), MapType::Tiny.capacity());
+        assert_eq!(map.reserved_range(), Some(10..64));
+        assert_eq!(map.used_count().unwrap(), 4);
+        assert_eq!(map.alloc_policy().unwrap(), AllocPolicy::LastFit);
+        assert_eq!(map.alloc().unwrap(), 8);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{header::FLAG_COUNTERS, MemoryMap};
+
+/// Lifetime churn counters persisted in the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocCounters {
+    /// Slots allocated since the map was initialized
+    pub total_allocs: u64,
+    /// Slots freed since the map was initialized
+    pub total_deallocs: u64,
+    /// Slots currently allocated
+    pub current_used: u64,
+}
+
+impl MemoryMap {
+    /// Lifetime counters of a map initialized with [`FLAG_COUNTERS`]
+    ///
+    /// Every mutation method updates them with the number of slots it
+    /// actually changed, so churn metrics need no transaction replay.
+    pub fn counters(&self) -> Option<AllocCounters> {
+        self.header()
+            .filter(|header| header.has_flag(FLAG_COUNTERS))
+            .map(|header| AllocCounters {
+                total_allocs: header.total_allocs,
+                total_deallocs: header.total_deallocs,
+                current_used: header.used_count,
+            })
+    }
+
+    /// Mutation generation recorded in the header, `None` without a header
+    ///
+    /// Off-chain readers assembling a view from several reads compare the
+    /// generation before and after, a change means the snapshot is torn.
+    pub fn generation(&self) -> Option<u64> {
+        self.header().map(|header| header.generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_counters_track_churn() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map =
+            MemoryMap::init_with_flags(&mut buffer, 0, MapType::Small, FLAG_COUNTERS).unwrap();
+        for _ in 0..5 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(1).unwrap();
+        // Freeing a free slot changes nothing
+        map.dealloc(1).unwrap();
+        let base = map.alloc_block().unwrap();
+        map.dealloc_block(base).unwrap();
+
+        let map = MemoryMap::load(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(
+            map.counters(),
+            Some(AllocCounters {
+                total_allocs: 69,
+                total_deallocs: 65,
+                current_used: 4,
+            })
+        );
+
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        map.alloc().unwrap();
+        assert_eq!(map.counters(), None);
+    }
+
+    #[test]
+    fn test_generation_detects_torn_reads() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(map.generation(), Some(0));
+
+        let before = map.generation();
+        map.alloc().unwrap();
+        map.dealloc(0).unwrap();
+        assert_eq!(map.generation(), Some(2));
+        assert_ne!(map.generation(), before);
+
+        // No-op mutations leave readers undisturbed
+        map.dealloc(0).unwrap();
+        assert!(map.alloc_block().is_ok());
+        assert_eq!(map.generation(), Some(3));
+        map.try_shrink(64).unwrap();
+        assert_eq!(map.generation(), Some(4));
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert_eq!(map.generation(), None);
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    bytes::{read_u32, read_u64, write_u32, write_u64},
+    MapType, MemoryMap, MemoryMapError,
+};
+
+const TAG_INNER: u32 = 1;
+const TAG_LEAF: u32 = 2;
+
+/// Ordered `u64 -> u64` map kept in an account, e.g. price level -> level data
+///
+/// A crit-bit tree whose nodes are slots of a [`MemoryMap`]: a leaf holds a
+/// key and its value, an inner node the highest bit on which its two subtrees
+/// differ. Layout from the given offset: an 8-byte tree header (root and
+/// length), the bitmap, then one [`CritbitTree::NODE_SIZE`] record per slot.
+/// `n` keys take `2n - 1` slots.
+pub struct CritbitTree<'a> {
+    header: &'a mut [u8],
+    map: MemoryMap,
+    nodes: &'a mut [u8],
+}
+
+/// Decoded node record
+#[derive(Clone, Copy)]
+enum Node {
+    Inner { bit: u32, children: [u32; 2] },
+    Leaf { key: u64, value: u64 },
+}
+
+impl<'a> CritbitTree<'a> {
+    /// Bytes per node record
+    pub const NODE_SIZE: usize = 24;
+
+    const HEADER_SIZE: usize = 8;
+
+    /// Number of bytes taken by a tree over a map of type `map_type`
+    pub const fn required_size(map_type: MapType) -> usize {
+        Self::HEADER_SIZE + map_type.required_size() + map_type.capacity() * Self::NODE_SIZE
+    }
+
+    /// Attach a tree at `data[offset..]`
+    ///
+    /// A zeroed region gives an empty tree, the same region reopens an
+    /// existing one.
+    pub fn new(
+        data: &'a mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        let end = offset
+            .checked_add(Self::required_size(map_type))
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        let region = data
+            .get_mut(offset..end)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+
+        let (header, rest) = region.split_at_mut(Self::HEADER_SIZE);
+        let (bitmap, nodes) = rest.split_at_mut(map_type.required_size());
+        let map = MemoryMap::new_from_slice(bitmap, 0, map_type)?;
+
+        Ok(Self { header, map, nodes })
+    }
+
+    /// Number of keys in the tree
+    pub fn len(&self) -> usize {
+        read_u32(self.header, 4) as usize
+    }
+
+    /// Check whether the tree has no keys
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value stored for `key`
+    pub fn get(&self, key: u64) -> Option<u64> {
+        match self.node(self.closest(key)?) {
+            Node::Leaf { key: found, value } if found == key => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Smallest key and its value
+    pub fn min(&self) -> Option<(u64, u64)> {
+        self.extreme(0)
+    }
+
+    /// Largest key and its value
+    pub fn max(&self) -> Option<(u64, u64)> {
+        self.extreme(1)
+    }
+
+    /// Keys and values in ascending key order
+    pub fn iter(&self) -> CritbitIter<'_, 'a> {
+        let mut iter = CritbitIter {
+            tree: self,
+            stack: [0; STACK_DEPTH],
+            len: 0,
+        };
+        if let Some(root) = self.root() {
+            iter.push(root);
+        }
+        iter
+    }
+
+    /// Map `key` to `value`, returning the value it replaced
+    ///
+    /// A new key takes two slots, if they are not both available the tree is
+    /// left unchanged.
+    pub fn insert(&mut self, key: u64, value: u64) -> Result<Option<u64>, MemoryMapError> {
+        let Some(closest) = self.closest(key) else {
+            let leaf = self.map.alloc()?;
+            self.set_node(leaf, Node::Leaf { key, value });
+            self.set_root(Some(leaf));
+            self.set_len(1);
+            return Ok(None);
+        };
+
+        let Node::Leaf {
+            key: found,
+            value: old,
+        } = self.node(closest)
+        else {
+            return Err(MemoryMapError::InvalidIndex);
+        };
+        if found == key {
+            self.set_node(closest, Node::Leaf { key, value });
+            return Ok(Some(old));
+        }
+
+        let leaf = self.map.alloc()?;
+        let inner = match self.map.alloc() {
+            Ok(inner) => inner,
+            Err(err) => {
+                self.map.dealloc(leaf)?;
+                return Err(err);
+            }
+        };
+
+        // Highest differing bit, the new inner node goes above every node
+        // splitting on a lower bit
+        let bit = 63 - (found ^ key).leading_zeros();
+        let mut parent = None;
+        let mut slot = self.root().ok_or(MemoryMapError::InvalidIndex)?;
+        while let Node::Inner {
+            bit: crit,
+            children,
+        } = self.node(slot)
+        {
+            if crit < bit {
+                break;
+            }
+            let side = direction(key, crit);
+            parent = Some((slot, side));
+            slot = children[side] as usize;
+        }
+
+        let side = direction(key, bit);
+        let mut children = [0; 2];
+        children[side] = leaf as u32;
+        children[1 - side] = slot as u32;
+        self.set_node(leaf, Node::Leaf { key, value });
+        self.set_node(inner, Node::Inner { bit, children });
+        self.replace_child(parent, inner);
+        self.set_len(self.len() + 1);
+
+        Ok(None)
+    }
+
+    /// Remove `key`, returning its value
+    pub fn remove(&mut self, key: u64) -> Result<Option<u64>, MemoryMapError> {
+        let Some(mut slot) = self.root() else {
+            return Ok(None);
+        };
+        let (mut parent, mut grandparent) = (None, None);
+        while let Node::Inner { bit, children } = self.node(slot) {
+            let side = direction(key, bit);
+            grandparent = parent;
+            parent = Some((slot, side));
+            slot = children[side] as usize;
+        }
+        let Node::Leaf { key: found, value } = self.node(slot) else {
+            return Err(MemoryMapError::InvalidIndex);
+        };
+        if found != key {
+            return Ok(None);
+        }
+
+        match parent {
+            None => self.set_root(None),
+            Some((inner, side)) => {
+                // The sibling takes the place of the parent
+                let Node::Inner { children, .. } = self.node(inner) else {
+                    return Err(MemoryMapError::InvalidIndex);
+                };
+                self.replace_child(grandparent, children[1 - side] as usize);
+                self.map.dealloc(inner)?;
+            }
+        }
+        self.map.dealloc(slot)?;
+        self.set_len(self.len() - 1);
+
+        Ok(Some(value))
+    }
+
+    /// Map holding the node slots
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Leaf reached by following the bits of `key`, the only one that can match
+    fn closest(&self, key: u64) -> Option<usize> {
+        let mut slot = self.root()?;
+        while let Node::Inner { bit, children } = self.node(slot) {
+            slot = children[direction(key, bit)] as usize;
+        }
+        Some(slot)
+    }
+
+    fn extreme(&self, side: usize) -> Option<(u64, u64)> {
+        let mut slot = self.root()?;
+        loop {
+            match self.node(slot) {
+                Node::Inner { children, .. } => slot = children[side] as usize,
+                Node::Leaf { key, value } => return Some((key, value)),
+            }
+        }
+    }
+
+    /// Point `parent`'s child on `side` (or the root) at `slot`
+    fn replace_child(&mut self, parent: Option<(usize, usize)>, slot: usize) {
+        match parent {
+            None => self.set_root(Some(slot)),
+            Some((parent, side)) => {
+                if let Node::Inner { bit, mut children } = self.node(parent) {
+                    children[side] = slot as u32;
+                    self.set_node(parent, Node::Inner { bit, children });
+                }
+            }
+        }
+    }
+
+    fn root(&self) -> Option<usize> {
+        // Slot index plus one, zero for an empty tree
+        (read_u32(self.header, 0) as usize).checked_sub(1)
+    }
+
+    fn set_root(&mut self, root: Option<usize>) {
+        write_u32(self.header, 0, root.map_or(0, |root| root as u32 + 1));
+    }
+
+    fn set_len(&mut self, len: usize) {
+        write_u32(self.header, 4, len as u32);
+    }
+
+    fn node(&self, slot: usize) -> Node {
+        let record = &self.nodes[slot * Self::NODE_SIZE..(slot + 1) * Self::NODE_SIZE];
+        let (a, b) = (read_u64(record, 8), read_u64(record, 16));
+        match read_u32(record, 0) {
+            TAG_INNER => Node::Inner {
+                bit: read_u32(record, 4),
+                children: [a as u32, b as u32],
+            },
+            _ => Node::Leaf { key: a, value: b },
+        }
+    }
+
+    fn set_node(&mut self, slot: usize, node: Node) {
+        let record = &mut self.nodes[slot * Self::NODE_SIZE..(slot + 1) * Self::NODE_SIZE];
+        let (tag, bit, a, b) = match node {
+            Node::Inner { bit, children } => (
+                TAG_INNER,
+                bit,
+                u64::from(children[0]),
+                u64::from(children[1]),
+            ),
+            Node::Leaf { key, value } => (TAG_LEAF, 0, key, value),
+        };
+        write_u32(record, 0, tag);
+        write_u32(record, 4, bit);
+        write_u64(record, 8, a);
+        write_u64(record, 16, b);
+    }
+}
+
+/// Most pending subtrees of an in-order walk: one right sibling per bit on
+/// the path, plus the node being expanded
+const STACK_DEPTH: usize = 66;
+
+/// In-order iterator over a [`CritbitTree`]
+pub struct CritbitIter<'t, 'a> {
+    tree: &'t CritbitTree<'a>,
+    stack: [u32; STACK_DEPTH],
+    len: usize,
+}
+
+impl CritbitIter<'_, '_> {
+    fn push(&mut self, slot: usize) {
+        self.stack[self.len] = slot as u32;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.stack[self.len] as usize)
+    }
+}
+
+impl Iterator for CritbitIter<'_, '_> {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        loop {
+            match self.tree.node(self.pop()?) {
+                Node::Inner { children, .. } => {
+                    self.push(children[1] as usize);
+                    self.push(children[0] as usize);
+                }
+                Node::Leaf { key, value } => return Some((key, value)),
+            }
+        }
+    }
+}
+
+/// Child taken by `key` at an inner node splitting on `bit`
+const fn direction(key: u64, bit: u32) -> usize {
+    ((key >> bit) & 1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_ordered_operations() {
+        let mut buffer = create_aligned_buffer(CritbitTree::required_size(MapType::Small));
+        let mut tree = CritbitTree::new(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!((tree.min(), tree.max()), (None, None));
+
+        let keys = [500, 20, 7_000, 21, u64::MAX, 0, 640];
+        for key in keys {
+            assert_eq!(tree.insert(key, key.wrapping_mul(2)).unwrap(), None);
+        }
+        assert_eq!(tree.len(), keys.len());
+        assert_eq!(tree.map().used_count().unwrap(), 2 * keys.len() - 1);
+        assert_eq!(tree.insert(21, 1).unwrap(), Some(42));
+        assert_eq!(tree.get(21), Some(1));
+        assert_eq!(tree.get(22), None);
+
+        let mut sorted = keys;
+        sorted.sort_unstable();
+        assert_eq!(tree.iter().map(|(key, _)| key).collect::<Vec<_>>(), sorted);
+        assert_eq!(tree.min(), Some((0, 0)));
+        assert_eq!(tree.max(), Some((u64::MAX, u64::MAX.wrapping_mul(2))));
+
+        assert_eq!(tree.remove(20).unwrap(), Some(40));
+        assert_eq!(tree.remove(20).unwrap(), None);
+        assert_eq!(tree.remove(0).unwrap(), Some(0));
+        assert_eq!(tree.min(), Some((21, 1)));
+        assert_eq!(tree.len(), keys.len() - 2);
+
+        // Reopened from the same bytes
+        let mut tree = CritbitTree::new(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(
+            tree.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            [21, 500, 640, 7_000, u64::MAX]
+        );
+        for key in [21, 500, 640, 7_000, u64::MAX] {
+            assert!(tree.remove(key).unwrap().is_some());
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.map().used_count().unwrap(), 0);
+        assert_eq!(tree.iter().next(), None);
+    }
+
+    #[test]
+    fn test_full_tree_is_left_unchanged() {
+        let mut buffer = create_aligned_buffer(CritbitTree::required_size(MapType::Tiny));
+        let mut tree = CritbitTree::new(&mut buffer, 0, MapType::Tiny).unwrap();
+        // 32 keys take 63 slots, the 33rd needs two
+        for key in 0..32 {
+            tree.insert(key * 3, key).unwrap();
+        }
+        assert!(matches!(
+            tree.insert(1_000, 0),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert_eq!(tree.len(), 32);
+        assert_eq!(tree.map().used_count().unwrap(), 63);
+        assert_eq!(tree.get(1_000), None);
+        // Existing keys can still be updated in place
+        assert_eq!(tree.insert(93, 7).unwrap(), Some(31));
+    }
+
+    #[test]
+    fn test_new_checks_size() {
+        let mut buffer = vec![0u8; CritbitTree::required_size(MapType::Tiny) - 1];
+        assert!(matches!(
+            CritbitTree::new(&mut buffer, 0, MapType::Tiny),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap};
+use core::fmt;
+
+/// Allocated indices listed by `Debug` before the list is cut short
+const DEBUG_PREVIEW: usize = 8;
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    fn debug_fields(&self, out: &mut fmt::DebugStruct<'_, '_>) {
+        out.field("capacity", &Self::CAPACITY);
+        match self.used_count() {
+            Ok(used) => out
+                .field("used", &used)
+                .field("free", &(Self::CAPACITY - used)),
+            Err(err) => out.field("used", &err),
+        };
+        out.field("allocated", &AllocatedPreview(self));
+    }
+}
+
+/// First allocated indices, ending in `..` when there are more
+struct AllocatedPreview<'m, const L1_BITS: usize, const LEVELS: usize, B>(
+    &'m HierMemoryMap<L1_BITS, LEVELS, B>,
+);
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> fmt::Debug
+    for AllocatedPreview<'_, L1_BITS, LEVELS, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        let mut shown = 0;
+        for block in 0..HierMemoryMap::<L1_BITS, LEVELS, B>::LEAF_WORDS {
+            let Ok(leaf) = self.0.leaf_word(block) else {
+                break;
+            };
+            let mut leaf = leaf & HierMemoryMap::<L1_BITS, LEVELS, B>::LEAF_MASK;
+            while leaf != 0 {
+                if shown == DEBUG_PREVIEW {
+                    return list.finish_non_exhaustive();
+                }
+                list.entry(&((block << 6) + leaf.trailing_zeros() as usize));
+                leaf &= leaf - 1;
+                shown += 1;
+            }
+        }
+        list.finish()
+    }
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> fmt::Debug
+    for HierMemoryMap<L1_BITS, LEVELS, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = f.debug_struct("HierMemoryMap");
+        self.debug_fields(&mut out);
+        out.finish()
+    }
+}
+
+impl fmt::Debug for MemoryMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = f.debug_struct("MemoryMap");
+        out.field("map_type", &self.map_type());
+        match self {
+            Self::Max(map) => map.debug_fields(&mut out),
+            Self::Standard(map) => map.debug_fields(&mut out),
+            Self::Small(map) => map.debug_fields(&mut out),
+            Self::Huge(map) => map.debug_fields(&mut out),
+            Self::Tiny(map) => map.debug_fields(&mut out),
+            Self::Medium(map) => map.debug_fields(&mut out),
+        }
+        out.finish()
+    }
+}
+
+impl MemoryMap {
+    /// Leaf bitmap as rows of `width` characters, `#` for an allocated slot
+    /// and `.` for a free one
+    ///
+    /// Rows end with a newline; a width of 0 is treated as 1. Slots whose
+    /// leaf word cannot be read render as `?`.
+    #[cfg(feature = "std")]
+    pub fn dump_occupancy(&self, width: usize) -> String {
+        let width = width.max(1);
+        let bits = self.to_bitvec().unwrap_or_default();
+        let capacity = self.capacity();
+        let mut out = String::with_capacity(capacity + capacity.div_ceil(width));
+        for index in 0..capacity {
+            out.push(match bits.get(index >> 3) {
+                Some(byte) if byte & (1 << (index & 7)) != 0 => '#',
+                Some(_) => '.',
+                None => '?',
+            });
+            if (index + 1) % width == 0 || index + 1 == capacity {
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, SmallMemoryMap};
+
+    #[test]
+    fn test_debug_summary() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(
+            format!("{map:?}"),
+            "MemoryMap { map_type: Small, capacity: 4096, used: 0, free: 4096, allocated: [] }"
+        );
+
+        for _ in 0..3 {
+            map.alloc().unwrap();
+        }
+        map.alloc_near(2).unwrap();
+        assert_eq!(
+            format!("{map:?}"),
+            "MemoryMap { map_type: Small, capacity: 4096, used: 4, free: 4092, \
+             allocated: [0, 1, 2, 128] }"
+        );
+
+        for _ in 0..10 {
+            map.alloc().unwrap();
+        }
+        let inner = SmallMemoryMap::new_from_slice(&mut buffer, 0).unwrap();
+        assert_eq!(
+            format!("{inner:?}"),
+            "HierMemoryMap { capacity: 4096, used: 14, free: 4082, \
+             allocated: [0, 1, 2, 3, 4, 5, 6, 7, ..] }"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dump_occupancy() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        for _ in 0..20 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(2).unwrap();
+        map.dealloc(17).unwrap();
+
+        let dump = map.dump_occupancy(16);
+        assert_eq!(
+            dump,
+            "##.#############\n\
+             #.##............\n\
+             ................\n\
+             ................\n"
+        );
+        assert_eq!(map.dump_occupancy(48).lines().count(), 2);
+        assert_eq!(map.dump_occupancy(0).lines().count(), 64);
+    }
+}
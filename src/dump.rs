@@ -0,0 +1,150 @@
+//! JSON dump of a map for support tooling
+
+use crate::{MemoryMap, MemoryMapError};
+use serde_json::{json, Value};
+
+impl MemoryMap {
+    /// Structured JSON description of the map
+    ///
+    /// Holds the header fields, the words of every level in hex, the derived
+    /// [`stats`](MemoryMap::stats) and any inconsistency found between the
+    /// levels or with the header counter.
+    pub fn dump_json(&self) -> Result<String, MemoryMapError> {
+        let map_type = self.map_type();
+        let mut levels = Vec::new();
+        for level in 0..map_type.levels() {
+            let words = (map_type.level_start(level)..map_type.level_start(level + 1))
+                .map(|index| Ok(format!("{:#018x}", self.word_at(index)?)))
+                .collect::<Result<Vec<_>, MemoryMapError>>()?;
+            levels.push(json!({ "level": level, "words": words }));
+        }
+
+        let stats = self.stats()?;
+        let mut inconsistencies = self.summary_mismatches()?;
+        let header = match self.header() {
+            Some(header) => {
+                if header.used_count != stats.used as u64 {
+                    inconsistencies.push(json!({
+                        "kind": "used_count",
+                        "header": header.used_count,
+                        "bitmap": stats.used,
+                    }));
+                }
+                json!({
+                    "magic": format!("{:#010x}", header.magic),
+                    "version": header.version,
+                    "map_type": header.map_type,
+                    "flags": header.flags,
+                    "first_free_block": header.first_free_block,
+                    "data_len": header.data_len,
+                    "leaf_hint": header.leaf_hint,
+                    "seal_hash": format!("{:#018x}", header.seal_hash),
+                    "used_count": header.used_count,
+                })
+            }
+            None => Value::Null,
+        };
+
+        let dump = json!({
+            "map_type": format!("{map_type:?}"),
+            "capacity": map_type.capacity(),
+            "header": header,
+            "levels": levels,
+            "stats": {
+                "used": stats.used,
+                "free": stats.free,
+                "partial_leaf_words": stats.partial_leaf_words,
+                "free_leaf_words": stats.free_leaf_words,
+                "largest_free_run": stats.largest_free_run,
+                "high_water_mark": stats.high_water_mark,
+            },
+            "inconsistencies": inconsistencies,
+        });
+        serde_json::to_string_pretty(&dump).map_err(|_| MemoryMapError::InvalidHeader)
+    }
+
+    /// Summary bits that disagree with the fullness of the word below them
+    fn summary_mismatches(&self) -> Result<Vec<Value>, MemoryMapError> {
+        let map_type = self.map_type();
+        let mut mismatches = Vec::new();
+        for level in 0..map_type.levels() - 1 {
+            let bits = if level == 0 {
+                map_type.first_level_bits()
+            } else {
+                64 * (map_type.level_start(level + 1) - map_type.level_start(level))
+            };
+            for position in 0..bits {
+                let word = map_type.level_start(level) + (position >> 6);
+                let set = self.word_at(word)? & (1 << (position & 0x3f)) != 0;
+                let full = self.word_at(map_type.level_start(level + 1) + position)? == u64::MAX;
+                if set != full {
+                    mismatches.push(json!({
+                        "kind": "summary_bit",
+                        "level": level,
+                        "word": word,
+                        "bit": position & 0x3f,
+                        "set": set,
+                    }));
+                }
+            }
+        }
+        Ok(mismatches)
+    }
+
+    fn word_at(&self, index: usize) -> Result<u64, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.word_at(index),
+            Self::Standard(map) => map.word_at(index),
+            Self::Small(map) => map.word_at(index),
+            Self::Huge(map) => map.word_at(index),
+            Self::Tiny(map) => map.word_at(index),
+            Self::Medium(map) => map.word_at(index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, header, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_dump_json() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer[..size], 0, MapType::Small).unwrap();
+        for _ in 0..64 {
+            map.alloc().unwrap();
+        }
+
+        let dump: Value = serde_json::from_str(&map.dump_json().unwrap()).unwrap();
+        assert_eq!(dump["map_type"], "Small");
+        assert_eq!(dump["header"]["used_count"], 64);
+        assert_eq!(dump["levels"][0]["words"][0], "0x0000000000000001");
+        assert_eq!(dump["levels"][1]["words"][0], "0xffffffffffffffff");
+        assert_eq!(dump["stats"]["used"], 64);
+        assert_eq!(dump["inconsistencies"], json!([]));
+
+        // Clear a leaf bit behind the map's back and skew the counter
+        let leaf = HEADER_SIZE + MapType::Small.level_start(1) * 8;
+        buffer[leaf] = 0xfe;
+        header::header_mut(&mut buffer, 0).unwrap().used_count = 70;
+        let map = MemoryMap::load(&mut buffer[..size], 0, MapType::Small).unwrap();
+        let dump: Value = serde_json::from_str(&map.dump_json().unwrap()).unwrap();
+        let found = dump["inconsistencies"].as_array().unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0]["kind"], "summary_bit");
+        assert_eq!(found[0]["set"], true);
+        assert_eq!(found[1]["kind"], "used_count");
+        assert_eq!(found[1]["bitmap"], 63);
+    }
+
+    #[test]
+    fn test_dump_without_header() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let dump: Value = serde_json::from_str(&map.dump_json().unwrap()).unwrap();
+        assert_eq!(dump["header"], Value::Null);
+        assert_eq!(dump["levels"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,452 @@
+use crate::{
+    backend::{Backend, RawBackend},
+    get_first_zero_bit::get_first_zero_bit,
+    header::{self, DYNAMIC_MAP_TYPE},
+    slice_memory, MapHeader, MemoryMapError, HEADER_SIZE,
+};
+use core::mem::{align_of, size_of};
+
+/// Maximum number of levels of a dynamic map (enough for any account size)
+const MAX_LEVELS: usize = 8;
+
+/// Largest leaf word count whose hierarchy fits in [`MAX_LEVELS`] levels
+const MAX_LEAF_WORDS: u64 = 1 << (6 * (MAX_LEVELS - 1));
+
+/// Hierarchical memory map whose leaf count is derived from the account size
+///
+/// The number of leaf words is computed from the space left after the header
+/// at init and recorded in the header, so one code path serves accounts of any
+/// size. Leaf words come first, followed by the summary levels from the one
+/// right above the leaves up to the single top word. Bits past the end of a
+/// level are marked set at init, so a summary word is full exactly when every
+/// word below it is.
+pub struct DynamicMemoryMap {
+    backend: RawBackend,
+    leaf_words: usize,
+    levels: usize,
+    /// Word offset of each level, top level first
+    level_starts: [usize; MAX_LEVELS],
+    /// Number of used bits in each level, top level first
+    level_bits: [usize; MAX_LEVELS],
+}
+
+impl DynamicMemoryMap {
+    /// Number of bitmap bytes needed for `leaf_words` leaf words
+    ///
+    /// Saturates at `usize::MAX`, which no account can hold.
+    pub const fn required_size(leaf_words: usize) -> usize {
+        match Self::checked_required_size(leaf_words) {
+            Some(size) => size,
+            None => usize::MAX,
+        }
+    }
+
+    const fn checked_required_size(leaf_words: usize) -> Option<usize> {
+        let mut words = leaf_words;
+        let mut total = leaf_words;
+        while words > 1 {
+            words = words.div_ceil(64);
+            total = match total.checked_add(words) {
+                Some(total) => total,
+                None => return None,
+            };
+        }
+        total.checked_mul(size_of::<u64>())
+    }
+
+    /// Largest number of leaf words whose bitmap fits in `size` bytes
+    pub const fn leaf_words_for(size: usize) -> usize {
+        // Summary levels take a bit more than 1/64 of the leaves
+        let mut leaf_words = size / size_of::<u64>() * 64 / 65;
+        while Self::required_size(leaf_words + 1) <= size {
+            leaf_words += 1;
+        }
+        while leaf_words > 0 && Self::required_size(leaf_words) > size {
+            leaf_words -= 1;
+        }
+        leaf_words
+    }
+
+    /// Initialize a dynamic map at `offset`, sized to the rest of `data`
+    ///
+    /// The header is written at `offset` and the bitmap starts at
+    /// `offset + HEADER_SIZE`.
+    pub fn init(data: &mut [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        let bitmap_offset = Self::bitmap_offset(offset)?;
+        let leaf_words = Self::leaf_words_for(data.len().saturating_sub(bitmap_offset));
+        if leaf_words == 0 {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        *header = MapHeader::new_dynamic(leaf_words as u64);
+        header.data_len = data_len as u64;
+        data[bitmap_offset..bitmap_offset + Self::required_size(leaf_words)].fill(0);
+
+        let mut map = Self::new_from_slice(data, bitmap_offset, leaf_words)?;
+        map.mark_padding()?;
+        Ok(map)
+    }
+
+    /// Load a map previously created with [`DynamicMemoryMap::init`]
+    pub fn load(data: &mut [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        header.validate()?;
+        header.check_data_len(data_len)?;
+        if header.map_type != DYNAMIC_MAP_TYPE {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        // Checked before any size is derived from the account's value
+        if header.leaf_words > MAX_LEAF_WORDS {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        let leaf_words =
+            usize::try_from(header.leaf_words).map_err(|_| MemoryMapError::InvalidHeader)?;
+
+        Self::new_from_slice(data, Self::bitmap_offset(offset)?, leaf_words)
+    }
+
+    /// Offset of the bitmap right after a header at `offset`
+    fn bitmap_offset(offset: usize) -> Result<usize, MemoryMapError> {
+        offset
+            .checked_add(HEADER_SIZE)
+            .ok_or(MemoryMapError::InvalidOffset)
+    }
+
+    fn new_from_slice(
+        data: &mut [u8],
+        offset: usize,
+        leaf_words: usize,
+    ) -> Result<Self, MemoryMapError> {
+        if leaf_words == 0 || leaf_words as u64 > MAX_LEAF_WORDS {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        let required_size =
+            Self::checked_required_size(leaf_words).ok_or(MemoryMapError::InvalidHeader)?;
+        let (memory, size) = slice_memory(data, offset, align_of::<u64>())?;
+        if size < required_size {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        // Walk up from the leaves, then flip so the top level comes first
+        let mut level_starts = [0; MAX_LEVELS];
+        let mut level_bits = [0; MAX_LEVELS];
+        let mut levels = 0;
+        let mut start = 0;
+        let mut bits = leaf_words
+            .checked_mul(64)
+            .ok_or(MemoryMapError::InvalidHeader)?;
+        loop {
+            let words = bits.div_ceil(64);
+            level_starts[levels] = start;
+            level_bits[levels] = bits;
+            levels += 1;
+            if words == 1 {
+                break;
+            }
+            start += words;
+            bits = words;
+        }
+        level_starts[..levels].reverse();
+        level_bits[..levels].reverse();
+
+        Ok(Self {
+            // Safety: `memory` points into `data`, which has `size` bytes left
+            backend: unsafe { RawBackend::new(memory, size) },
+            leaf_words,
+            levels,
+            level_starts,
+            level_bits,
+        })
+    }
+
+    /// Take in the space added to the account by a `realloc`
+    ///
+    /// `data` and `offset` must be the ones the map was loaded from, now
+    /// longer. Leaf words come first in the bitmap, so the new ones are added
+    /// after them and existing indices keep their slots; the summary levels
+    /// are rebuilt after the new leaves. Returns the new capacity.
+    pub fn grow(&mut self, data: &mut [u8], offset: usize) -> Result<usize, MemoryMapError> {
+        let bitmap_offset = Self::bitmap_offset(offset)?;
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        header.validate()?;
+        if header.map_type != DYNAMIC_MAP_TYPE {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        if header.data_len > data_len as u64 {
+            return Err(MemoryMapError::LengthMismatch);
+        }
+        if data[bitmap_offset..].as_ptr() != self.backend.memory().as_ptr().cast_const() {
+            return Err(MemoryMapError::InvalidOffset);
+        }
+
+        let leaf_words = Self::leaf_words_for(data_len - bitmap_offset);
+        if leaf_words > self.leaf_words {
+            // The old summary levels sit where the new leaves go
+            let start = bitmap_offset + self.leaf_words * size_of::<u64>();
+            data[start..bitmap_offset + Self::required_size(leaf_words)].fill(0);
+            *self = Self::new_from_slice(data, bitmap_offset, leaf_words)?;
+            self.rebuild_summaries()?;
+        }
+
+        let header = header::header_mut(data, offset)?;
+        header.leaf_words = self.leaf_words as u64;
+        header.data_len = data_len as u64;
+        Ok(self.capacity())
+    }
+
+    /// Number of leaf words recorded at init
+    pub const fn leaf_words(&self) -> usize {
+        self.leaf_words
+    }
+
+    /// Number of indices the map can hand out
+    pub const fn capacity(&self) -> usize {
+        self.leaf_words * 64
+    }
+
+    /// Check whether every slot is allocated
+    pub fn is_full(&self) -> bool {
+        matches!(self.backend.read_word(self.level_starts[0]), Ok(u64::MAX))
+    }
+
+    /// Allocate a new slot
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        if self.is_full() {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+
+        // Descend to the leaf level following the first zero bit of each word
+        let mut index = 0;
+        for level in 0..self.levels {
+            let word = self.backend.read_word(self.level_starts[level] + index)?;
+            index = (index << 6) + get_first_zero_bit(word, 64)?;
+        }
+
+        self.mark_allocated(index)?;
+
+        Ok(index)
+    }
+
+    /// Deallocate a previously allocated slot
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        if index >= self.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+
+        // Clear the allocation bit and the full bits on the path above it
+        let mut position = index;
+        for level in (0..self.levels).rev() {
+            let word = self.level_starts[level] + (position >> 6);
+            let value = self.backend.read_word(word)? & !(1 << (position & 0x3f));
+            self.backend.write_word(word, value)?;
+            position >>= 6;
+        }
+
+        Ok(())
+    }
+
+    /// Set the bits past the end of every level so they never look free
+    fn mark_padding(&mut self) -> Result<(), MemoryMapError> {
+        for level in 0..self.levels {
+            let bits = self.level_bits[level];
+            if !bits.is_multiple_of(64) {
+                let word = self.level_starts[level] + bits / 64;
+                let value = self.backend.read_word(word)? | (u64::MAX << (bits % 64));
+                self.backend.write_word(word, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Recompute every summary level from the one below it, padding included
+    fn rebuild_summaries(&mut self) -> Result<(), MemoryMapError> {
+        for level in (0..self.levels - 1).rev() {
+            let bits = self.level_bits[level];
+            for word in 0..bits.div_ceil(64) {
+                let mut value = 0;
+                for bit in 0..64 {
+                    let position = (word << 6) + bit;
+                    let full = position >= bits
+                        || self
+                            .backend
+                            .read_word(self.level_starts[level + 1] + position)?
+                            == u64::MAX;
+                    value |= u64::from(full) << bit;
+                }
+                self.backend
+                    .write_word(self.level_starts[level] + word, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the leaf bit of `index` and propagate full words upwards
+    fn mark_allocated(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        let mut position = index;
+        for level in (0..self.levels).rev() {
+            let word = self.level_starts[level] + (position >> 6);
+            let value = self.backend.read_word(word)? | (1 << (position & 0x3f));
+            self.backend.write_word(word, value)?;
+            if value != u64::MAX {
+                break;
+            }
+            position >>= 6;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, migrate, MapType, MemoryMap};
+
+    #[test]
+    fn test_geometry() {
+        assert_eq!(DynamicMemoryMap::required_size(1), 8);
+        assert_eq!(DynamicMemoryMap::required_size(64), 65 * 8);
+        assert_eq!(DynamicMemoryMap::required_size(100), (100 + 2 + 1) * 8);
+        assert_eq!(DynamicMemoryMap::leaf_words_for(7), 0);
+        assert_eq!(DynamicMemoryMap::leaf_words_for(16), 1);
+        assert_eq!(DynamicMemoryMap::leaf_words_for(65 * 8), 64);
+        assert_eq!(DynamicMemoryMap::leaf_words_for(103 * 8), 100);
+        assert_eq!(DynamicMemoryMap::leaf_words_for(104 * 8), 101);
+    }
+
+    #[test]
+    fn test_fill_and_reuse() {
+        // 100 leaf words: three levels with partially used summary words
+        let size = HEADER_SIZE + DynamicMemoryMap::required_size(100);
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = DynamicMemoryMap::init(&mut buffer[..size], 0).unwrap();
+        assert_eq!(map.leaf_words(), 100);
+        assert_eq!(map.capacity(), 6_400);
+
+        for expected in 0..6_400 {
+            assert!(!map.is_full());
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(6_399).unwrap();
+        map.dealloc(70).unwrap();
+        assert_eq!(map.alloc().unwrap(), 70);
+        assert_eq!(map.alloc().unwrap(), 6_399);
+        assert!(matches!(
+            map.dealloc(6_400),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_load_keeps_recorded_size() {
+        let size = HEADER_SIZE + DynamicMemoryMap::required_size(3);
+        let mut buffer = create_aligned_buffer(size + 1_000);
+        let mut map = DynamicMemoryMap::init(&mut buffer[..size], 0).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+
+        // The account got larger, once migrated the recorded geometry still applies
+        assert!(matches!(
+            DynamicMemoryMap::load(&mut buffer, 0),
+            Err(MemoryMapError::LengthMismatch)
+        ));
+        migrate(&mut buffer, 0).unwrap();
+        let mut map = DynamicMemoryMap::load(&mut buffer, 0).unwrap();
+        assert_eq!(map.capacity(), 192);
+        assert_eq!(map.alloc().unwrap(), 1);
+
+        // Fixed-size loads refuse dynamic maps and vice versa
+        assert!(matches!(
+            MemoryMap::load_auto(&mut buffer, 0),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+        MemoryMap::init(&mut buffer, 0, MapType::Tiny).unwrap();
+        assert!(matches!(
+            DynamicMemoryMap::load(&mut buffer, 0),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+    }
+
+    #[test]
+    fn test_load_rejects_oversized_leaf_words() {
+        let size = HEADER_SIZE + DynamicMemoryMap::required_size(3);
+        let mut buffer = create_aligned_buffer(size);
+        DynamicMemoryMap::init(&mut buffer, 0).unwrap();
+
+        for leaf_words in [u64::MAX, MAX_LEAF_WORDS + 1, u64::MAX / 8] {
+            header::header_mut(&mut buffer, 0).unwrap().leaf_words = leaf_words;
+            assert!(matches!(
+                DynamicMemoryMap::load(&mut buffer, 0),
+                Err(MemoryMapError::InvalidHeader)
+            ));
+        }
+        // Within the level limit but larger than the account
+        header::header_mut(&mut buffer, 0).unwrap().leaf_words = MAX_LEAF_WORDS;
+        assert!(matches!(
+            DynamicMemoryMap::load(&mut buffer, 0),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        assert_eq!(DynamicMemoryMap::required_size(usize::MAX), usize::MAX);
+        assert!(matches!(
+            DynamicMemoryMap::init(&mut buffer, usize::MAX),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+        assert!(matches!(
+            DynamicMemoryMap::load(&mut buffer, usize::MAX),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+    }
+
+    #[test]
+    fn test_grow_after_realloc() {
+        let size = HEADER_SIZE + DynamicMemoryMap::required_size(70);
+        let grown = HEADER_SIZE + DynamicMemoryMap::required_size(200);
+        let mut buffer = create_aligned_buffer(grown);
+        let mut map = DynamicMemoryMap::init(&mut buffer[..size], 0).unwrap();
+        for _ in 0..4_480 {
+            map.alloc().unwrap();
+        }
+        assert!(map.is_full());
+        map.dealloc(1_000).unwrap();
+
+        assert!(matches!(
+            map.grow(&mut buffer[8..grown], 0),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert_eq!(map.grow(&mut buffer[..grown], 0).unwrap(), 12_800);
+        assert_eq!(map.leaf_words(), 200);
+        // Old indices keep their slots, the freed one comes first
+        assert_eq!(map.alloc().unwrap(), 1_000);
+        for expected in 4_480..12_800 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+
+        // Recorded in the header
+        let map = DynamicMemoryMap::load(&mut buffer[..grown], 0).unwrap();
+        assert_eq!(map.capacity(), 12_800);
+        assert!(map.is_full());
+    }
+
+    #[test]
+    fn test_single_leaf_word() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + 8);
+        let mut map = DynamicMemoryMap::init(&mut buffer[..HEADER_SIZE + 8], 0).unwrap();
+        for expected in 0..64 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+
+        assert!(matches!(
+            DynamicMemoryMap::init(&mut buffer[..HEADER_SIZE + 7], 0),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{MemoryMap, MemoryMapError};
+
+/// State transitions of the leaf word touched by one alloc or dealloc
+///
+/// Lets programs emit events exactly when a price level fills or empties
+/// without reading the words again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocEffects {
+    /// Leaf word (block of 64 slots) holding the slot
+    pub block: usize,
+    /// The slot was the first allocated one of its block
+    pub block_became_nonempty: bool,
+    /// The slot was the last free one of its block
+    pub block_became_full: bool,
+    /// The slot was the last allocated one of its block
+    pub block_became_empty: bool,
+    /// The block was full before the slot was freed
+    pub block_became_nonfull: bool,
+    /// No slot is left anywhere in the map
+    pub map_became_full: bool,
+}
+
+impl MemoryMap {
+    /// Allocate a new slot and report the transitions it caused
+    pub fn alloc_with_effects(&mut self) -> Result<(usize, AllocEffects), MemoryMapError> {
+        let index = self.alloc()?;
+        let block = index >> 6;
+        let after = self.leaf_word(block)?;
+        let before = after & !(1 << (index & 0x3f));
+        Ok((
+            index,
+            AllocEffects {
+                block,
+                block_became_nonempty: before == 0,
+                block_became_full: after == u64::MAX,
+                map_became_full: self.is_full(),
+                ..AllocEffects::default()
+            },
+        ))
+    }
+
+    /// Deallocate slot `index` and report the transitions it caused
+    ///
+    /// Freeing a free slot reports no transition.
+    pub fn dealloc_with_effects(&mut self, index: usize) -> Result<AllocEffects, MemoryMapError> {
+        let block = index >> 6;
+        let before = if self.is_allocated(index)? {
+            self.leaf_word(block)?
+        } else {
+            0
+        };
+        self.dealloc(index)?;
+        let after = before & !(1 << (index & 0x3f));
+        Ok(AllocEffects {
+            block,
+            block_became_empty: before != 0 && after == 0,
+            block_became_nonfull: before == u64::MAX,
+            ..AllocEffects::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_block_transitions() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+
+        let (index, effects) = map.alloc_with_effects().unwrap();
+        assert_eq!(index, 0);
+        assert!(effects.block_became_nonempty && !effects.block_became_full);
+        for _ in 1..63 {
+            let (_, effects) = map.alloc_with_effects().unwrap();
+            assert_eq!(effects, AllocEffects::default());
+        }
+        let (_, effects) = map.alloc_with_effects().unwrap();
+        assert!(effects.block_became_full && !effects.map_became_full);
+
+        let (index, effects) = map.alloc_with_effects().unwrap();
+        assert_eq!(index, 64);
+        assert_eq!(effects.block, 1);
+        assert!(effects.block_became_nonempty);
+
+        let effects = map.dealloc_with_effects(5).unwrap();
+        assert!(effects.block_became_nonfull && !effects.block_became_empty);
+        let effects = map.dealloc_with_effects(64).unwrap();
+        assert!(effects.block_became_empty && !effects.block_became_nonfull);
+        let effects = map.dealloc_with_effects(64).unwrap();
+        assert_eq!(effects.block, 1);
+        assert!(!effects.block_became_empty);
+    }
+
+    #[test]
+    fn test_map_became_full() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        for _ in 0..63 {
+            map.alloc().unwrap();
+        }
+        let (_, effects) = map.alloc_with_effects().unwrap();
+        assert!(effects.block_became_full && effects.map_became_full);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    backend::Backend,
+    bytes::{read_u32, write_u32},
+    header::FLAG_FROZEN,
+    hier_memory_map::HierMemoryMap,
+    trace::OpSpan,
+    MapType, MemoryMap, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Size of the release tag stored for each slot
+const TAG_SIZE: usize = size_of::<u32>();
+
+/// Persisted release epoch of every slot, for grace periods on reuse
+///
+/// Tags hold the low 32 bits of the epoch plus one, so an all-zero region
+/// has no slot released in any epoch.
+pub struct EpochTags<'a> {
+    data: &'a mut [u8],
+    map_type: MapType,
+}
+
+impl<'a> EpochTags<'a> {
+    /// Number of bytes needed to tag a map of type `map_type`
+    pub const fn size(map_type: MapType) -> usize {
+        map_type.capacity() * TAG_SIZE
+    }
+
+    /// Wrap a region previously zeroed or written by this extension
+    pub fn new(data: &'a mut [u8], map_type: MapType) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::size(map_type) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data, map_type })
+    }
+
+    /// Check whether slot `index` was released in `epoch`
+    pub fn released_in(&self, index: usize, epoch: u64) -> bool {
+        index < self.map_type.capacity() && self.tag(index) == Self::encode(epoch)
+    }
+
+    const fn encode(epoch: u64) -> u32 {
+        (epoch as u32).wrapping_add(1)
+    }
+
+    fn tag(&self, index: usize) -> u32 {
+        read_u32(self.data, index * TAG_SIZE)
+    }
+
+    fn set_tag(&mut self, index: usize, epoch: u64) {
+        write_u32(self.data, index * TAG_SIZE, Self::encode(epoch));
+    }
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Allocate the lowest free slot for which `skip` is false
+    ///
+    /// Walks the free slots in order, so every skipped slot costs one search.
+    pub(crate) fn alloc_skipping(
+        &mut self,
+        skip: impl Fn(usize) -> bool,
+    ) -> Result<usize, MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        let reserved = self.reserved_range();
+        let mut floor = 0;
+        while floor < Self::CAPACITY {
+            let Some(mut index) = self.find_free_above(floor)? else {
+                break;
+            };
+            if let Some(reserved) = &reserved {
+                index = self.skip_reserved(index, reserved)?;
+            }
+            if !skip(index) {
+                self.mark_allocated(index)?;
+                return Ok(index);
+            }
+            floor = index + 1;
+        }
+
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+}
+
+impl MemoryMap {
+    /// Current recycling epoch recorded in the header
+    pub fn epoch(&self) -> Result<u64, MemoryMapError> {
+        self.header()
+            .map(|header| header.epoch)
+            .ok_or(MemoryMapError::InvalidHeader)
+    }
+
+    /// Start a new epoch and return it; slots released before become reusable
+    pub fn advance_epoch(&mut self) -> Result<u64, MemoryMapError> {
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        header.epoch += 1;
+        Ok(header.epoch)
+    }
+
+    /// Deallocate slot `index` and tag it with the current epoch
+    pub fn dealloc_tagged(
+        &mut self,
+        tags: &mut EpochTags,
+        index: usize,
+    ) -> Result<(), MemoryMapError> {
+        if tags.map_type != self.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        let epoch = self.epoch()?;
+        self.dealloc(index)?;
+        tags.set_tag(index, epoch);
+        Ok(())
+    }
+
+    /// Allocate a slot, skipping those released in the current epoch
+    ///
+    /// Gives clients a grace period in which a just-cancelled order index is
+    /// not handed out again. Fails with `NoAvailableSlots` if only such slots
+    /// are left.
+    ///
+    /// Every skipped slot costs a search from the slot after it, and nothing
+    /// bounds how many are skipped: after a mass cancel in one epoch a call
+    /// may walk all of them. Keep the slots released per epoch small or
+    /// advance the epoch often when compute is tight.
+    pub fn alloc_tagged(&mut self, tags: &EpochTags) -> Result<usize, MemoryMapError> {
+        if tags.map_type != self.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        let epoch = self.epoch()?;
+        let skip = |index| tags.released_in(index, epoch);
+        let span = OpSpan::enter("alloc_tagged", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.alloc_skipping(skip),
+            Self::Standard(map) => map.alloc_skipping(skip),
+            Self::Small(map) => map.alloc_skipping(skip),
+            Self::Huge(map) => map.alloc_skipping(skip),
+            Self::Tiny(map) => map.alloc_skipping(skip),
+            Self::Medium(map) => map.alloc_skipping(skip),
+        };
+        if let Ok(index) = result {
+            span.record_index(index);
+        }
+        span.finish(&result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, HEADER_SIZE};
+
+    #[test]
+    fn test_released_slots_wait_for_next_epoch() {
+        let size = HEADER_SIZE + MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; EpochTags::size(MapType::Tiny)];
+        let mut tags = EpochTags::new(&mut region, MapType::Tiny).unwrap();
+        for expected in 0..4 {
+            assert_eq!(map.alloc_tagged(&tags).unwrap(), expected);
+        }
+
+        map.dealloc_tagged(&mut tags, 1).unwrap();
+        map.dealloc_tagged(&mut tags, 2).unwrap();
+        assert!(tags.released_in(1, 0));
+        assert_eq!(map.alloc_tagged(&tags).unwrap(), 4);
+
+        // A new epoch ends the grace period
+        assert_eq!(map.advance_epoch().unwrap(), 1);
+        assert_eq!(map.alloc_tagged(&tags).unwrap(), 1);
+        map.dealloc_tagged(&mut tags, 0).unwrap();
+        assert_eq!(map.alloc_tagged(&tags).unwrap(), 2);
+        assert_eq!(map.alloc_tagged(&tags).unwrap(), 5);
+
+        // The epoch survives a reload
+        let map = MemoryMap::load(&mut buffer, 0, MapType::Tiny).unwrap();
+        assert_eq!(map.epoch().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_only_recent_slots_left() {
+        let size = HEADER_SIZE + MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; EpochTags::size(MapType::Tiny)];
+        let mut tags = EpochTags::new(&mut region, MapType::Tiny).unwrap();
+        while map.alloc().is_ok() {}
+        map.dealloc_tagged(&mut tags, 63).unwrap();
+
+        assert!(matches!(
+            map.alloc_tagged(&tags),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        map.advance_epoch().unwrap();
+        assert_eq!(map.alloc_tagged(&tags).unwrap(), 63);
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert!(matches!(
+            map.advance_epoch(),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Occupancy as packed bytes, LSB-first, exactly `CAPACITY` bits long
+    ///
+    /// Only the leaf level is exported, the summary words are left out.
+    #[cfg(feature = "std")]
+    pub fn to_bitvec(&self) -> Result<Vec<u8>, MemoryMapError> {
+        let mut bits = Vec::with_capacity(Self::LEAF_WORDS * 8);
+        for block in 0..Self::LEAF_WORDS {
+            bits.extend_from_slice(&(self.leaf_word(block)? & Self::LEAF_MASK).to_le_bytes());
+        }
+        bits.truncate(Self::CAPACITY.div_ceil(8));
+        Ok(bits)
+    }
+}
+
+impl MemoryMap {
+    /// Copy of the bitmap bytes starting at `cursor`, at most `max_bytes` long
+    ///
+    /// Returns the chunk and the cursor of the next chunk, or `None` once the
+    /// whole bitmap has been exported. Start with cursor 0 and concatenate the
+    /// chunks to get the full occupancy, one bounded call at a time.
+    pub fn export_chunk(
+        &self,
+        cursor: usize,
+        max_bytes: usize,
+    ) -> Result<(&[u8], Option<usize>), MemoryMapError> {
+        let bitmap = self.as_bytes();
+        if cursor > bitmap.len() {
+            return Err(MemoryMapError::InvalidOffset);
+        }
+        if max_bytes == 0 {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        let end = bitmap.len().min(cursor.saturating_add(max_bytes));
+        let next = (end < bitmap.len()).then_some(end);
+
+        Ok((&bitmap[cursor..end], next))
+    }
+
+    /// Occupancy as packed bytes, LSB-first, exactly `capacity()` bits long
+    ///
+    /// Bit `i % 8` of byte `i / 8` is set when slot `i` is allocated, so
+    /// analytics tools can read it without knowing the hierarchical layout.
+    #[cfg(feature = "std")]
+    pub fn to_bitvec(&self) -> Result<Vec<u8>, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.to_bitvec(),
+            Self::Standard(map) => map.to_bitvec(),
+            Self::Small(map) => map.to_bitvec(),
+            Self::Huge(map) => map.to_bitvec(),
+            Self::Tiny(map) => map.to_bitvec(),
+            Self::Medium(map) => map.to_bitvec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_export_in_chunks() {
+        let mut buffer = create_aligned_buffer(MapType::Max.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        for _ in 0..5_000 {
+            map.alloc().unwrap();
+        }
+
+        let mut exported = Vec::new();
+        let mut cursor = Some(0);
+        let mut calls = 0;
+        while let Some(at) = cursor {
+            let (chunk, next) = map.export_chunk(at, 1_000).unwrap();
+            assert!(chunk.len() <= 1_000);
+            exported.extend_from_slice(chunk);
+            cursor = next;
+            calls += 1;
+        }
+
+        assert_eq!(calls, MapType::Max.required_size().div_ceil(1_000));
+        assert_eq!(exported, &buffer[..MapType::Max.required_size()]);
+    }
+
+    #[test]
+    fn test_export_bounds() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+
+        assert_eq!(map.export_chunk(0, 100).unwrap(), (&[0u8; 8][..], None));
+        assert_eq!(map.export_chunk(8, 100).unwrap().0.len(), 0);
+        assert!(matches!(
+            map.export_chunk(9, 100),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+        assert!(matches!(
+            map.export_chunk(0, 0),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_to_bitvec() {
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Standard).unwrap();
+        for _ in 0..10 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(3).unwrap();
+        map.alloc_near(100).unwrap();
+
+        let bits = map.to_bitvec().unwrap();
+        assert_eq!(bits.len(), MapType::Standard.capacity() / 8);
+        assert_eq!(bits[0], 0b1111_0111);
+        assert_eq!(bits[1], 0b0000_0011);
+        // Slot 6400 sits in leaf word 100
+        assert_eq!(bits[6_400 / 8], 1);
+        assert_eq!(bits.iter().map(|byte| byte.count_ones()).sum::<u32>(), 10);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_to_bitvec_partial_single_level() {
+        let (mut data, ptr) = crate::create_aligned_memory(8);
+        data.fill(0);
+        let mut map = HierMemoryMap::<5, 1>::new(ptr, 8).unwrap();
+        map.alloc().unwrap();
+        map.alloc().unwrap();
+        assert_eq!(map.to_bitvec().unwrap(), vec![0b11]);
+    }
+}
@@ -0,0 +1,217 @@
+//! C interface for services holding account snapshots in their own memory
+//!
+//! Every function returns [`IMM_OK`] or a negative [`MemoryMapError`] code
+//! and writes its result through an out pointer. The `ffi/` crate builds
+//! these into a static and a shared library, declared for C in
+//! `ffi/include/index_mem_alloc.h`.
+
+use crate::{MapType, MemoryMap, MemoryMapError};
+use core::ptr::NonNull;
+
+/// Status returned on success
+pub const IMM_OK: i32 = 0;
+
+/// Status returned when a required pointer argument is null
+pub const IMM_NULL_ARGUMENT: i32 = -100;
+
+/// Opaque map handle created by [`imm_new`] and released by [`imm_free`]
+pub struct ImmHandle {
+    map: MemoryMap,
+}
+
+/// Negative status code of `err`
+pub const fn imm_error_code(err: MemoryMapError) -> i32 {
+    -(match err {
+        MemoryMapError::InvalidOffset => 1,
+        MemoryMapError::NoAvailableSlots => 2,
+        MemoryMapError::AlignmentError => 3,
+        MemoryMapError::InsufficientMemory => 4,
+        MemoryMapError::InvalidIndex => 5,
+        MemoryMapError::IndexOutOfBounds => 6,
+        MemoryMapError::InvalidMapType => 7,
+        MemoryMapError::NullPointer => 8,
+        MemoryMapError::InvalidHeader => 9,
+        MemoryMapError::UnsupportedVersion => 10,
+        MemoryMapError::LengthMismatch => 11,
+        MemoryMapError::WriteVerificationFailed => 12,
+        MemoryMapError::RegionOverlap => 13,
+        MemoryMapError::TailNotEmpty => 14,
+        MemoryMapError::PartialWord => 15,
+        MemoryMapError::SlotTaken => 16,
+        MemoryMapError::OwnerMismatch => 17,
+        MemoryMapError::SlotPinned => 18,
+        MemoryMapError::ChecksumMismatch => 19,
+        MemoryMapError::DuplicateKey => 20,
+        MemoryMapError::QuotaExceeded => 21,
+        MemoryMapError::MapFrozen => 22,
+    })
+}
+
+/// Attach a `map_type` map to the bitmap at `data + offset`
+///
+/// # Safety
+/// `data` must be valid for reads and writes of `len` bytes until the handle
+/// is freed, and nothing else may write the bitmap while the handle is used.
+#[no_mangle]
+pub unsafe extern "C" fn imm_new(
+    data: *mut u8,
+    len: usize,
+    offset: usize,
+    map_type: u8,
+    out: *mut *mut ImmHandle,
+) -> i32 {
+    if data.is_null() || out.is_null() {
+        return IMM_NULL_ARGUMENT;
+    }
+    // Safety: the caller guarantees `data` spans `len` writable bytes
+    let data = unsafe { core::slice::from_raw_parts_mut(data, len) };
+    let map = MapType::try_from(map_type)
+        .and_then(|map_type| MemoryMap::new_from_slice(data, offset, map_type));
+    match map {
+        Ok(map) => {
+            // Safety: `out` is non-null and points to writable storage
+            unsafe { out.write(Box::into_raw(Box::new(ImmHandle { map }))) };
+            IMM_OK
+        }
+        Err(err) => imm_error_code(err),
+    }
+}
+
+/// Release a handle created by [`imm_new`], null is ignored
+///
+/// # Safety
+/// `handle` must come from [`imm_new`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn imm_free(handle: *mut ImmHandle) {
+    if let Some(handle) = NonNull::new(handle) {
+        // Safety: the handle was boxed by `imm_new` and is freed once
+        drop(unsafe { Box::from_raw(handle.as_ptr()) });
+    }
+}
+
+/// Allocate a slot and write its index to `out`
+///
+/// # Safety
+/// `handle` must be a live handle from [`imm_new`], `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn imm_alloc(handle: *mut ImmHandle, out: *mut usize) -> i32 {
+    if handle.is_null() || out.is_null() {
+        return IMM_NULL_ARGUMENT;
+    }
+    // Safety: checked non-null, liveness is up to the caller
+    match unsafe { (*handle).map.alloc() } {
+        Ok(index) => {
+            // Safety: `out` is non-null and writable
+            unsafe { out.write(index) };
+            IMM_OK
+        }
+        Err(err) => imm_error_code(err),
+    }
+}
+
+/// Free slot `index`
+///
+/// # Safety
+/// `handle` must be a live handle from [`imm_new`].
+#[no_mangle]
+pub unsafe extern "C" fn imm_dealloc(handle: *mut ImmHandle, index: usize) -> i32 {
+    if handle.is_null() {
+        return IMM_NULL_ARGUMENT;
+    }
+    // Safety: checked non-null, liveness is up to the caller
+    match unsafe { (*handle).map.dealloc(index) } {
+        Ok(()) => IMM_OK,
+        Err(err) => imm_error_code(err),
+    }
+}
+
+/// Write whether slot `index` is allocated to `out`
+///
+/// # Safety
+/// `handle` must be a live handle from [`imm_new`], `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn imm_is_allocated(
+    handle: *const ImmHandle,
+    index: usize,
+    out: *mut bool,
+) -> i32 {
+    if handle.is_null() || out.is_null() {
+        return IMM_NULL_ARGUMENT;
+    }
+    // Safety: checked non-null, liveness is up to the caller
+    match unsafe { (*handle).map.is_allocated(index) } {
+        Ok(allocated) => {
+            // Safety: `out` is non-null and writable
+            unsafe { out.write(allocated) };
+            IMM_OK
+        }
+        Err(err) => imm_error_code(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+    use core::ptr;
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut handle = ptr::null_mut();
+        let (mut index, mut allocated) = (usize::MAX, false);
+        unsafe {
+            assert_eq!(
+                imm_new(
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    0,
+                    MapType::Small as u8,
+                    &mut handle
+                ),
+                IMM_OK
+            );
+            assert_eq!(imm_alloc(handle, &mut index), IMM_OK);
+            assert_eq!(index, 0);
+            assert_eq!(imm_is_allocated(handle, 0, &mut allocated), IMM_OK);
+            assert!(allocated);
+            assert_eq!(imm_dealloc(handle, 0), IMM_OK);
+            assert_eq!(imm_is_allocated(handle, 0, &mut allocated), IMM_OK);
+            assert!(!allocated);
+            assert_eq!(
+                imm_is_allocated(handle, 1 << 20, &mut allocated),
+                imm_error_code(MemoryMapError::InvalidIndex)
+            );
+            assert_eq!(imm_alloc(handle, ptr::null_mut()), IMM_NULL_ARGUMENT);
+            imm_free(handle);
+        }
+    }
+
+    #[test]
+    fn test_ffi_new_errors() {
+        let mut buffer = create_aligned_buffer(8);
+        let mut handle = ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                imm_new(buffer.as_mut_ptr(), buffer.len(), 0, 9, &mut handle),
+                imm_error_code(MemoryMapError::InvalidMapType)
+            );
+            assert_eq!(
+                imm_new(
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    0,
+                    MapType::Small as u8,
+                    &mut handle
+                ),
+                imm_error_code(MemoryMapError::InsufficientMemory)
+            );
+            assert_eq!(
+                imm_new(ptr::null_mut(), 0, 0, 0, &mut handle),
+                IMM_NULL_ARGUMENT
+            );
+            assert!(handle.is_null());
+            imm_free(handle);
+        }
+    }
+}
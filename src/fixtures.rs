@@ -0,0 +1,132 @@
+//! Maps in pathological occupancy states, for benches and downstream tests
+//!
+//! Each generator returns an owned [`Fixture`] whose summary levels agree
+//! with its leaf words, as if it had been filled through `alloc`.
+
+use crate::{MapType, MemoryMap, MemoryMapError};
+use std::mem::size_of;
+
+/// Owned, 8-byte aligned bitmap of a map in a given state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    map_type: MapType,
+    words: Vec<u64>,
+}
+
+impl Fixture {
+    /// Build a bitmap whose slot `i` is allocated when `allocated(i)` holds
+    fn from_fn(map_type: MapType, allocated: impl Fn(usize) -> bool) -> Self {
+        let levels = map_type.levels();
+        let leaf_start = map_type.level_start(levels - 1);
+        let mut words = vec![0u64; map_type.required_size() / size_of::<u64>()];
+        for index in (0..map_type.capacity()).filter(|&index| allocated(index)) {
+            words[leaf_start + (index >> 6)] |= 1 << (index & 0x3f);
+        }
+
+        // A bit above the leaves is set when the word it stands for is full
+        for level in (0..levels - 1).rev() {
+            let (start, child_start) =
+                (map_type.level_start(level), map_type.level_start(level + 1));
+            let width = map_type.level_start(level + 2) - child_start;
+            for position in 0..width {
+                if words[child_start + position] == u64::MAX {
+                    words[start + (position >> 6)] |= 1 << (position & 0x3f);
+                }
+            }
+        }
+
+        Self { map_type, words }
+    }
+
+    pub const fn map_type(&self) -> MapType {
+        self.map_type
+    }
+
+    /// Raw bitmap bytes, e.g. for [`SliceQuery`](crate::SliceQuery)
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safety: the byte view covers exactly the u64 buffer
+        unsafe {
+            std::slice::from_raw_parts(
+                self.words.as_ptr() as *const u8,
+                self.words.len() * size_of::<u64>(),
+            )
+        }
+    }
+
+    /// Map over the fixture, without a header
+    pub fn map(&mut self) -> Result<MemoryMap, MemoryMapError> {
+        // Safety: the byte view covers exactly the u64 buffer
+        let data = unsafe {
+            std::slice::from_raw_parts_mut(
+                self.words.as_mut_ptr() as *mut u8,
+                self.words.len() * size_of::<u64>(),
+            )
+        };
+        MemoryMap::new_from_slice(data, 0, self.map_type)
+    }
+}
+
+/// Every other slot allocated: each leaf word is partial and no two free
+/// slots are adjacent
+pub fn worst_case_fragmented(map_type: MapType) -> Fixture {
+    Fixture::from_fn(map_type, |index| index.is_multiple_of(2))
+}
+
+/// Every slot allocated but the last one, the slowest slot to reach
+pub fn nearly_full(map_type: MapType) -> Fixture {
+    let last = map_type.capacity() - 1;
+    Fixture::from_fn(map_type, |index| index != last)
+}
+
+/// Alternating runs of `stripe` allocated and `stripe` free slots
+pub fn striped(map_type: MapType, stripe: usize) -> Fixture {
+    let stripe = stripe.max(1);
+    Fixture::from_fn(map_type, |index| (index / stripe).is_multiple_of(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worst_case_fragmented() {
+        let mut fixture = worst_case_fragmented(MapType::Small);
+        let mut map = fixture.map().unwrap();
+        let stats = map.stats().unwrap();
+        assert_eq!(stats.used, MapType::Small.capacity() / 2);
+        assert_eq!(stats.free_leaf_words, 0);
+        assert_eq!(stats.largest_free_run, 1);
+        assert_eq!(map.alloc().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_nearly_full() {
+        for map_type in [MapType::Tiny, MapType::Standard, MapType::Max] {
+            let mut fixture = nearly_full(map_type);
+            let mut map = fixture.map().unwrap();
+            assert!(!map.is_full());
+            assert_eq!(map.alloc().unwrap(), map_type.capacity() - 1);
+            assert!(map.is_full());
+        }
+    }
+
+    #[test]
+    fn test_striped_matches_alloc() {
+        let mut fixture = striped(MapType::Standard, 4_096);
+        let mut buffer = crate::create_aligned_buffer(MapType::Standard.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Standard).unwrap();
+        for _ in 0..4_096 {
+            map.alloc().unwrap();
+        }
+        for _ in 0..4_096 {
+            map.alloc_above(8_192).unwrap();
+        }
+
+        // Same words as filling the map through alloc, summaries included
+        assert_eq!(
+            fixture.as_bytes(),
+            &buffer[..MapType::Standard.required_size()]
+        );
+        assert_eq!(fixture.map().unwrap().largest_free_run(), 4_096);
+    }
+}
@@ -0,0 +1,85 @@
+//! Capacity forecasting for off-chain monitoring
+
+/// Estimate the slot at which a map with `capacity` slots fills up
+///
+/// `history` holds `(slot, allocated)` occupancy samples in ascending slot
+/// order. A least-squares line is fitted through the samples and extended
+/// until it reaches `capacity`. Returns `None` when there are fewer than two
+/// distinct slots or occupancy is not growing.
+pub fn forecast_exhaustion(history: &[(u64, usize)], capacity: usize) -> Option<u64> {
+    let &(first_slot, _) = history.first()?;
+    let &(last_slot, last_allocated) = history.last()?;
+    if last_allocated >= capacity {
+        return Some(last_slot);
+    }
+    if history.len() < 2 {
+        return None;
+    }
+
+    // Work relative to the first slot to keep the sums small
+    let n = history.len() as f64;
+    let points = history
+        .iter()
+        .map(|&(slot, allocated)| (slot.saturating_sub(first_slot) as f64, allocated as f64));
+    let (sum_x, sum_y) = points
+        .clone()
+        .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+
+    let (sxx, sxy) = points.fold((0.0, 0.0), |(sxx, sxy), (x, y)| {
+        let dx = x - mean_x;
+        (sxx + dx * dx, sxy + dx * (y - mean_y))
+    });
+    if sxx == 0.0 {
+        return None;
+    }
+
+    let slope = sxy / sxx;
+    if slope <= 0.0 {
+        return None;
+    }
+
+    let intercept = mean_y - slope * mean_x;
+    let offset = ((capacity as f64 - intercept) / slope).ceil();
+    let forecast = first_slot.saturating_add(offset.max(0.0) as u64);
+
+    Some(forecast.max(last_slot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_growth() {
+        // 10 slots per slot of growth starting at 100 allocated
+        let history: Vec<_> = (0..10)
+            .map(|i| (1_000 + i, 100 + 10 * i as usize))
+            .collect();
+        // 4096 - 100 = 3996 more allocations at 10 per slot
+        assert_eq!(forecast_exhaustion(&history, 4_096), Some(1_000 + 400));
+    }
+
+    #[test]
+    fn test_noisy_growth() {
+        let history = [(0, 0), (10, 120), (20, 180), (30, 330), (40, 390)];
+        let forecast = forecast_exhaustion(&history, 1_000).unwrap();
+        assert!((95..=110).contains(&forecast), "forecast {forecast}");
+    }
+
+    #[test]
+    fn test_no_forecast() {
+        assert_eq!(forecast_exhaustion(&[], 64), None);
+        assert_eq!(forecast_exhaustion(&[(5, 10)], 64), None);
+        // Flat and shrinking occupancy never fill the map
+        assert_eq!(forecast_exhaustion(&[(1, 10), (2, 10)], 64), None);
+        assert_eq!(forecast_exhaustion(&[(1, 20), (2, 10)], 64), None);
+        // Samples from a single slot carry no trend
+        assert_eq!(forecast_exhaustion(&[(3, 10), (3, 20)], 64), None);
+    }
+
+    #[test]
+    fn test_already_full() {
+        assert_eq!(forecast_exhaustion(&[(1, 10), (7, 64)], 64), Some(7));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+
+/// Occupancy and fragmentation snapshot of a map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryMapStats {
+    /// Allocated slots
+    pub used: usize,
+    /// Slots still available
+    pub free: usize,
+    /// Leaf words with both allocated and free slots
+    pub partial_leaf_words: usize,
+    /// Leaf words with every slot free
+    pub free_leaf_words: usize,
+    /// Longest run of consecutive free slots
+    pub largest_free_run: usize,
+    /// Highest allocated index plus one, zero for an empty map
+    pub high_water_mark: usize,
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Occupancy and fragmentation snapshot, read from the leaf words
+    ///
+    /// Slots in the tail reserved by `with_capacity` or `try_shrink` are
+    /// neither used nor free, matching `used_count`.
+    pub fn stats(&self) -> Result<MemoryMapStats, MemoryMapError> {
+        leaf_stats(
+            Self::LEAF_WORDS,
+            Self::LEAF_MASK,
+            self.slot_limit(),
+            |block| self.leaf_word(block),
+        )
+    }
+}
+
+/// Stats over `leaf_words` leaf words read from `leaf`
+///
+/// `mask` holds the bits of a leaf word that map to slots. Slots at or past
+/// `limit` are left out of every counter and end the current free run.
+pub(crate) fn leaf_stats(
+    leaf_words: usize,
+    mask: u64,
+    limit: usize,
+    mut leaf: impl FnMut(usize) -> Result<u64, MemoryMapError>,
+) -> Result<MemoryMapStats, MemoryMapError> {
+    let mut stats = MemoryMapStats::default();
+    let (mut start, mut len) = (0, 0);
+    for block in 0..leaf_words {
+        let base = block << 6;
+        let usable = match limit.saturating_sub(base) {
+            0 => break,
+            left if left >= 64 => mask,
+            left => mask & (u64::MAX >> (64 - left)),
+        };
+        let word = leaf(block)? & usable;
+        stats.used += word.count_ones() as usize;
+        if word == 0 {
+            stats.free_leaf_words += 1;
+        } else {
+            if word != usable {
+                stats.partial_leaf_words += 1;
+            }
+            stats.high_water_mark = base + 64 - word.leading_zeros() as usize;
+        }
+
+        let mut free = !word & usable;
+        while free != 0 {
+            let bit = free.trailing_zeros() as usize;
+            let count = (free >> bit).trailing_ones() as usize;
+            if len > 0 && start + len == base + bit {
+                len += count;
+            } else {
+                (start, len) = (base + bit, count);
+            }
+            stats.largest_free_run = stats.largest_free_run.max(len);
+            free &= u64::MAX.checked_shl((bit + count) as u32).unwrap_or(0);
+        }
+    }
+    stats.free = limit.min(leaf_words << 6) - stats.used;
+
+    Ok(stats)
+}
+
+impl MemoryMap {
+    /// Occupancy and fragmentation snapshot, for capacity planning
+    ///
+    /// Walks every leaf word, meant for off-chain monitoring rather than
+    /// instructions.
+    pub fn stats(&self) -> Result<MemoryMapStats, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.stats(),
+            Self::Standard(map) => map.stats(),
+            Self::Small(map) => map.stats(),
+            Self::Huge(map) => map.stats(),
+            Self::Tiny(map) => map.stats(),
+            Self::Medium(map) => map.stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, create_aligned_memory, MapType};
+
+    #[test]
+    fn test_stats() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let leaf_words = MapType::Small.capacity() / 64;
+        assert_eq!(
+            map.stats().unwrap(),
+            MemoryMapStats {
+                free: MapType::Small.capacity(),
+                free_leaf_words: leaf_words,
+                largest_free_run: MapType::Small.capacity(),
+                ..MemoryMapStats::default()
+            }
+        );
+
+        for _ in 0..100 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(10).unwrap();
+        map.dealloc(99).unwrap();
+        let stats = map.stats().unwrap();
+        assert_eq!(stats.used, 98);
+        assert_eq!(stats.free, MapType::Small.capacity() - 98);
+        assert_eq!(stats.partial_leaf_words, 2);
+        assert_eq!(stats.free_leaf_words, leaf_words - 2);
+        assert_eq!(stats.largest_free_run, MapType::Small.capacity() - 99);
+        assert_eq!(stats.high_water_mark, 99);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_stats_leave_out_reserved_tail() {
+        let map_type = MapType::for_slots(100).unwrap();
+        let mut buffer = create_aligned_buffer(crate::HEADER_SIZE + map_type.required_size());
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+        for _ in 0..70 {
+            map.alloc().unwrap();
+        }
+        map.dealloc_batch(&[5, 6, 7]).unwrap();
+
+        let stats = map.stats().unwrap();
+        assert_eq!(stats.used, map.used_count().unwrap());
+        assert_eq!(stats.free, map.free_count().unwrap());
+        assert_eq!(stats.free, 33);
+        assert_eq!(stats.high_water_mark, 70);
+        assert_eq!(stats.largest_free_run, 30);
+        assert_eq!(stats.partial_leaf_words, 2);
+        assert_eq!(stats.free_leaf_words, 0);
+    }
+
+    #[test]
+    fn test_stats_partial_single_level() {
+        let (mut data, ptr) = create_aligned_memory(8);
+        data.fill(0);
+        let mut map = HierMemoryMap::<5, 1>::new(ptr, 8).unwrap();
+        for _ in 0..5 {
+            map.alloc().unwrap();
+        }
+        let stats = map.stats().unwrap();
+        assert_eq!(stats.used, 5);
+        assert_eq!(stats.free, 0);
+        assert_eq!(stats.partial_leaf_words, 0);
+        assert_eq!(stats.high_water_mark, 5);
+    }
+}
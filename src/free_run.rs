@@ -0,0 +1,148 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Length of the longest run of consecutive free slots
+    pub fn largest_free_run(&self) -> usize {
+        let mut largest = 0;
+        // Read errors end the scan, like `is_full` treats them as not full
+        let _ = self.scan_free_runs(|_, len| {
+            largest = largest.max(len);
+            false
+        });
+        largest
+    }
+
+    /// First index starting `n` consecutive free slots, if there is one
+    pub fn find_free_run(&self, n: usize) -> Option<usize> {
+        if n == 0 {
+            return Some(0);
+        }
+
+        let mut found = None;
+        let _ = self.scan_free_runs(|start, len| {
+            if len >= n {
+                found = Some(start);
+            }
+            found.is_some()
+        });
+        found
+    }
+
+    /// Call `visit(start, len)` each time a free run grows, until it returns true
+    ///
+    /// Leaf words marked full above are skipped, they end the current run.
+    fn scan_free_runs(
+        &self,
+        mut visit: impl FnMut(usize, usize) -> bool,
+    ) -> Result<(), MemoryMapError> {
+        let (mut start, mut len) = (0, 0);
+        let mut leaf = 0;
+        while let Some(open) = self.next_open_leaf(leaf)? {
+            let base = open << 6;
+            let mut free = !self.leaf_word(open)? & Self::LEAF_MASK;
+            while free != 0 {
+                let bit = free.trailing_zeros() as usize;
+                let count = (free >> bit).trailing_ones() as usize;
+                if len > 0 && start + len == base + bit {
+                    len += count;
+                } else {
+                    (start, len) = (base + bit, count);
+                }
+                if visit(start, len) {
+                    return Ok(());
+                }
+                free &= u64::MAX.checked_shl((bit + count) as u32).unwrap_or(0);
+            }
+            leaf = open + 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl MemoryMap {
+    /// Length of the longest run of consecutive free slots
+    pub fn largest_free_run(&self) -> usize {
+        match self {
+            Self::Max(map) => map.largest_free_run(),
+            Self::Standard(map) => map.largest_free_run(),
+            Self::Small(map) => map.largest_free_run(),
+            Self::Huge(map) => map.largest_free_run(),
+            Self::Tiny(map) => map.largest_free_run(),
+            Self::Medium(map) => map.largest_free_run(),
+        }
+    }
+
+    /// First index starting `n` consecutive free slots, if there is one
+    ///
+    /// Lets callers check that a multi-slot record fits before allocating.
+    pub fn find_free_run(&self, n: usize) -> Option<usize> {
+        match self {
+            Self::Max(map) => map.find_free_run(n),
+            Self::Standard(map) => map.find_free_run(n),
+            Self::Small(map) => map.find_free_run(n),
+            Self::Huge(map) => map.find_free_run(n),
+            Self::Tiny(map) => map.find_free_run(n),
+            Self::Medium(map) => map.find_free_run(n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, create_aligned_memory, MapType};
+
+    #[test]
+    fn test_free_runs_across_leaf_words() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let capacity = MapType::Small.capacity();
+        assert_eq!(map.largest_free_run(), capacity);
+        assert_eq!(map.find_free_run(capacity), Some(0));
+        assert_eq!(map.find_free_run(capacity + 1), None);
+
+        // Fill the first two leaf words and a bit more
+        for _ in 0..200 {
+            map.alloc().unwrap();
+        }
+        // Holes: 10..13 inside a word, 60..70 across a word boundary
+        for index in (10..13).chain(60..70) {
+            map.dealloc(index).unwrap();
+        }
+        assert_eq!(map.find_free_run(1), Some(10));
+        assert_eq!(map.find_free_run(3), Some(10));
+        assert_eq!(map.find_free_run(4), Some(60));
+        assert_eq!(map.find_free_run(10), Some(60));
+        assert_eq!(map.find_free_run(11), Some(200));
+        assert_eq!(map.largest_free_run(), capacity - 200);
+        assert_eq!(map.find_free_run(0), Some(0));
+    }
+
+    #[test]
+    fn test_free_runs_skip_full_subtrees() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        while map.alloc_block().is_ok() {}
+        assert_eq!(map.largest_free_run(), 0);
+        assert_eq!(map.find_free_run(1), None);
+
+        // A block freed between full ones, then the slot right after it
+        map.dealloc_block(128).unwrap();
+        map.dealloc(192).unwrap();
+        assert_eq!(map.largest_free_run(), 65);
+        assert_eq!(map.find_free_run(65), Some(128));
+    }
+
+    #[test]
+    fn test_free_runs_partial_single_level() {
+        let (mut data, ptr) = create_aligned_memory(8);
+        data.fill(0);
+        let mut map = HierMemoryMap::<5, 1>::new(ptr, 8).unwrap();
+        assert_eq!(map.largest_free_run(), 5);
+        map.alloc().unwrap();
+        assert_eq!(map.largest_free_run(), 4);
+        assert_eq!(map.find_free_run(4), Some(1));
+        assert_eq!(map.find_free_run(5), None);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    bytes::{read_u32, write_u32},
+    MemoryMap, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Size of one stack entry, every capacity fits in a u32
+const ENTRY_SIZE: usize = size_of::<u32>();
+
+/// Header entries: number of stacked indices, then the next write position
+const HEADER_ENTRIES: usize = 2;
+
+/// Persisted bounded stack of recently freed slots for LIFO reuse
+///
+/// When the stack is full the oldest entry is overwritten, once it is empty
+/// allocation falls back to the bitmap scan. An all-zero region is an empty
+/// stack.
+pub struct FreeStack<'a> {
+    data: &'a mut [u8],
+}
+
+impl<'a> FreeStack<'a> {
+    /// Number of bytes needed for a stack holding `depth` indices
+    pub const fn required_size(depth: usize) -> usize {
+        (HEADER_ENTRIES + depth) * ENTRY_SIZE
+    }
+
+    /// Wrap a region previously zeroed or written by this stack
+    pub fn new(data: &'a mut [u8]) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::required_size(1) || !data.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        let stack = Self { data };
+        if stack.len() > stack.depth() || stack.head() >= stack.depth() {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        Ok(stack)
+    }
+
+    /// Maximum number of indices the stack holds
+    pub fn depth(&self) -> usize {
+        self.data.len() / ENTRY_SIZE - HEADER_ENTRIES
+    }
+
+    /// Number of stacked indices
+    pub fn len(&self) -> usize {
+        self.entry(0) as usize
+    }
+
+    /// Check whether no index is stacked
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Push freed slot `index`, dropping the oldest entry when full
+    pub fn push(&mut self, index: usize) {
+        let head = self.head();
+        self.set_entry(HEADER_ENTRIES + head, index as u32);
+        self.set_entry(1, ((head + 1) % self.depth()) as u32);
+        self.set_entry(0, (self.len() + 1).min(self.depth()) as u32);
+    }
+
+    /// Pop the most recently pushed index
+    pub fn pop(&mut self) -> Option<usize> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        let head = (self.head() + self.depth() - 1) % self.depth();
+        self.set_entry(1, head as u32);
+        self.set_entry(0, len as u32 - 1);
+        Some(self.entry(HEADER_ENTRIES + head) as usize)
+    }
+
+    fn head(&self) -> usize {
+        self.entry(1) as usize
+    }
+
+    fn entry(&self, entry: usize) -> u32 {
+        read_u32(self.data, entry * ENTRY_SIZE)
+    }
+
+    fn set_entry(&mut self, entry: usize, value: u32) {
+        write_u32(self.data, entry * ENTRY_SIZE, value);
+    }
+}
+
+impl MemoryMap {
+    /// Deallocate slot `index` and push it onto `stack` for LIFO reuse
+    pub fn dealloc_lifo(
+        &mut self,
+        stack: &mut FreeStack,
+        index: usize,
+    ) -> Result<(), MemoryMapError> {
+        self.dealloc(index)?;
+        stack.push(index);
+        Ok(())
+    }
+
+    /// Allocate the most recently freed slot still on `stack`, scanning the
+    /// bitmap once the stack is empty
+    ///
+    /// Entries whose slot was allocated again by other means, or that lie in
+    /// the range set by [`MemoryMap::reserve_range`], are discarded.
+    pub fn alloc_lifo(&mut self, stack: &mut FreeStack) -> Result<usize, MemoryMapError> {
+        let reserved = self.reserved_range();
+        while let Some(index) = stack.pop() {
+            if reserved
+                .as_ref()
+                .is_some_and(|range| range.contains(&index))
+            {
+                continue;
+            }
+            match self.alloc_at(index) {
+                Ok(()) => return Ok(index),
+                Err(MemoryMapError::SlotTaken | MemoryMapError::InvalidIndex) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        self.alloc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_lifo_reuse() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = vec![0u8; FreeStack::required_size(2)];
+        let mut stack = FreeStack::new(&mut region).unwrap();
+        for _ in 0..10 {
+            map.alloc_lifo(&mut stack).unwrap();
+        }
+
+        // Depth 2: freeing three slots forgets the oldest one
+        map.dealloc_lifo(&mut stack, 1).unwrap();
+        map.dealloc_lifo(&mut stack, 7).unwrap();
+        map.dealloc_lifo(&mut stack, 4).unwrap();
+        assert_eq!(stack.len(), 2);
+        assert_eq!(map.alloc_lifo(&mut stack).unwrap(), 4);
+
+        // A stale entry taken by a plain alloc is skipped
+        map.alloc_at(7).unwrap();
+        assert_eq!(map.alloc_lifo(&mut stack).unwrap(), 1);
+        assert!(stack.is_empty());
+        assert_eq!(map.alloc_lifo(&mut stack).unwrap(), 10);
+
+        // The stack survives in its region
+        map.dealloc_lifo(&mut stack, 3).unwrap();
+        let mut stack = FreeStack::new(&mut region).unwrap();
+        assert_eq!(stack.depth(), 2);
+        assert_eq!(map.alloc_lifo(&mut stack).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_reserved_slots_not_reused() {
+        let size = HEADER_SIZE + MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; FreeStack::required_size(4)];
+        let mut stack = FreeStack::new(&mut region).unwrap();
+        map.reserve_range(0, 8).unwrap();
+        map.alloc_at(2).unwrap();
+        assert_eq!(map.alloc().unwrap(), 8);
+
+        // The freed seat stays with the protocol
+        map.dealloc_lifo(&mut stack, 2).unwrap();
+        assert_eq!(map.alloc_lifo(&mut stack).unwrap(), 9);
+        assert!(stack.is_empty());
+        assert!(!map.is_allocated(2).unwrap());
+    }
+
+    #[test]
+    fn test_stack_region_checks() {
+        assert!(matches!(
+            FreeStack::new(&mut [0u8; 8]),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        let mut region = [0u8; 12];
+        region[0] = 2;
+        assert!(matches!(
+            FreeStack::new(&mut region),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    header::{FLAG_FROZEN, FLAG_FROZEN_DEALLOC},
+    MemoryMap, MemoryMapError,
+};
+
+impl MemoryMap {
+    /// Stop every allocation until [`MemoryMap::thaw`], e.g. for a market halt
+    /// or a migration
+    ///
+    /// While frozen, allocating fails with `MapFrozen`, and with
+    /// `block_dealloc` deallocating does too. The state is persisted in the
+    /// header, so every instruction loading the map sees it without checks of
+    /// its own. Capacity changes stay possible on purpose, for migrations:
+    /// `try_shrink` reserves the tail and `extend_capacity` releases it even
+    /// while frozen.
+    pub fn freeze(&mut self, block_dealloc: bool) -> Result<(), MemoryMapError> {
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        header.flags |= FLAG_FROZEN;
+        if block_dealloc {
+            header.flags |= FLAG_FROZEN_DEALLOC;
+        } else {
+            header.flags &= !FLAG_FROZEN_DEALLOC;
+        }
+        Ok(())
+    }
+
+    /// Allow allocations and deallocations again
+    pub fn thaw(&mut self) -> Result<(), MemoryMapError> {
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        header.flags &= !(FLAG_FROZEN | FLAG_FROZEN_DEALLOC);
+        Ok(())
+    }
+
+    /// Check whether allocations are frozen, never for maps without a header
+    pub fn is_frozen(&self) -> bool {
+        self.header()
+            .is_some_and(|header| header.has_flag(FLAG_FROZEN))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, AllocPolicy, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_freeze_blocks_alloc() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        for _ in 0..3 {
+            map.alloc().unwrap();
+        }
+        map.freeze(false).unwrap();
+
+        // The freeze survives a reload and covers every placement
+        let mut map = MemoryMap::load(&mut buffer, 0, MapType::Small).unwrap();
+        assert!(map.is_frozen());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::MapFrozen)));
+        assert!(matches!(map.alloc_near(5), Err(MemoryMapError::MapFrozen)));
+        assert!(matches!(map.alloc_block(), Err(MemoryMapError::MapFrozen)));
+        assert!(matches!(map.alloc_twin(), Err(MemoryMapError::MapFrozen)));
+        assert!(matches!(map.alloc_at(9), Err(MemoryMapError::MapFrozen)));
+        assert_eq!(map.used_count().unwrap(), 3);
+
+        // Orders can still be cancelled
+        map.dealloc(1).unwrap();
+        map.thaw().unwrap();
+        assert!(!map.is_frozen());
+        assert_eq!(map.alloc().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_frozen_alloc_leaves_header_alone() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+        map.set_alloc_policy(AllocPolicy::RoundRobin).unwrap();
+        map.alloc().unwrap();
+        map.freeze(true).unwrap();
+
+        let before = *map.header().unwrap();
+        assert!(matches!(map.alloc(), Err(MemoryMapError::MapFrozen)));
+        assert_eq!(*map.header().unwrap(), before);
+
+        // Migrations may still change the capacity
+        map.extend_capacity(200).unwrap();
+        map.try_shrink(150).unwrap();
+        assert_eq!(map.slot_limit(), 150);
+    }
+
+    #[test]
+    fn test_freeze_blocks_dealloc() {
+        let size = HEADER_SIZE + MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Tiny).unwrap();
+        map.alloc().unwrap();
+        map.freeze(true).unwrap();
+        assert!(matches!(map.dealloc(0), Err(MemoryMapError::MapFrozen)));
+        assert!(map.is_allocated(0).unwrap());
+
+        // Freezing again without the dealloc block lifts it
+        map.freeze(false).unwrap();
+        map.dealloc(0).unwrap();
+        assert!(matches!(map.alloc(), Err(MemoryMapError::MapFrozen)));
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert!(matches!(
+            map.freeze(false),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert!(!map.is_frozen());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_freeze_blocks_every_free_path() {
+        use crate::BuddyMemoryMap;
+
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        let base = map.alloc_block().unwrap();
+        map.alloc().unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+        let run = buddy.alloc(2).unwrap();
+        let mut map = buddy.into_inner();
+        map.freeze(true).unwrap();
+
+        assert!(matches!(map.dealloc(base), Err(MemoryMapError::MapFrozen)));
+        assert!(matches!(
+            map.dealloc_batch(&[base, 64]),
+            Err(MemoryMapError::MapFrozen)
+        ));
+        assert!(matches!(
+            map.dealloc_block(base),
+            Err(MemoryMapError::MapFrozen)
+        ));
+        let mut buddy = BuddyMemoryMap::new(map);
+        assert!(matches!(
+            buddy.dealloc(run, 2),
+            Err(MemoryMapError::MapFrozen)
+        ));
+        let mut map = buddy.into_inner();
+        assert_eq!(map.used_count().unwrap(), 64 + 1 + 4);
+
+        map.thaw().unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+        buddy.dealloc(run, 2).unwrap();
+        assert_eq!(buddy.map().used_count().unwrap(), 64 + 1);
+    }
+}
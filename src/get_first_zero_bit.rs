@@ -1,41 +1,33 @@
 use crate::MemoryMapError;
 
+/// Lowest zero bit among the low `bits` bits of `pattern`
+#[inline]
 pub(crate) fn get_first_zero_bit(pattern: u64, bits: usize) -> Result<usize, MemoryMapError> {
-    if bits < 33 {
-        for j in 0..bits {
-            if pattern & (1 << j) == 0 {
-                return Ok(j);
-            }
-        }
-    } else if pattern & 0xffffffff == 0xffffffff {
-        if pattern & 0xffff00000000 == 0xffff00000000 {
-            for j in 48..bits {
-                if pattern & (1 << j) == 0 {
-                    return Ok(j);
-                }
-            }
-        } else {
-            for j in 32..bits.min(48) {
-                if pattern & (1 << j) == 0 {
-                    return Ok(j);
-                }
-            }
-        }
-    } else if pattern & 0xffff == 0xffff {
-        for j in 16..32 {
-            if pattern & (1 << j) == 0 {
-                return Ok(j);
-            }
-        }
-    } else {
-        for j in 0..16 {
-            if pattern & (1 << j) == 0 {
-                return Ok(j);
-            }
-        }
+    let free = !pattern & low_bits(bits);
+    if free == 0 {
+        return Err(MemoryMapError::NoAvailableSlots);
     }
+    Ok(free.trailing_zeros() as usize)
+}
+
+/// Highest zero bit among the low `bits` bits of `pattern`
+#[inline]
+pub(crate) fn get_last_zero_bit(pattern: u64, bits: usize) -> Result<usize, MemoryMapError> {
+    let free = !pattern & low_bits(bits);
+    if free == 0 {
+        return Err(MemoryMapError::NoAvailableSlots);
+    }
+    Ok(63 - free.leading_zeros() as usize)
+}
 
-    Err(MemoryMapError::NoAvailableSlots)
+/// Mask of the low `bits` bits, all of them for 64 or more
+#[inline]
+const fn low_bits(bits: usize) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
 }
 
 #[cfg(test)]
@@ -129,4 +121,94 @@ mod tests {
         let result = get_first_zero_bit(pattern, 64);
         assert_eq!(result.unwrap(), 1, "Should find zero bit at position 1");
     }
+
+    #[test]
+    fn test_get_last_zero_bit() {
+        assert_eq!(get_last_zero_bit(0, 64).unwrap(), 63);
+        assert_eq!(get_last_zero_bit(0, 4).unwrap(), 3);
+        assert_eq!(get_last_zero_bit(1 << 63, 64).unwrap(), 62);
+        assert_eq!(get_last_zero_bit(0xFFFF_FFFF_FFFF_FFFE, 64).unwrap(), 0);
+        assert!(matches!(
+            get_last_zero_bit(0xF, 4),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert!(matches!(
+            get_last_zero_bit(u64::MAX, 64),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+    }
+
+    /// Range-scan implementation this module used before the intrinsic
+    fn reference_first_zero_bit(pattern: u64, bits: usize) -> Result<usize, MemoryMapError> {
+        if bits < 33 {
+            for j in 0..bits {
+                if pattern & (1 << j) == 0 {
+                    return Ok(j);
+                }
+            }
+        } else if pattern & 0xffffffff == 0xffffffff {
+            if pattern & 0xffff00000000 == 0xffff00000000 {
+                for j in 48..bits {
+                    if pattern & (1 << j) == 0 {
+                        return Ok(j);
+                    }
+                }
+            } else {
+                for j in 32..bits.min(48) {
+                    if pattern & (1 << j) == 0 {
+                        return Ok(j);
+                    }
+                }
+            }
+        } else if pattern & 0xffff == 0xffff {
+            for j in 16..32 {
+                if pattern & (1 << j) == 0 {
+                    return Ok(j);
+                }
+            }
+        } else {
+            for j in 0..16 {
+                if pattern & (1 << j) == 0 {
+                    return Ok(j);
+                }
+            }
+        }
+
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+
+    #[test]
+    fn test_matches_reference_on_boundary_patterns() {
+        for boundary in 0..=64 {
+            // Low `boundary` bits set, optionally with noise above the first zero
+            let low = low_bits(boundary);
+            let patterns = [
+                low,
+                low | (0xAAAA_AAAA_AAAA_AAAA & !low_bits(boundary + 1)),
+                low | (0x5555_5555_5555_5555 & !low_bits(boundary + 1)),
+                !(1u64.checked_shl(boundary as u32).unwrap_or(0)),
+            ];
+            for pattern in patterns {
+                for bits in 0..=64 {
+                    assert_eq!(
+                        get_first_zero_bit(pattern, bits).ok(),
+                        reference_first_zero_bit(pattern, bits).ok(),
+                        "pattern {pattern:#x}, bits {bits}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_bits_searched() {
+        assert!(matches!(
+            get_first_zero_bit(0, 0),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert!(matches!(
+            get_last_zero_bit(0, 0),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+    }
 }
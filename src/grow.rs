@@ -0,0 +1,248 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Slots below the reserved tail recorded in the header, the full
+    /// capacity without one
+    pub(crate) fn slot_limit(&self) -> usize {
+        // Safety: the header outlives the map, like the bitmap itself
+        match self
+            .header()
+            .map(|header| unsafe { header.as_ref() }.slot_limit)
+        {
+            Some(limit) if limit != 0 => limit as usize,
+            _ => Self::CAPACITY,
+        }
+    }
+
+    /// Free the slots `from..to` reserved by `reserve_tail`
+    pub(crate) fn release_tail(&mut self, from: usize, to: usize) -> Result<(), MemoryMapError> {
+        if from > to || to > Self::CAPACITY {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let mut index = from;
+        while index < to {
+            let block = index >> 6;
+            let end = to.min((block + 1) << 6);
+            let mask = (u64::MAX >> (64 - (end - index))) << (index & 0x3f);
+            self.release_bits(block, mask)?;
+            index = end;
+        }
+
+        Ok(())
+    }
+
+    /// Check whether any slot in `from..to` is allocated
+    pub(crate) fn any_allocated(&self, from: usize, to: usize) -> Result<bool, MemoryMapError> {
+        let mut index = from;
+        while index < to {
+            let block = index >> 6;
+            let end = to.min((block + 1) << 6);
+            let mask = (u64::MAX >> (64 - (end - index))) << (index & 0x3f);
+            if self.leaf_word(block)? & Self::LEAF_MASK & mask != 0 {
+                return Ok(true);
+            }
+            index = end;
+        }
+
+        Ok(false)
+    }
+}
+
+impl MemoryMap {
+    /// Accept the account length after a `realloc` that extended it
+    ///
+    /// The bitmap of a fixed map type does not move, only the length recorded
+    /// in the header is updated so the map loads again. Only maps created with
+    /// [`MemoryMap::init`] or [`MemoryMap::load`] record a length; shrinking
+    /// fails with `LengthMismatch`.
+    pub fn grow(&mut self, new_len: usize) -> Result<(), MemoryMapError> {
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        if (new_len as u64) < header.data_len {
+            return Err(MemoryMapError::LengthMismatch);
+        }
+        header.data_len = new_len as u64;
+        Ok(())
+    }
+
+    /// Slots usable by a map created with [`MemoryMap::with_capacity`], the
+    /// full capacity otherwise
+    pub fn slot_limit(&self) -> usize {
+        match self {
+            Self::Max(map) => map.slot_limit(),
+            Self::Standard(map) => map.slot_limit(),
+            Self::Small(map) => map.slot_limit(),
+            Self::Huge(map) => map.slot_limit(),
+            Self::Tiny(map) => map.slot_limit(),
+            Self::Medium(map) => map.slot_limit(),
+        }
+    }
+
+    /// Raise the slot limit of a map created with [`MemoryMap::with_capacity`]
+    /// to `slots`, once the account holds data for them
+    ///
+    /// The reserved slots from the old limit up to `slots` become allocatable,
+    /// existing indices are left untouched.
+    pub fn extend_capacity(&mut self, slots: usize) -> Result<(), MemoryMapError> {
+        let limit = self.slot_limit();
+        if slots < limit || slots > self.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        match self {
+            Self::Max(map) => map.release_tail(limit, slots)?,
+            Self::Standard(map) => map.release_tail(limit, slots)?,
+            Self::Small(map) => map.release_tail(limit, slots)?,
+            Self::Huge(map) => map.release_tail(limit, slots)?,
+            Self::Tiny(map) => map.release_tail(limit, slots)?,
+            Self::Medium(map) => map.release_tail(limit, slots)?,
+        }
+        if let Some(header) = self.header_mut() {
+            header.slot_limit = if slots == header.map_type()?.capacity() {
+                0
+            } else {
+                slots as u64
+            };
+        }
+        Ok(())
+    }
+
+    /// Lower the slot limit to `new_capacity` before a `realloc` that shrinks
+    /// the data region
+    ///
+    /// Fails with `TailNotEmpty` if any index from `new_capacity` on is still
+    /// allocated, otherwise those slots are reserved so alloc never hands them
+    /// out again. [`MemoryMap::extend_capacity`] undoes it; the limit is only
+    /// remembered by maps with a header.
+    pub fn try_shrink(&mut self, new_capacity: usize) -> Result<(), MemoryMapError> {
+        let limit = self.slot_limit();
+        if new_capacity > limit {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let occupied = match self {
+            Self::Max(map) => map.any_allocated(new_capacity, limit)?,
+            Self::Standard(map) => map.any_allocated(new_capacity, limit)?,
+            Self::Small(map) => map.any_allocated(new_capacity, limit)?,
+            Self::Huge(map) => map.any_allocated(new_capacity, limit)?,
+            Self::Tiny(map) => map.any_allocated(new_capacity, limit)?,
+            Self::Medium(map) => map.any_allocated(new_capacity, limit)?,
+        };
+        if occupied {
+            return Err(MemoryMapError::TailNotEmpty);
+        }
+
+        match self {
+            Self::Max(map) => map.reserve_tail(new_capacity)?,
+            Self::Standard(map) => map.reserve_tail(new_capacity)?,
+            Self::Small(map) => map.reserve_tail(new_capacity)?,
+            Self::Huge(map) => map.reserve_tail(new_capacity)?,
+            Self::Tiny(map) => map.reserve_tail(new_capacity)?,
+            Self::Medium(map) => map.reserve_tail(new_capacity)?,
+        }
+        if let Some(header) = self.header_mut() {
+            header.slot_limit = new_capacity as u64;
+        }
+        self.recount()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_grow_records_length() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size + 64);
+        let mut map = MemoryMap::init(&mut buffer[..size], 0, MapType::Small).unwrap();
+        map.alloc().unwrap();
+
+        assert!(matches!(
+            map.grow(size - 1),
+            Err(MemoryMapError::LengthMismatch)
+        ));
+        map.grow(size + 64).unwrap();
+        let mut map = MemoryMap::load(&mut buffer[..size + 64], 0, MapType::Small).unwrap();
+        assert_eq!(map.alloc().unwrap(), 1);
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert!(matches!(map.grow(64), Err(MemoryMapError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_extend_capacity() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+        assert_eq!(map.map_type(), MapType::Small);
+        assert_eq!(map.slot_limit(), 100);
+        for expected in 0..100 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        map.dealloc(40).unwrap();
+        assert!(matches!(map.alloc_near(10), Ok(40)));
+        assert!(map.is_full());
+
+        assert!(matches!(
+            map.extend_capacity(99),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        map.extend_capacity(300).unwrap();
+        assert_eq!(map.slot_limit(), 300);
+        assert_eq!(map.used_count().unwrap(), 100);
+        for expected in 100..300 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+
+        // The raised limit survives a reload, and the last step clears it
+        let mut map = MemoryMap::load_auto(&mut buffer, 0).unwrap();
+        assert_eq!(map.slot_limit(), 300);
+        map.extend_capacity(MapType::Small.capacity()).unwrap();
+        assert_eq!(map.slot_limit(), MapType::Small.capacity());
+        assert_eq!(map.alloc().unwrap(), 300);
+        assert_eq!(map.used_count().unwrap(), 301);
+    }
+
+    #[test]
+    fn test_try_shrink() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer[..size], 0, MapType::Small).unwrap();
+        for _ in 0..300 {
+            map.alloc().unwrap();
+        }
+
+        assert!(matches!(
+            map.try_shrink(200),
+            Err(MemoryMapError::TailNotEmpty)
+        ));
+        for index in 150..300 {
+            map.dealloc(index).unwrap();
+        }
+        map.try_shrink(150).unwrap();
+        assert_eq!(map.slot_limit(), 150);
+        assert!(map.is_full());
+        assert!(matches!(
+            map.alloc_near(40),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert!(matches!(
+            map.try_shrink(151),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        // Shrinking is undone by extending again
+        map.dealloc(3).unwrap();
+        for index in 100..150 {
+            map.dealloc(index).unwrap();
+        }
+        map.try_shrink(100).unwrap();
+        map.extend_capacity(160).unwrap();
+        assert_eq!(map.alloc().unwrap(), 3);
+        assert_eq!(map.alloc().unwrap(), 100);
+        assert_eq!(map.used_count().unwrap(), 101);
+        assert_eq!(map.free_count().unwrap(), 160 - 101);
+    }
+}
@@ -0,0 +1,397 @@
+use crate::{AllocPolicy, MapType, MemoryMapError};
+use core::{
+    mem::{align_of, size_of},
+    ptr::NonNull,
+};
+
+/// Magic value marking an initialized map header ("IMAP")
+pub const HEADER_MAGIC: u32 = u32::from_le_bytes(*b"IMAP");
+
+/// Map type byte recorded for a [`DynamicMemoryMap`](crate::DynamicMemoryMap)
+///
+/// Not a valid [`MapType`], so fixed-size loads reject dynamic maps.
+pub const DYNAMIC_MAP_TYPE: u8 = 0xff;
+
+/// Header flag: keep [`MapHeader::leaf_hint`] up to date and try it first on
+/// alloc
+pub const FLAG_LEAF_HINT: u8 = 1 << 0;
+
+/// Header flag: read every allocated bit back before returning, failing with
+/// [`MemoryMapError::WriteVerificationFailed`] if it did not stick
+pub const FLAG_VERIFY_WRITES: u8 = 1 << 1;
+
+/// Header flag: keep the lifetime [`MapHeader::total_allocs`] and
+/// [`MapHeader::total_deallocs`] counters up to date
+pub const FLAG_COUNTERS: u8 = 1 << 2;
+
+/// Header flag: keep [`MapHeader::checksum`] up to date on every bitmap write
+pub const FLAG_CHECKSUM: u8 = 1 << 3;
+
+/// Header flag: fail allocations with [`MemoryMapError::MapFrozen`], set by
+/// `MemoryMap::freeze`
+pub const FLAG_FROZEN: u8 = 1 << 4;
+
+/// Header flag: with [`FLAG_FROZEN`], fail deallocations as well
+pub const FLAG_FROZEN_DEALLOC: u8 = 1 << 5;
+
+/// Every flag understood by this version
+pub const KNOWN_FLAGS: u8 = FLAG_LEAF_HINT
+    | FLAG_VERIFY_WRITES
+    | FLAG_COUNTERS
+    | FLAG_CHECKSUM
+    | FLAG_FROZEN
+    | FLAG_FROZEN_DEALLOC;
+
+/// Size of the header placed in front of the bitmap
+pub const HEADER_SIZE: usize = size_of::<MapHeader>();
+
+/// Version of the on-account layout (header + bitmap)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u16)]
+pub enum LayoutVersion {
+    V1 = 1,
+}
+
+impl LayoutVersion {
+    /// Version written by `MemoryMap::init`
+    pub const CURRENT: Self = Self::V1;
+}
+
+impl TryFrom<u16> for LayoutVersion {
+    type Error = MemoryMapError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::V1),
+            _ => Err(MemoryMapError::UnsupportedVersion),
+        }
+    }
+}
+
+/// Header stored in front of the bitmap of an initialized map
+///
+/// The header has a fixed size, new fields are carved out of `reserved` so the
+/// bitmap never moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[repr(C)]
+pub struct MapHeader {
+    pub magic: u32,
+    pub version: u16,
+    pub map_type: u8,
+    pub flags: u8,
+    /// Number of leaf words of a dynamic map, 0 for fixed map types
+    pub leaf_words: u64,
+    /// Leaf block before which every block is known to be full
+    pub first_free_block: u64,
+    /// Account length at init or the last migration, 0 if not recorded
+    pub data_len: u64,
+    /// Last leaf block that had free slots, used with [`FLAG_LEAF_HINT`]
+    pub leaf_hint: u64,
+    /// `state_hash` recorded by the last `MemoryMap::seal`
+    pub seal_hash: u64,
+    /// Number of allocated slots, kept up to date by alloc and dealloc
+    pub used_count: u64,
+    /// Slots usable by a map created with `MemoryMap::with_capacity`, 0 if
+    /// the whole capacity is usable
+    pub slot_limit: u64,
+    /// Start of the slot range `alloc` skips, see `MemoryMap::reserve_range`
+    pub reserved_start: u64,
+    /// End of the slot range `alloc` skips, equal to the start if there is none
+    pub reserved_end: u64,
+    /// Recycling epoch, advanced by `MemoryMap::advance_epoch`
+    pub epoch: u64,
+    /// Placement policy consulted by alloc, see `AllocPolicy`
+    pub alloc_policy: u64,
+    /// Round-robin cursor or pseudo-random state of the placement policy
+    pub policy_state: u64,
+    /// Slots allocated over the map's lifetime, used with [`FLAG_COUNTERS`]
+    pub total_allocs: u64,
+    /// Slots freed over the map's lifetime, used with [`FLAG_COUNTERS`]
+    pub total_deallocs: u64,
+    /// Bumped by every mutation of the bitmap, for readers detecting torn
+    /// multi-read snapshots
+    pub generation: u64,
+    /// Checksum of the bitmap, used with [`FLAG_CHECKSUM`]
+    pub checksum: u64,
+    /// Sequence number of the next order ID, see `MemoryMap::alloc_order`
+    pub order_seq: u64,
+    pub reserved: [u64; 14],
+}
+
+impl MapHeader {
+    /// Create a header for a freshly initialized map
+    pub const fn new(map_type: MapType) -> Self {
+        Self {
+            magic: HEADER_MAGIC,
+            version: LayoutVersion::CURRENT as u16,
+            map_type: map_type as u8,
+            flags: 0,
+            leaf_words: 0,
+            first_free_block: 0,
+            data_len: 0,
+            leaf_hint: 0,
+            seal_hash: 0,
+            used_count: 0,
+            slot_limit: 0,
+            reserved_start: 0,
+            reserved_end: 0,
+            epoch: 0,
+            alloc_policy: 0,
+            policy_state: 0,
+            total_allocs: 0,
+            total_deallocs: 0,
+            generation: 0,
+            checksum: 0,
+            order_seq: 0,
+            reserved: [0; 14],
+        }
+    }
+
+    /// Create a header for a freshly initialized dynamic map
+    pub const fn new_dynamic(leaf_words: u64) -> Self {
+        Self {
+            map_type: DYNAMIC_MAP_TYPE,
+            leaf_words,
+            ..Self::new(MapType::Max)
+        }
+    }
+
+    /// Layout version recorded in the header
+    pub fn layout_version(&self) -> Result<LayoutVersion, MemoryMapError> {
+        LayoutVersion::try_from(self.version)
+    }
+
+    /// Map type recorded in the header
+    pub fn map_type(&self) -> Result<MapType, MemoryMapError> {
+        MapType::try_from(self.map_type)
+    }
+
+    /// Check whether `flag` is set
+    pub const fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag == flag
+    }
+
+    /// Check that the header was written by `MemoryMap::init` with the current
+    /// layout version
+    pub fn validate(&self) -> Result<(), MemoryMapError> {
+        if self.magic != HEADER_MAGIC {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        if self.layout_version()? != LayoutVersion::CURRENT {
+            return Err(MemoryMapError::UnsupportedVersion);
+        }
+        if self.flags & !KNOWN_FLAGS != 0 {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        AllocPolicy::from_header(self)?;
+        Ok(())
+    }
+
+    /// Check that the account still has the length recorded at init or the
+    /// last migration
+    ///
+    /// Catches accounts that were realloc'd without running [`migrate`].
+    pub fn check_data_len(&self, data_len: usize) -> Result<(), MemoryMapError> {
+        if self.data_len != 0 && self.data_len != data_len as u64 {
+            return Err(MemoryMapError::LengthMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Get the header stored at `offset` in `data`
+pub(crate) fn header_mut(data: &mut [u8], offset: usize) -> Result<&mut MapHeader, MemoryMapError> {
+    if offset >= data.len() {
+        return Err(MemoryMapError::InvalidOffset);
+    }
+    if data.len() - offset < HEADER_SIZE {
+        return Err(MemoryMapError::InsufficientMemory);
+    }
+
+    let ptr = data[offset..].as_mut_ptr();
+    if !(ptr as usize).is_multiple_of(align_of::<MapHeader>()) {
+        return Err(MemoryMapError::AlignmentError);
+    }
+
+    let header = NonNull::new(ptr as *mut MapHeader).ok_or(MemoryMapError::NullPointer)?;
+    // Safety: bounds and alignment are checked above and the header is plain data
+    unsafe { Ok(&mut *header.as_ptr()) }
+}
+
+/// Bring the layout stored at `offset` up to date after a `realloc`
+///
+/// V1 is the only layout so far, so no layout step runs: the version is
+/// checked and the current account length is recorded as the expected one.
+/// Versions this release does not know fail with `UnsupportedVersion`.
+pub fn migrate(data: &mut [u8], offset: usize) -> Result<LayoutVersion, MemoryMapError> {
+    let data_len = data.len();
+    let header = header_mut(data, offset)?;
+    if header.magic != HEADER_MAGIC {
+        return Err(MemoryMapError::InvalidHeader);
+    }
+
+    let version = header.layout_version()?;
+    header.data_len = data_len as u64;
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_memory, MemoryMap};
+
+    #[test]
+    fn test_header_size() {
+        assert_eq!(HEADER_SIZE, 256);
+        assert_eq!(HEADER_SIZE % size_of::<u64>(), 0);
+    }
+
+    #[test]
+    fn test_init_writes_header() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let (mut data, _) = create_aligned_memory(size);
+
+        MemoryMap::init(&mut data, 0, MapType::Small).unwrap();
+
+        let header = header_mut(&mut data, 0).unwrap();
+        assert_eq!(header.magic, HEADER_MAGIC);
+        assert_eq!(header.layout_version().unwrap(), LayoutVersion::CURRENT);
+        assert_eq!(header.map_type().unwrap(), MapType::Small);
+        assert!(header.validate().is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let (mut data, _) = create_aligned_memory(size);
+        MemoryMap::init(&mut data, 0, MapType::Small).unwrap();
+
+        header_mut(&mut data, 0).unwrap().version = 99;
+
+        assert!(matches!(
+            MemoryMap::load(&mut data, 0, MapType::Small),
+            Err(MemoryMapError::UnsupportedVersion)
+        ));
+        assert!(matches!(
+            migrate(&mut data, 0),
+            Err(MemoryMapError::UnsupportedVersion)
+        ));
+    }
+
+    #[test]
+    fn test_migrate_current_is_noop() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let (mut data, _) = create_aligned_memory(size);
+        let mut map = MemoryMap::init(&mut data, 0, MapType::Small).unwrap();
+        let index = map.alloc().unwrap();
+
+        assert_eq!(migrate(&mut data, 0).unwrap(), LayoutVersion::CURRENT);
+
+        // Allocation state survives the migration
+        let mut map = MemoryMap::load(&mut data, 0, MapType::Small).unwrap();
+        assert_eq!(map.alloc().unwrap(), index + 1);
+
+        // Layouts from a later release are left alone
+        header_mut(&mut data, 0).unwrap().version = 2;
+        assert!(matches!(
+            migrate(&mut data, 0),
+            Err(MemoryMapError::UnsupportedVersion)
+        ));
+    }
+
+    #[test]
+    fn test_uninitialized_header() {
+        let (mut data, _) = create_aligned_memory(HEADER_SIZE);
+        assert!(matches!(
+            migrate(&mut data, 0),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_first_free_block_hint() {
+        let size = HEADER_SIZE + MapType::Max.required_size();
+        let (mut data, _) = create_aligned_memory(size);
+        let mut map = MemoryMap::init(&mut data, 0, MapType::Max).unwrap();
+        for _ in 0..200 {
+            map.alloc().unwrap();
+        }
+        assert_eq!(header_mut(&mut data, 0).unwrap().first_free_block, 3);
+
+        // Dealloc moves the hint back, alloc refills the lowest free slot
+        let mut map = MemoryMap::load(&mut data, 0, MapType::Max).unwrap();
+        map.dealloc(130).unwrap();
+        map.dealloc(5).unwrap();
+        assert_eq!(header_mut(&mut data, 0).unwrap().first_free_block, 0);
+
+        let mut map = MemoryMap::load(&mut data, 0, MapType::Max).unwrap();
+        assert_eq!(map.alloc().unwrap(), 5);
+        assert_eq!(map.alloc().unwrap(), 130);
+        assert_eq!(map.alloc().unwrap(), 200);
+
+        // A hint past the leaf level is ignored
+        header_mut(&mut data, 0).unwrap().first_free_block = u64::MAX;
+        let mut map = MemoryMap::load(&mut data, 0, MapType::Max).unwrap();
+        assert_eq!(map.alloc().unwrap(), 201);
+        assert_eq!(header_mut(&mut data, 0).unwrap().first_free_block, 3);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let (mut data, _) = create_aligned_memory(size + 64);
+        MemoryMap::init(&mut data[..size], 0, MapType::Small).unwrap();
+        assert_eq!(header_mut(&mut data, 0).unwrap().data_len, size as u64);
+
+        // Account grew without a migration
+        assert!(matches!(
+            MemoryMap::load(&mut data, 0, MapType::Small),
+            Err(MemoryMapError::LengthMismatch)
+        ));
+        assert!(matches!(
+            MemoryMap::load_auto(&mut data[..size - 8], 0),
+            Err(MemoryMapError::LengthMismatch)
+        ));
+
+        migrate(&mut data, 0).unwrap();
+        assert!(MemoryMap::load(&mut data, 0, MapType::Small).is_ok());
+
+        // Headers written before the length was recorded are not checked
+        header_mut(&mut data, 0).unwrap().data_len = 0;
+        assert!(MemoryMap::load(&mut data[..size], 0, MapType::Small).is_ok());
+    }
+
+    #[test]
+    fn test_leaf_hint_flag() {
+        let size = HEADER_SIZE + MapType::Max.required_size();
+        let (mut data, _) = create_aligned_memory(size);
+        let mut map =
+            MemoryMap::init_with_flags(&mut data, 0, MapType::Max, FLAG_LEAF_HINT).unwrap();
+        for _ in 0..130 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(3).unwrap();
+        assert_eq!(header_mut(&mut data, 0).unwrap().leaf_hint, 2);
+
+        // The cached block is used before the lower free slot
+        let mut map = MemoryMap::load(&mut data, 0, MapType::Max).unwrap();
+        assert_eq!(map.alloc().unwrap(), 130);
+        header_mut(&mut data, 0).unwrap().leaf_hint = 0;
+        let mut map = MemoryMap::load(&mut data, 0, MapType::Max).unwrap();
+        assert_eq!(map.alloc().unwrap(), 3);
+
+        // Without the flag the word is left alone
+        let mut map = MemoryMap::init(&mut data, 0, MapType::Max).unwrap();
+        map.alloc().unwrap();
+        assert_eq!(header_mut(&mut data, 0).unwrap().leaf_hint, 0);
+
+        assert!(matches!(
+            MemoryMap::init_with_flags(&mut data, 0, MapType::Max, 0x80),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+}
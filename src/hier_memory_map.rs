@@ -0,0 +1,1185 @@
+use crate::{
+    backend::{Backend, RawBackend},
+    checksum::checksum_term,
+    get_first_zero_bit::{get_first_zero_bit, get_last_zero_bit},
+    header::{
+        FLAG_CHECKSUM, FLAG_COUNTERS, FLAG_FROZEN, FLAG_FROZEN_DEALLOC, FLAG_LEAF_HINT,
+        FLAG_VERIFY_WRITES,
+    },
+    trace::trace_event,
+    MapHeader, MemoryMapError,
+};
+use core::{
+    mem::{align_of, size_of},
+    ptr::NonNull,
+};
+
+/// Outcome of [`HierMemoryMap::dealloc_batch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchDealloc {
+    /// Slots that were allocated and are now free
+    pub freed: usize,
+    /// Bitmap words written, each at most once
+    pub words_written: usize,
+}
+
+/// Hierarchical memory map with `LEVELS` levels and `L1_BITS` bits in the
+/// first level
+///
+/// Every word below the first level has 64 bits. A set bit in the last level
+/// marks an allocated slot, a set bit in any other level marks a full word in
+/// the level below. Index `i` is encoded as the path of bit positions from the
+/// first level down, 6 bits per level below the first.
+///
+/// Words are read and written through the [`Backend`] `B`, by default in place
+/// in the account bytes. Maps created through [`MemoryMap::init`] or
+/// [`MemoryMap::load`] also keep a pointer to their [`MapHeader`] for the
+/// persisted allocation hints.
+///
+/// [`MemoryMap::init`]: crate::MemoryMap::init
+/// [`MemoryMap::load`]: crate::MemoryMap::load
+#[derive(Clone)]
+pub struct HierMemoryMap<const L1_BITS: usize, const LEVELS: usize, B = RawBackend> {
+    backend: B,
+    header: Option<NonNull<MapHeader>>,
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize> HierMemoryMap<L1_BITS, LEVELS> {
+    /// Create a new memory map over `size` bytes at `memory`
+    ///
+    /// The memory must stay valid for reads and writes of `size` bytes while
+    /// the map is used, which is why this is crate-private; public callers go
+    /// through [`HierMemoryMap::new_from_slice`] or
+    /// [`HierMemoryMap::with_backend`].
+    pub(crate) fn new(memory: NonNull<u8>, size: usize) -> Result<Self, MemoryMapError> {
+        // Safety: callers pass memory valid for `size` bytes for the map's use
+        let backend = unsafe { RawBackend::new(memory, size) };
+        Self::with_backend(backend.clamped(Self::REQUIRED_SIZE))
+    }
+
+    /// Create a new memory map at `offset` in a mutable byte slice
+    pub fn new_from_slice(data: &mut [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        let (memory, size) = crate::slice_memory(data, offset, align_of::<u64>())?;
+        Self::new(memory, size)
+    }
+
+    /// Create a new memory map at a 4-byte aligned `offset` in a mutable byte
+    /// slice, accessing words as pairs of u32 halves
+    pub fn new_from_slice_u32(data: &mut [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        let (memory, size) = crate::slice_memory(data, offset, align_of::<u32>())?;
+        // Safety: `memory` points into `data`, which has `size` bytes left
+        let backend = unsafe { RawBackend::new_u32(memory, size) };
+        Self::with_backend(backend.clamped(Self::REQUIRED_SIZE))
+    }
+
+    /// Raw bitmap bytes, all levels in order
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safety: `with_backend` checked that `REQUIRED_SIZE` bytes are available
+        unsafe { core::slice::from_raw_parts(self.backend.memory().as_ptr(), Self::REQUIRED_SIZE) }
+    }
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Number of indices the map can hand out
+    pub const CAPACITY: usize = L1_BITS << (6 * (LEVELS - 1));
+
+    /// Number of bytes occupied by the bitmap
+    pub const REQUIRED_SIZE: usize = Self::level_start(LEVELS) * size_of::<u64>();
+
+    /// Word offset of the leaf level
+    pub(crate) const LEAF_START: usize = Self::level_start(LEVELS - 1);
+
+    /// Number of words in the leaf level
+    pub(crate) const LEAF_WORDS: usize = Self::level_start(LEVELS) - Self::LEAF_START;
+
+    /// Bits of the first-level word that are in use
+    const FIRST_LEVEL_MASK: u64 = u64::MAX >> (64 - L1_BITS);
+
+    /// Bits of each leaf word that map to a slot
+    pub(crate) const LEAF_MASK: u64 = if LEVELS == 1 {
+        Self::FIRST_LEVEL_MASK
+    } else {
+        u64::MAX
+    };
+
+    /// Reject impossible geometries at compile time
+    const GEOMETRY: () = assert!(
+        L1_BITS >= 1 && L1_BITS <= 64 && LEVELS >= 1 && LEVELS <= 4,
+        "HierMemoryMap needs 1..=64 first-level bits and 1..=4 levels"
+    );
+
+    /// Word offset of `level` within the bitmap
+    pub const fn level_start(level: usize) -> usize {
+        let mut start = 0;
+        let mut words = 1;
+        let mut i = 0;
+        while i < level {
+            start += words;
+            words = if i == 0 { L1_BITS } else { words * 64 };
+            i += 1;
+        }
+        start
+    }
+
+    /// Create a new memory map on top of `backend`
+    pub fn with_backend(backend: B) -> Result<Self, MemoryMapError> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::GEOMETRY;
+
+        // Check if there's enough memory
+        if backend.size() < Self::REQUIRED_SIZE {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        Ok(Self {
+            backend,
+            header: None,
+        })
+    }
+
+    /// Keep the persisted hints in `header`
+    pub(crate) fn set_header(&mut self, header: NonNull<MapHeader>) {
+        self.header = Some(header);
+    }
+
+    /// Header attached by `MemoryMap::init`/`load`, if any
+    pub(crate) const fn header(&self) -> Option<NonNull<MapHeader>> {
+        self.header
+    }
+
+    /// Give back the backend the map was built on
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    /// Check whether every slot is allocated
+    pub fn is_full(&self) -> bool {
+        matches!(
+            self.backend.read_word(0),
+            Ok(word) if word & Self::FIRST_LEVEL_MASK == Self::FIRST_LEVEL_MASK
+        )
+    }
+
+    /// Check whether slot `index` is allocated
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        if index >= Self::CAPACITY {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(self.leaf_word(index >> 6)? & (1 << (index & 0x3f)) != 0)
+    }
+
+    /// Check whether leaf word `block` has no allocated slot
+    pub fn block_is_empty(&self, block: usize) -> Result<bool, MemoryMapError> {
+        if self.block_marked_full(block)? {
+            return Ok(false);
+        }
+        Ok(self.leaf_word(block)? & Self::LEAF_MASK == 0)
+    }
+
+    /// Check whether every slot of leaf word `block` is allocated
+    pub fn block_is_full(&self, block: usize) -> Result<bool, MemoryMapError> {
+        if self.block_marked_full(block)? {
+            return Ok(true);
+        }
+        Ok(self.leaf_word(block)? & Self::LEAF_MASK == Self::LEAF_MASK)
+    }
+
+    /// Full bit of leaf word `block` in the level above, if there is one
+    fn block_marked_full(&self, block: usize) -> Result<bool, MemoryMapError> {
+        if block >= Self::LEAF_WORDS {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        if LEVELS == 1 {
+            return Ok(false);
+        }
+        self.summary_bit(LEVELS - 2, block)
+    }
+
+    /// Bit `position` of `level`, set for allocated leaves and for full subtrees
+    pub(crate) fn summary_bit(
+        &self,
+        level: usize,
+        position: usize,
+    ) -> Result<bool, MemoryMapError> {
+        let word = self
+            .backend
+            .read_word(Self::level_start(level) + (position >> 6))?;
+        Ok(word & (1 << (position & 0x3f)) != 0)
+    }
+
+    /// Allocate a new slot
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        // Before the policy advances its cursor, so a refused alloc changes nothing
+        self.check_frozen(FLAG_FROZEN)?;
+
+        // All first-level bits set - the map is full, no need to descend
+        if self.is_full() {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+
+        // Placement chosen by a policy other than first-fit, already clear of
+        // the reserved range
+        if let Some(index) = self.policy_candidate()? {
+            self.mark_allocated(index)?;
+            return Ok(index);
+        }
+
+        // The hints may point into the reserved range, search past it instead
+        if let Some(reserved) = self.reserved_range() {
+            let index = self.skip_reserved(self.find_first_free()?, &reserved)?;
+            self.mark_allocated(index)?;
+            return Ok(index);
+        }
+
+        // Last block that had room, skips the descent when it still does
+        if let Some(hint) = self.leaf_hint() {
+            if let Some(index) = self.alloc_in_block(hint)? {
+                return Ok(index);
+            }
+        }
+
+        // Every block before the hint is full, a free bit there is the first one
+        if let Some(hint) = self.first_free_block() {
+            if let Some(index) = self.alloc_in_block(hint)? {
+                self.set_leaf_hint(hint);
+                return Ok(index);
+            }
+        }
+
+        // Descend to the leaf level following the first zero bit of each word
+        let index = self.find_first_free()?;
+
+        self.mark_allocated(index)?;
+        self.set_first_free_block(index >> 6);
+        self.set_leaf_hint(index >> 6);
+
+        Ok(index)
+    }
+
+    /// Allocate the first free slot of leaf word `block`, if it has one
+    fn alloc_in_block(&mut self, block: usize) -> Result<Option<usize>, MemoryMapError> {
+        if block >= Self::LEAF_WORDS {
+            return Ok(None);
+        }
+
+        let bits = if LEVELS == 1 { L1_BITS } else { 64 };
+        let word = self.backend.read_word(Self::LEAF_START + block)?;
+        let Ok(bit) = get_first_zero_bit(word, bits) else {
+            return Ok(None);
+        };
+
+        let index = (block << 6) + bit;
+        self.mark_allocated(index)?;
+
+        Ok(Some(index))
+    }
+
+    /// Lowest free slot, without allocating it
+    pub(crate) fn find_first_free(&self) -> Result<usize, MemoryMapError> {
+        let mut index = 0;
+        for level in 0..LEVELS {
+            let bits = if level == 0 { L1_BITS } else { 64 };
+            let word = self.backend.read_word(Self::level_start(level) + index)?;
+            index = (index << 6) + get_first_zero_bit(word, bits)?;
+        }
+        Ok(index)
+    }
+
+    /// Highest free slot, without allocating it
+    pub(crate) fn find_last_free(&self) -> Result<usize, MemoryMapError> {
+        let mut index = 0;
+        for level in 0..LEVELS {
+            let bits = if level == 0 { L1_BITS } else { 64 };
+            let word = self.backend.read_word(Self::level_start(level) + index)?;
+            index = (index << 6) + get_last_zero_bit(word, bits)?;
+        }
+        Ok(index)
+    }
+
+    /// Allocate the lowest free slot at or above `floor`
+    pub fn alloc_above(&mut self, floor: usize) -> Result<usize, MemoryMapError> {
+        if floor >= Self::CAPACITY {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.check_frozen(FLAG_FROZEN)?;
+        let mut index = self
+            .find_free_above(floor)?
+            .ok_or(MemoryMapError::NoAvailableSlots)?;
+        if let Some(reserved) = self.reserved_range() {
+            index = self.skip_reserved(index, &reserved)?;
+        }
+        self.mark_allocated(index)?;
+
+        Ok(index)
+    }
+
+    /// Lowest free slot at or above `floor`, without allocating it
+    ///
+    /// Scans the rest of the word on the path to `floor` at each level, going
+    /// up until one has a free bit, then descends normally from there.
+    pub(crate) fn find_free_above(&self, floor: usize) -> Result<Option<usize>, MemoryMapError> {
+        let mut position = floor;
+        for level in (0..LEVELS).rev() {
+            let valid = if level == 0 {
+                Self::FIRST_LEVEL_MASK
+            } else {
+                u64::MAX
+            };
+            // The floor slot itself counts, above the leaves only later subtrees do
+            let from = (position & 0x3f) + usize::from(level != LEVELS - 1);
+            let word = self
+                .backend
+                .read_word(Self::level_start(level) + (position >> 6))?;
+            let free = !word & valid & u64::MAX.checked_shl(from as u32).unwrap_or(0);
+            if free != 0 {
+                let mut index = (position & !0x3f) + free.trailing_zeros() as usize;
+                for below in level + 1..LEVELS {
+                    let word = self.backend.read_word(Self::level_start(below) + index)?;
+                    index = (index << 6) + get_first_zero_bit(word, 64)?;
+                }
+                return Ok(Some(index));
+            }
+            position >>= 6;
+        }
+
+        Ok(None)
+    }
+
+    /// Highest free slot at or below `ceiling`, without allocating it
+    ///
+    /// Mirror of [`HierMemoryMap::find_free_above`]: scans the part of the word
+    /// below the path to `ceiling` at each level, then descends to the last
+    /// free slot.
+    pub(crate) fn find_free_below(&self, ceiling: usize) -> Result<Option<usize>, MemoryMapError> {
+        let mut position = ceiling;
+        for level in (0..LEVELS).rev() {
+            let valid = if level == 0 {
+                Self::FIRST_LEVEL_MASK
+            } else {
+                u64::MAX
+            };
+            // The ceiling slot itself counts, above the leaves only earlier subtrees do
+            let bit = position & 0x3f;
+            let below = if level == LEVELS - 1 {
+                u64::MAX >> (63 - bit)
+            } else {
+                (1 << bit) - 1
+            };
+            let word = self
+                .backend
+                .read_word(Self::level_start(level) + (position >> 6))?;
+            let free = !word & valid & below;
+            if free != 0 {
+                let mut index = (position & !0x3f) + 63 - free.leading_zeros() as usize;
+                for below in level + 1..LEVELS {
+                    let word = self.backend.read_word(Self::level_start(below) + index)?;
+                    index = (index << 6) + get_last_zero_bit(word, 64)?;
+                }
+                return Ok(Some(index));
+            }
+            position >>= 6;
+        }
+
+        Ok(None)
+    }
+
+    /// Allocate a new slot, preferring the leaf word `block`
+    ///
+    /// Falls back to a regular allocation when the block is full or out of range.
+    pub fn alloc_near(&mut self, block: usize) -> Result<usize, MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        let slots = block << 6..(block + 1) << 6;
+        if self
+            .reserved_range()
+            .is_some_and(|reserved| reserved.start < slots.end && slots.start < reserved.end)
+        {
+            return self.alloc();
+        }
+        match self.alloc_in_block(block)? {
+            Some(index) => Ok(index),
+            None => self.alloc(),
+        }
+    }
+
+    /// Allocate two slots in the same leaf word, adjacent when possible
+    ///
+    /// Either both slots are allocated or none is: if no leaf word has two
+    /// free bits or the second slot cannot be marked, the first is released.
+    pub fn alloc_twin(&mut self) -> Result<(usize, usize), MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        let reserved = self.reserved_range();
+        let mut leaf = 0;
+        while let Some(open) = self.next_open_leaf(leaf)? {
+            let free = !self.backend.read_word(Self::LEAF_START + open)?
+                & Self::LEAF_MASK
+                & !Self::reserved_bits(open, reserved.as_ref());
+            if free.count_ones() >= 2 {
+                let adjacent = free & (free >> 1);
+                let (first, second) = if adjacent != 0 {
+                    let bit = adjacent.trailing_zeros() as usize;
+                    (bit, bit + 1)
+                } else {
+                    let rest = free & (free - 1);
+                    (
+                        free.trailing_zeros() as usize,
+                        rest.trailing_zeros() as usize,
+                    )
+                };
+
+                let pair = ((open << 6) + first, (open << 6) + second);
+                self.mark_allocated(pair.0)?;
+                if let Err(err) = self.mark_allocated(pair.1) {
+                    // Release the first slot again so a failed pair leaves no trace
+                    self.dealloc(pair.0)?;
+                    return Err(err);
+                }
+                return Ok(pair);
+            }
+            leaf = open + 1;
+        }
+
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+
+    /// Allocate every slot of a fully free leaf word and return the first index
+    ///
+    /// Claims 64 slots with one leaf write instead of 64 separate allocations.
+    pub fn alloc_block(&mut self) -> Result<usize, MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        // A partial single-level map has no 64-slot block
+        if LEVELS == 1 && L1_BITS < 64 {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+
+        // Every block before the hint is full
+        let reserved = self.reserved_range();
+        let mut leaf = self.first_free_block().unwrap_or(0);
+        while let Some(open) = self.next_open_leaf(leaf)? {
+            if self.leaf_word(open)? == 0 && Self::reserved_bits(open, reserved.as_ref()) == 0 {
+                self.claim_bits(open, u64::MAX)?;
+                return Ok(open << 6);
+            }
+            leaf = open + 1;
+        }
+
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+
+    /// Deallocate a previously allocated slot
+    ///
+    /// Slots in the tail reserved past the slot limit cannot be freed.
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        // Check upper bound
+        if index >= self.slot_limit() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.check_frozen(FLAG_FROZEN_DEALLOC)?;
+
+        let leaf = self.backend.read_word(Self::LEAF_START + (index >> 6))?;
+        if leaf & (1 << (index & 0x3f)) != 0 {
+            self.add_used(-1);
+        }
+
+        // Clear the allocation bit and the full bits on the path above it
+        trace_event!("imm dealloc index={index}");
+        let mut position = index;
+        for level in (0..LEVELS).rev() {
+            let word = Self::level_start(level) + (position >> 6);
+            let value = self.backend.read_word(word)? & !(1 << (position & 0x3f));
+            self.write_word(word, value)?;
+            trace_event!("imm write level={level} word={word} value={value:#x}");
+            position >>= 6;
+        }
+
+        if self
+            .first_free_block()
+            .is_some_and(|hint| index >> 6 < hint)
+        {
+            self.set_first_free_block(index >> 6);
+        }
+
+        Ok(())
+    }
+
+    /// Deallocate every slot of `indices` with as few word writes as possible
+    ///
+    /// Leaf words are written in ascending order, then the summary words level
+    /// by level towards the first one. Every word is written at most once and
+    /// only if its value changes. Indices at or past the slot limit fail the
+    /// whole batch before anything is written, already free slots are skipped.
+    #[cfg(feature = "std")]
+    pub fn dealloc_batch(&mut self, indices: &[usize]) -> Result<BatchDealloc, MemoryMapError> {
+        let limit = self.slot_limit();
+        if indices.iter().any(|&index| index >= limit) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.check_frozen(FLAG_FROZEN_DEALLOC)?;
+        let mut sorted = indices.to_vec();
+        sorted.sort_unstable();
+
+        let mut result = BatchDealloc::default();
+        let mut positions = Vec::new();
+        for group in sorted.chunk_by(|a, b| a >> 6 == b >> 6) {
+            let block = group[0] >> 6;
+            let mask = group
+                .iter()
+                .fold(0, |mask, index| mask | 1 << (index & 0x3f));
+            let leaf = self.leaf_word(block)?;
+            if leaf & mask != 0 {
+                self.write_word(Self::LEAF_START + block, leaf & !mask)?;
+                result.words_written += 1;
+                result.freed += (leaf & mask).count_ones() as usize;
+                positions.push(block);
+            }
+        }
+        self.add_used(-(result.freed as i64));
+
+        // Leaf words freed into are no longer full, nor is anything above them
+        for level in (0..LEVELS - 1).rev() {
+            let mut parents = Vec::new();
+            for group in positions.chunk_by(|a, b| a >> 6 == b >> 6) {
+                let word = Self::level_start(level) + (group[0] >> 6);
+                let mask = group
+                    .iter()
+                    .fold(0, |mask, position| mask | 1 << (position & 0x3f));
+                let value = self.backend.read_word(word)?;
+                if value & mask != 0 {
+                    self.write_word(word, value & !mask)?;
+                    result.words_written += 1;
+                }
+                parents.push(group[0] >> 6);
+            }
+            positions = parents;
+        }
+
+        if let Some(&first) = sorted.first() {
+            if self
+                .first_free_block()
+                .is_some_and(|hint| first >> 6 < hint)
+            {
+                self.set_first_free_block(first >> 6);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Deallocate the 64 slots of the leaf word starting at `base`
+    ///
+    /// `base` must be a multiple of 64, as returned by `alloc_block`. A block
+    /// reaching into the reserved tail is rejected.
+    pub fn dealloc_block(&mut self, base: usize) -> Result<(), MemoryMapError> {
+        let limit = self.slot_limit();
+        if base >= limit || !base.is_multiple_of(64) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        // A block the tail starts in would free part of the tail
+        if limit < Self::CAPACITY && base + 64 > limit {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.check_frozen(FLAG_FROZEN_DEALLOC)?;
+
+        self.release_bits(base >> 6, u64::MAX)
+    }
+
+    /// Allocate the slots of `mask` in leaf word `block`, propagating a full word
+    pub(crate) fn claim_bits(&mut self, block: usize, mask: u64) -> Result<(), MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        let leaf = self.leaf_word(block)?;
+        let value = leaf | mask;
+        self.write_word(Self::LEAF_START + block, value)?;
+        trace_event!("imm claim block={block} mask={mask:#x} value={value:#x}");
+        self.verify_claimed(block, mask)?;
+        self.add_used(i64::from((mask & !leaf).count_ones()));
+        if value == u64::MAX {
+            self.mark_full_above(block)?;
+        }
+
+        Ok(())
+    }
+
+    /// Free the slots of `mask` in leaf word `block`
+    pub(crate) fn release_bits(&mut self, block: usize, mask: u64) -> Result<(), MemoryMapError> {
+        let leaf = self.leaf_word(block)?;
+        self.add_used(-i64::from((leaf & mask).count_ones()));
+        self.write_word(Self::LEAF_START + block, leaf & !mask)?;
+        trace_event!("imm release block={block} mask={mask:#x}");
+
+        // Clear the full bits on the path above the leaf word
+        let mut position = block;
+        for level in (0..LEVELS - 1).rev() {
+            let word = Self::level_start(level) + (position >> 6);
+            let value = self.backend.read_word(word)? & !(1 << (position & 0x3f));
+            self.write_word(word, value)?;
+            trace_event!("imm write level={level} word={word} value={value:#x}");
+            position >>= 6;
+        }
+
+        if self.first_free_block().is_some_and(|hint| block < hint) {
+            self.set_first_free_block(block);
+        }
+
+        Ok(())
+    }
+
+    /// Mark every slot from `slots` up to the capacity as allocated
+    ///
+    /// Subtrees lying entirely past `slots` are marked full at every level, so
+    /// neither `alloc` nor `alloc_near` can reach them.
+    pub(crate) fn reserve_tail(&mut self, slots: usize) -> Result<(), MemoryMapError> {
+        self.bump_generation();
+        for level in 0..LEVELS {
+            // Each bit at this level covers `span` slots
+            let span = 1 << (6 * (LEVELS - 1 - level));
+            let width = Self::CAPACITY / span;
+            let mut position = slots.div_ceil(span);
+            while position < width {
+                let bit = position & 0x3f;
+                let count = (width - position).min(64 - bit);
+                let word = Self::level_start(level) + (position >> 6);
+                let value = self.backend.read_word(word)? | ((u64::MAX >> (64 - count)) << bit);
+                self.write_word(word, value)?;
+                position += count;
+            }
+        }
+
+        // The boundary block may have become full together with slots that
+        // were already allocated
+        if slots < Self::CAPACITY && self.leaf_word(slots >> 6)? == u64::MAX {
+            self.mark_full_above(slots >> 6)?;
+        }
+
+        Ok(())
+    }
+
+    /// Persisted hint: every leaf block before it is full
+    fn first_free_block(&self) -> Option<usize> {
+        // Safety: the header outlives the map, like the bitmap itself
+        self.header
+            .map(|header| unsafe { header.as_ref() }.first_free_block as usize)
+    }
+
+    fn set_first_free_block(&mut self, block: usize) {
+        if let Some(mut header) = self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            unsafe { header.as_mut() }.first_free_block = block as u64;
+        }
+    }
+
+    /// Persisted hint: last block that had free slots, if enabled at init
+    fn leaf_hint(&self) -> Option<usize> {
+        // Safety: the header outlives the map, like the bitmap itself
+        let header = unsafe { self.header?.as_ref() };
+        header
+            .has_flag(FLAG_LEAF_HINT)
+            .then_some(header.leaf_hint as usize)
+    }
+
+    fn set_leaf_hint(&mut self, block: usize) {
+        if let Some(mut header) = self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            let header = unsafe { header.as_mut() };
+            if header.has_flag(FLAG_LEAF_HINT) {
+                header.leaf_hint = block as u64;
+            }
+        }
+    }
+
+    /// Raw bitmap word `index`, counted from the first level
+    pub(crate) fn word_at(&self, index: usize) -> Result<u64, MemoryMapError> {
+        self.backend.read_word(index)
+    }
+
+    /// Overwrite bitmap word `index`, counted from the first level
+    pub(crate) fn set_word_at(&mut self, index: usize, value: u64) -> Result<(), MemoryMapError> {
+        trace_event!("imm write word={index} value={value:#x}");
+        self.bump_generation();
+        self.write_word(index, value)
+    }
+
+    /// Raw leaf word `block`, bits past the capacity included
+    pub(crate) fn leaf_word(&self, block: usize) -> Result<u64, MemoryMapError> {
+        self.backend.read_word(Self::LEAF_START + block)
+    }
+
+    /// First leaf word at or after `leaf` that is not marked full above it
+    pub(crate) fn next_open_leaf(&self, mut leaf: usize) -> Result<Option<usize>, MemoryMapError> {
+        'leaves: while leaf < Self::LEAF_WORDS {
+            for level in 0..LEVELS - 1 {
+                // Each bit at this level covers `1 << shift` leaf words
+                let shift = 6 * (LEVELS - 2 - level);
+                let position = leaf >> shift;
+                let word = self
+                    .backend
+                    .read_word(Self::level_start(level) + (position >> 6))?;
+                if word & (1 << (position & 0x3f)) != 0 {
+                    leaf = (position + 1) << shift;
+                    continue 'leaves;
+                }
+            }
+            return Ok(Some(leaf));
+        }
+
+        Ok(None)
+    }
+
+    /// Set the leaf bit of `index` and propagate full words upwards
+    pub(crate) fn mark_allocated(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        trace_event!("imm alloc index={index}");
+        let mut position = index;
+        for level in (0..LEVELS).rev() {
+            let word = Self::level_start(level) + (position >> 6);
+            let value = self.backend.read_word(word)? | (1 << (position & 0x3f));
+            self.write_word(word, value)?;
+            trace_event!("imm write level={level} word={word} value={value:#x}");
+            if value != u64::MAX {
+                break;
+            }
+            position >>= 6;
+        }
+        self.verify_claimed(index >> 6, 1 << (index & 0x3f))?;
+        self.add_used(1);
+
+        Ok(())
+    }
+
+    /// Fail with `MapFrozen` if the header has `flag` set
+    pub(crate) fn check_frozen(&self, flag: u8) -> Result<(), MemoryMapError> {
+        // Safety: the header outlives the map, like the bitmap itself
+        let frozen = self
+            .header
+            .is_some_and(|header| unsafe { header.as_ref() }.has_flag(FLAG_FROZEN | flag));
+        if frozen {
+            return Err(MemoryMapError::MapFrozen);
+        }
+        Ok(())
+    }
+
+    /// Read leaf word `block` back when write verification is enabled
+    fn verify_claimed(&self, block: usize, mask: u64) -> Result<(), MemoryMapError> {
+        // Safety: the header outlives the map, like the bitmap itself
+        let verify = self
+            .header
+            .is_some_and(|header| unsafe { header.as_ref() }.has_flag(FLAG_VERIFY_WRITES));
+        if verify && self.leaf_word(block)? & mask != mask {
+            return Err(MemoryMapError::WriteVerificationFailed);
+        }
+        Ok(())
+    }
+
+    /// Set the bit of full leaf word `block` in its parent and propagate upwards
+    fn mark_full_above(&mut self, block: usize) -> Result<(), MemoryMapError> {
+        let mut position = block;
+        for level in (0..LEVELS - 1).rev() {
+            let word = Self::level_start(level) + (position >> 6);
+            let value = self.backend.read_word(word)? | (1 << (position & 0x3f));
+            self.write_word(word, value)?;
+            trace_event!("imm full level={level} word={word} value={value:#x}");
+            if value != u64::MAX {
+                break;
+            }
+            position >>= 6;
+        }
+
+        Ok(())
+    }
+
+    /// Number of allocated slots, from the header counter when there is one
+    ///
+    /// The header counter matches the leaf words and so includes the tail
+    /// reserved past the slot limit; it is left out here.
+    pub fn used_count(&self) -> Result<usize, MemoryMapError> {
+        let used = match self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            Some(header) => unsafe { header.as_ref() }.used_count as usize,
+            None => self.count_used()?,
+        };
+        Ok(used.saturating_sub(Self::CAPACITY - self.slot_limit()))
+    }
+
+    /// Recompute the header counter from the leaf words
+    ///
+    /// Returns the repaired count, without the reserved tail like `used_count`.
+    pub fn recount(&mut self) -> Result<usize, MemoryMapError> {
+        let used = self.count_used()?;
+        if let Some(mut header) = self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            unsafe { header.as_mut() }.used_count = used as u64;
+        }
+        Ok(used.saturating_sub(Self::CAPACITY - self.slot_limit()))
+    }
+
+    /// Popcount of every leaf word
+    fn count_used(&self) -> Result<usize, MemoryMapError> {
+        (0..Self::LEAF_WORDS).try_fold(0, |used, leaf| {
+            let word = self.backend.read_word(Self::LEAF_START + leaf)?;
+            Ok(used + (word & Self::LEAF_MASK).count_ones() as usize)
+        })
+    }
+
+    fn add_used(&mut self, delta: i64) {
+        if let Some(mut header) = self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            let header = unsafe { header.as_mut() };
+            header.used_count = header.used_count.saturating_add_signed(delta);
+            if delta != 0 {
+                header.generation = header.generation.wrapping_add(1);
+            }
+            if header.has_flag(FLAG_COUNTERS) {
+                if delta > 0 {
+                    header.total_allocs += delta.unsigned_abs();
+                } else {
+                    header.total_deallocs += delta.unsigned_abs();
+                }
+            }
+        }
+    }
+
+    /// Write bitmap word `index`, keeping the header checksum up to date
+    fn write_word(&mut self, index: usize, value: u64) -> Result<(), MemoryMapError> {
+        if let Some(mut header) = self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            let header = unsafe { header.as_mut() };
+            if header.has_flag(FLAG_CHECKSUM) {
+                let old = self.backend.read_word(index)?;
+                header.checksum = header
+                    .checksum
+                    .wrapping_sub(checksum_term(index, old))
+                    .wrapping_add(checksum_term(index, value));
+            }
+        }
+        self.backend.write_word(index, value)
+    }
+
+    /// Mark a bitmap change that does not go through `add_used`
+    fn bump_generation(&mut self) {
+        if let Some(mut header) = self.header {
+            // Safety: the header outlives the map, like the bitmap itself
+            let header = unsafe { header.as_mut() };
+            header.generation = header.generation.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_memory;
+
+    type OctetMemoryMap = HierMemoryMap<8, 3>;
+
+    #[test]
+    fn test_geometry_constants() {
+        assert_eq!(HierMemoryMap::<64, 1>::CAPACITY, 64);
+        assert_eq!(HierMemoryMap::<64, 1>::REQUIRED_SIZE, 8);
+        assert_eq!(HierMemoryMap::<4, 3>::CAPACITY, 16_384);
+        assert_eq!(HierMemoryMap::<4, 3>::level_start(2), 5);
+        assert_eq!(HierMemoryMap::<64, 4>::level_start(3), 4161);
+        assert_eq!(OctetMemoryMap::CAPACITY, 8 * 64 * 64);
+        assert_eq!(OctetMemoryMap::REQUIRED_SIZE, (1 + 8 + 8 * 64) * 8);
+    }
+
+    #[test]
+    fn test_custom_geometry_fill_and_reuse() {
+        let (mut data, ptr) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE);
+        data.fill(0);
+        let mut map = OctetMemoryMap::new(ptr, OctetMemoryMap::REQUIRED_SIZE).unwrap();
+
+        for expected in 0..OctetMemoryMap::CAPACITY {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(3_000).unwrap();
+        assert!(!map.is_full());
+        assert_eq!(map.alloc().unwrap(), 3_000);
+        assert!(matches!(
+            map.dealloc(OctetMemoryMap::CAPACITY),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_partial_single_level() {
+        let (mut data, ptr) = create_aligned_memory(8);
+        data.fill(0);
+        let mut map = HierMemoryMap::<5, 1>::new(ptr, 8).unwrap();
+
+        for expected in 0..5 {
+            assert_eq!(map.alloc_near(0).unwrap(), expected);
+        }
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+    }
+
+    #[test]
+    fn test_new_from_slice() {
+        let (mut data, _) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE + 8);
+        let mut map = OctetMemoryMap::new_from_slice(&mut data, 8).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+        assert_eq!(data[8 + OctetMemoryMap::LEAF_START * 8], 1);
+
+        assert!(matches!(
+            OctetMemoryMap::new_from_slice(&mut data, 24),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_reserve_tail() {
+        let (mut data, ptr) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE);
+        data.fill(0);
+        let mut map = OctetMemoryMap::new(ptr, OctetMemoryMap::REQUIRED_SIZE).unwrap();
+        map.reserve_tail(4_100).unwrap();
+
+        for expected in 0..4_100 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+        // Blocks past the limit are already marked full
+        assert!(matches!(
+            map.alloc_near(100),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+    }
+
+    #[test]
+    fn test_alloc_twin() {
+        let (mut data, ptr) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE);
+        data.fill(0);
+        let mut map = OctetMemoryMap::new(ptr, OctetMemoryMap::REQUIRED_SIZE).unwrap();
+
+        assert_eq!(map.alloc_twin().unwrap(), (0, 1));
+        for _ in 2..63 {
+            map.alloc().unwrap();
+        }
+
+        // Only bit 63 is left in the first leaf word, the pair moves on
+        assert_eq!(map.alloc_twin().unwrap(), (64, 65));
+
+        // Non-adjacent free bits in the same word
+        map.dealloc(10).unwrap();
+        map.dealloc(20).unwrap();
+        for _ in 66..128 {
+            map.alloc_near(1).unwrap();
+        }
+        assert_eq!(map.alloc_twin().unwrap(), (10, 20));
+    }
+
+    #[test]
+    fn test_alloc_twin_is_atomic() {
+        let (mut data, ptr) = create_aligned_memory(8);
+        data.fill(0);
+        let mut map = HierMemoryMap::<3, 1>::new(ptr, 8).unwrap();
+
+        assert_eq!(map.alloc_twin().unwrap(), (0, 1));
+        assert!(matches!(
+            map.alloc_twin(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        // The single remaining slot is still free
+        assert_eq!(map.alloc().unwrap(), 2);
+        assert!(map.is_full());
+    }
+
+    #[test]
+    fn test_alloc_block() {
+        let (mut data, ptr) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE);
+        data.fill(0);
+        let mut map = OctetMemoryMap::new(ptr, OctetMemoryMap::REQUIRED_SIZE).unwrap();
+
+        assert_eq!(map.alloc().unwrap(), 0);
+        // The first leaf word is partly used, the block comes from the next one
+        assert_eq!(map.alloc_block().unwrap(), 64);
+        assert_eq!(map.alloc().unwrap(), 1);
+        assert_eq!(map.alloc_near(1).unwrap(), 2);
+        assert_eq!(map.alloc_block().unwrap(), 128);
+
+        map.dealloc_block(64).unwrap();
+        assert_eq!(map.alloc_block().unwrap(), 64);
+        assert!(matches!(
+            map.dealloc_block(65),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            map.dealloc_block(OctetMemoryMap::CAPACITY),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        // Blocks alone fill the map and propagate the full bits to the root
+        map.dealloc(0).unwrap();
+        map.dealloc(1).unwrap();
+        map.dealloc(2).unwrap();
+        assert_eq!(map.alloc_block().unwrap(), 0);
+        while map.alloc_block().is_ok() {}
+        assert!(map.is_full());
+        assert_eq!(map.used_count().unwrap(), OctetMemoryMap::CAPACITY);
+
+        map.dealloc_block(4_032).unwrap();
+        assert!(!map.is_full());
+        assert_eq!(map.alloc().unwrap(), 4_032);
+    }
+
+    #[test]
+    fn test_alloc_block_partial_single_level() {
+        let (mut data, ptr) = create_aligned_memory(8);
+        data.fill(0);
+        let mut map = HierMemoryMap::<5, 1>::new(ptr, 8).unwrap();
+        assert!(matches!(
+            map.alloc_block(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+
+        let mut map = HierMemoryMap::<64, 1>::new(ptr, 8).unwrap();
+        assert_eq!(map.alloc_block().unwrap(), 0);
+        assert!(map.is_full());
+        map.dealloc_block(0).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+    }
+
+    /// Backend whose word 0 has bit 5 stuck at zero
+    struct StuckBitBackend {
+        words: Vec<u64>,
+    }
+
+    impl Backend for StuckBitBackend {
+        fn size(&self) -> usize {
+            self.words.len() * 8
+        }
+
+        fn read_word(&self, index: usize) -> Result<u64, MemoryMapError> {
+            self.words
+                .get(index)
+                .copied()
+                .ok_or(MemoryMapError::IndexOutOfBounds)
+        }
+
+        fn write_word(&mut self, index: usize, value: u64) -> Result<(), MemoryMapError> {
+            let stuck = if index == 0 { !(1 << 5) } else { u64::MAX };
+            *self
+                .words
+                .get_mut(index)
+                .ok_or(MemoryMapError::IndexOutOfBounds)? = value & stuck;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verify_writes() {
+        let backend = StuckBitBackend { words: vec![0] };
+        let mut map = HierMemoryMap::<64, 1, _>::with_backend(backend).unwrap();
+        let mut header = MapHeader {
+            flags: FLAG_VERIFY_WRITES,
+            ..MapHeader::new(crate::MapType::Tiny)
+        };
+        map.set_header(NonNull::from(&mut header));
+
+        for expected in 0..5 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(matches!(
+            map.alloc(),
+            Err(MemoryMapError::WriteVerificationFailed)
+        ));
+        assert!(matches!(
+            map.alloc_block(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+
+        // Without the flag the lost write goes unnoticed
+        header.flags = 0;
+        map.set_header(NonNull::from(&mut header));
+        assert_eq!(map.alloc().unwrap(), 5);
+        assert_eq!(header.used_count, 6);
+    }
+
+    #[test]
+    fn test_alloc_twin_rolls_back() {
+        let mut header = MapHeader {
+            flags: FLAG_VERIFY_WRITES,
+            ..MapHeader::new(crate::MapType::Tiny)
+        };
+        let backend = StuckBitBackend { words: vec![0] };
+        let mut map = HierMemoryMap::<64, 1, _>::with_backend(backend).unwrap();
+        map.set_header(NonNull::from(&mut header));
+        for _ in 0..4 {
+            map.alloc().unwrap();
+        }
+
+        // The pair lands on (4, 5) and slot 5 does not stick, so 4 is released
+        assert!(matches!(
+            map.alloc_twin(),
+            Err(MemoryMapError::WriteVerificationFailed)
+        ));
+        assert!(!map.is_allocated(4).unwrap());
+        assert_eq!(header.used_count, 4);
+    }
+
+    #[test]
+    fn test_alloc_above() {
+        let (mut data, ptr) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE);
+        data.fill(0);
+        let mut map = OctetMemoryMap::new(ptr, OctetMemoryMap::REQUIRED_SIZE).unwrap();
+
+        assert_eq!(map.alloc_above(100).unwrap(), 100);
+        assert_eq!(map.alloc_above(100).unwrap(), 101);
+        assert_eq!(map.alloc_above(0).unwrap(), 0);
+
+        // Skips a full leaf word and a full subtree above it
+        for expected in 4_000..8_192 {
+            assert_eq!(map.alloc_above(4_000).unwrap(), expected);
+        }
+        assert_eq!(map.alloc_above(4_000).unwrap(), 8_192);
+        assert_eq!(map.alloc_above(90).unwrap(), 90);
+
+        let last = OctetMemoryMap::CAPACITY - 1;
+        assert_eq!(map.alloc_above(last).unwrap(), last);
+        assert!(matches!(
+            map.alloc_above(last),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert!(matches!(
+            map.alloc_above(OctetMemoryMap::CAPACITY),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_dealloc_batch_writes() {
+        let (mut data, ptr) = create_aligned_memory(OctetMemoryMap::REQUIRED_SIZE);
+        data.fill(0);
+        let mut map = OctetMemoryMap::new(ptr, OctetMemoryMap::REQUIRED_SIZE).unwrap();
+        for _ in 0..8_192 {
+            map.alloc().unwrap();
+        }
+
+        // Two full leaf words of one region and one of the next: 3 leaves,
+        // 2 words of the middle level, the first-level word once
+        let batch = map.dealloc_batch(&[4_100, 3, 70, 5, 3]).unwrap();
+        assert_eq!(
+            batch,
+            BatchDealloc {
+                freed: 4,
+                words_written: 3 + 2 + 1,
+            }
+        );
+        for index in [3, 5, 70, 4_100] {
+            assert_eq!(map.alloc().unwrap(), index);
+        }
+        assert_eq!(map.alloc().unwrap(), 8_192);
+
+        // Free slots cost nothing, bad indices fail before any write
+        assert_eq!(
+            map.dealloc_batch(&[9_000]).unwrap(),
+            BatchDealloc::default()
+        );
+        assert!(matches!(
+            map.dealloc_batch(&[0, OctetMemoryMap::CAPACITY]),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(map.is_allocated(0).unwrap());
+    }
+}
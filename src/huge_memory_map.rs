@@ -0,0 +1,77 @@
+use crate::hier_memory_map::HierMemoryMap;
+
+/// Huge memory map implementation (4 levels, 64 bits at every level)
+pub type HugeMemoryMap = HierMemoryMap<64, 4>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_memory, MapType, MemoryMapError};
+
+    #[test]
+    fn test_huge_map_basic_operations() {
+        let required_size = MapType::Huge.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+
+        assert!(matches!(
+            HugeMemoryMap::new(ptr, required_size - 8),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        let mut map = HugeMemoryMap::new(ptr, data.len()).unwrap();
+        for expected in 0..130 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+
+        map.dealloc(64).unwrap();
+        assert_eq!(map.alloc().unwrap(), 64);
+
+        assert!(matches!(
+            map.dealloc(MapType::Huge.capacity()),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_huge_map_level_transitions() {
+        let required_size = MapType::Huge.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+        let mut map = HugeMemoryMap::new(ptr, data.len()).unwrap();
+
+        // Fill the first third-level block (64 * 64 slots) by hand
+        data[65 * 8..66 * 8].fill(0xff);
+        data[8..16].copy_from_slice(&1u64.to_le_bytes());
+        data[4161 * 8..(4161 + 64) * 8].fill(0xff);
+
+        // Next allocation must come from second-level bit 1
+        assert_eq!(map.alloc().unwrap(), 1 << 12);
+
+        // Freeing a slot in the full block makes it available again
+        map.dealloc(100).unwrap();
+        assert_eq!(map.alloc().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_huge_map_top_bits() {
+        let required_size = MapType::Huge.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+        let mut map = HugeMemoryMap::new(ptr, data.len()).unwrap();
+
+        // Pretend everything below the last first-level block is taken
+        data[..8].copy_from_slice(&(u64::MAX >> 1).to_le_bytes());
+
+        let index = map.alloc().unwrap();
+        assert_eq!(index, 63 << 18);
+
+        let last = MapType::Huge.capacity() - 1;
+        let near = map.alloc_near(last >> 6).unwrap();
+        assert_eq!(near, last & !0x3f);
+
+        map.dealloc(index).unwrap();
+        assert!(!map.is_full());
+        assert_eq!(map.alloc().unwrap(), index);
+    }
+}
@@ -0,0 +1,404 @@
+use crate::{MapType, MemoryMapError};
+use core::mem::size_of;
+
+/// Position of the data region relative to the bitmap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataPlacement {
+    /// Bitmap first, data region right after it
+    #[default]
+    AfterBitmap,
+    /// Data region first, bitmap appended after it
+    BeforeBitmap,
+}
+
+/// Byte layout of a bitmap and its companion data region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotLayout {
+    map_type: MapType,
+    placement: DataPlacement,
+    bitmap_offset: usize,
+    data_offset: usize,
+    item_size: usize,
+    stride: usize,
+    slots: usize,
+    end_offset: usize,
+}
+
+/// Builder for [`SlotLayout`]
+#[derive(Debug, Clone, Copy)]
+pub struct SlotLayoutBuilder {
+    map_type: MapType,
+    item_size: usize,
+    item_align: usize,
+    base_offset: usize,
+    placement: DataPlacement,
+    slots: Option<usize>,
+}
+
+impl SlotLayout {
+    /// Start building a layout for `map_type` with `item_size` bytes per slot
+    pub const fn builder(map_type: MapType, item_size: usize) -> SlotLayoutBuilder {
+        SlotLayoutBuilder {
+            map_type,
+            item_size,
+            item_align: 1,
+            base_offset: 0,
+            placement: DataPlacement::AfterBitmap,
+            slots: None,
+        }
+    }
+
+    /// Map type whose bitmap the layout places
+    pub const fn map_type(&self) -> MapType {
+        self.map_type
+    }
+
+    /// Whether the data region sits before or after the bitmap
+    pub const fn placement(&self) -> DataPlacement {
+        self.placement
+    }
+
+    /// Offset of the bitmap, to be passed to `MemoryMap::new_from_slice`
+    pub const fn bitmap_offset(&self) -> usize {
+        self.bitmap_offset
+    }
+
+    /// Offset of slot 0 in the data region
+    pub const fn data_offset(&self) -> usize {
+        self.data_offset
+    }
+
+    /// Size of one item as given to the builder, without alignment padding
+    pub const fn item_size(&self) -> usize {
+        self.item_size
+    }
+
+    /// Distance between consecutive slots, the item size rounded up to the
+    /// item alignment
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Number of slots backed by the data region
+    pub const fn slots(&self) -> usize {
+        self.slots
+    }
+
+    /// Total number of bytes the layout occupies from offset 0
+    pub const fn end_offset(&self) -> usize {
+        self.end_offset
+    }
+
+    /// Byte offset of the slot with the given index
+    pub fn index_to_offset(&self, index: usize) -> Result<usize, MemoryMapError> {
+        match self.placement {
+            DataPlacement::AfterBitmap => {
+                if index >= self.slots {
+                    return Err(MemoryMapError::InvalidIndex);
+                }
+                Ok(self.data_offset + index * self.stride)
+            }
+            DataPlacement::BeforeBitmap => {
+                // Data ends where the (unpadded) region stops, so walk back from there
+                let data_end = self.data_offset + self.slots * self.stride;
+                index_to_offset_before(data_end, self.slots, self.stride, index)
+                    .ok_or(MemoryMapError::InvalidIndex)
+            }
+        }
+    }
+
+    /// Check that an account of `data_len` bytes holds the whole layout
+    pub const fn validate(&self, data_len: usize) -> Result<(), MemoryMapError> {
+        if data_len < self.end_offset() {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(())
+    }
+}
+
+impl SlotLayoutBuilder {
+    /// Offset where the layout starts (bitmap or data, depending on placement)
+    pub const fn base_offset(mut self, base_offset: usize) -> Self {
+        self.base_offset = base_offset;
+        self
+    }
+
+    /// Put the data region before or after the bitmap
+    pub const fn placement(mut self, placement: DataPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Align every slot to `item_align` bytes from offset 0, padding items
+    /// and the start of the data region as needed
+    ///
+    /// Must be a power of two, `build` fails with `AlignmentError` otherwise.
+    pub const fn item_align(mut self, item_align: usize) -> Self {
+        self.item_align = item_align;
+        self
+    }
+
+    /// Limit the data region to `slots` items instead of the full map capacity
+    pub const fn slots(mut self, slots: usize) -> Self {
+        self.slots = Some(slots);
+        self
+    }
+
+    /// Compute the offsets, failing if the layout does not fit in `usize`
+    /// or the slot count exceeds the map capacity
+    pub fn build(self) -> Result<SlotLayout, MemoryMapError> {
+        let capacity = self.map_type.capacity();
+        let slots = self.slots.unwrap_or(capacity);
+        if self.item_size == 0 || slots > capacity {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        if !self.item_align.is_power_of_two() {
+            return Err(MemoryMapError::AlignmentError);
+        }
+
+        let stride = align_up(self.item_size, self.item_align)?;
+        let data_len = slots
+            .checked_mul(stride)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+
+        let (bitmap_offset, data_offset, end_offset) = match self.placement {
+            DataPlacement::AfterBitmap => {
+                let bitmap_offset = align_up(self.base_offset, size_of::<u64>())?;
+                let bitmap_end = bitmap_offset
+                    .checked_add(self.map_type.required_size())
+                    .ok_or(MemoryMapError::InsufficientMemory)?;
+                let data_offset = align_up(bitmap_end, self.item_align)?;
+                let data_end = data_offset
+                    .checked_add(data_len)
+                    .ok_or(MemoryMapError::InsufficientMemory)?;
+                (bitmap_offset, data_offset, data_end)
+            }
+            DataPlacement::BeforeBitmap => {
+                let data_offset = align_up(self.base_offset, self.item_align)?;
+                let data_end = data_offset
+                    .checked_add(data_len)
+                    .ok_or(MemoryMapError::InsufficientMemory)?;
+                let bitmap_offset = align_up(data_end, size_of::<u64>())?;
+                let bitmap_end = bitmap_offset
+                    .checked_add(self.map_type.required_size())
+                    .ok_or(MemoryMapError::InsufficientMemory)?;
+                (bitmap_offset, data_offset, bitmap_end)
+            }
+        };
+
+        Ok(SlotLayout {
+            map_type: self.map_type,
+            placement: self.placement,
+            bitmap_offset,
+            data_offset,
+            item_size: self.item_size,
+            stride,
+            slots,
+            end_offset,
+        })
+    }
+}
+
+/// Offset of a slot in a data region that ends at `data_end` and holds `slots`
+/// items, counting backwards from the end of the region
+///
+/// Used for accounts where records were written first and the bitmap was
+/// appended later, so the region end is the only fixed anchor.
+pub const fn index_to_offset_before(
+    data_end: usize,
+    slots: usize,
+    item_size: usize,
+    index: usize,
+) -> Option<usize> {
+    if index >= slots {
+        return None;
+    }
+    match (slots - index).checked_mul(item_size) {
+        Some(distance) if distance <= data_end => Some(data_end - distance),
+        _ => None,
+    }
+}
+
+/// Pair of maps whose regions overlap, as positions in the checked list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overlap {
+    pub first: usize,
+    pub second: usize,
+}
+
+/// Check that maps placed at the given `(offset, map_type)` positions of one
+/// buffer do not share any byte
+///
+/// Returns the first colliding pair, so overlapping layouts are caught before
+/// allocations start trampling each other.
+pub fn assert_disjoint(maps: &[(usize, MapType)]) -> Result<(), Overlap> {
+    for (first, &(offset, map_type)) in maps.iter().enumerate() {
+        let end = offset.saturating_add(map_type.required_size());
+        for (second, &(other_offset, other_type)) in maps.iter().enumerate().skip(first + 1) {
+            let other_end = other_offset.saturating_add(other_type.required_size());
+            if offset < other_end && other_offset < end {
+                return Err(Overlap { first, second });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Round an offset up to the next multiple of `align`, a power of two
+const fn align_up(offset: usize, align: usize) -> Result<usize, MemoryMapError> {
+    match offset.checked_add(align - 1) {
+        Some(padded) => Ok(padded & !(align - 1)),
+        None => Err(MemoryMapError::InvalidOffset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_after_bitmap_layout() {
+        let layout = SlotLayout::builder(MapType::Small, 16)
+            .base_offset(8)
+            .build()
+            .unwrap();
+
+        assert_eq!(layout.bitmap_offset(), 8);
+        assert_eq!(layout.data_offset(), 8 + MapType::Small.required_size());
+        assert_eq!(layout.index_to_offset(0).unwrap(), layout.data_offset());
+        assert_eq!(
+            layout.index_to_offset(3).unwrap(),
+            layout.data_offset() + 48
+        );
+        assert!(matches!(
+            layout.index_to_offset(MapType::Small.capacity()),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_before_bitmap_layout() {
+        // Legacy account: 10 records of 12 bytes at offset 0, bitmap appended after
+        let layout = SlotLayout::builder(MapType::Small, 12)
+            .placement(DataPlacement::BeforeBitmap)
+            .slots(10)
+            .build()
+            .unwrap();
+
+        assert_eq!(layout.data_offset(), 0);
+        // 120 bytes of data, rounded up to the next u64 boundary
+        assert_eq!(layout.bitmap_offset(), 120);
+        assert_eq!(layout.end_offset(), 120 + MapType::Small.required_size());
+
+        for index in 0..10 {
+            assert_eq!(layout.index_to_offset(index).unwrap(), index * 12);
+        }
+        assert!(layout.index_to_offset(10).is_err());
+
+        let padded = SlotLayout::builder(MapType::Small, 5)
+            .placement(DataPlacement::BeforeBitmap)
+            .slots(3)
+            .build()
+            .unwrap();
+        assert_eq!(padded.bitmap_offset(), 16);
+        assert_eq!(padded.index_to_offset(2).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_item_alignment() {
+        // Tiny bitmap is 8 bytes, 12-byte items aligned to 16
+        let layout = SlotLayout::builder(MapType::Tiny, 12)
+            .base_offset(4)
+            .item_align(16)
+            .build()
+            .unwrap();
+        assert_eq!(layout.bitmap_offset(), 8);
+        assert_eq!(layout.data_offset(), 16);
+        assert_eq!(layout.stride(), 16);
+        assert_eq!(layout.index_to_offset(3).unwrap(), 64);
+        assert_eq!(layout.end_offset(), 16 + 64 * 16);
+        for index in 0..layout.slots() {
+            assert_eq!(layout.index_to_offset(index).unwrap() % 16, 0);
+        }
+
+        assert!(layout.validate(layout.end_offset()).is_ok());
+        assert!(matches!(
+            layout.validate(layout.end_offset() - 1),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        let before = SlotLayout::builder(MapType::Tiny, 6)
+            .placement(DataPlacement::BeforeBitmap)
+            .base_offset(3)
+            .item_align(4)
+            .slots(3)
+            .build()
+            .unwrap();
+        assert_eq!(before.data_offset(), 4);
+        assert_eq!(before.index_to_offset(1).unwrap(), 12);
+        assert_eq!(before.bitmap_offset(), 32);
+
+        assert!(matches!(
+            SlotLayout::builder(MapType::Tiny, 8).item_align(3).build(),
+            Err(MemoryMapError::AlignmentError)
+        ));
+    }
+
+    #[test]
+    fn test_index_to_offset_before() {
+        assert_eq!(index_to_offset_before(120, 10, 12, 0), Some(0));
+        assert_eq!(index_to_offset_before(120, 10, 12, 9), Some(108));
+        assert_eq!(index_to_offset_before(120, 10, 12, 10), None);
+        // Region would start before offset 0
+        assert_eq!(index_to_offset_before(100, 10, 12, 0), None);
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        assert!(SlotLayout::builder(MapType::Small, 0).build().is_err());
+        assert!(SlotLayout::builder(MapType::Small, 8)
+            .slots(MapType::Small.capacity() + 1)
+            .build()
+            .is_err());
+
+        // The bitmap end must not wrap past the end of the address space
+        assert!(matches!(
+            SlotLayout::builder(MapType::Small, 8)
+                .base_offset(usize::MAX - 8 * 8 + 1)
+                .build(),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_assert_disjoint() {
+        let small = MapType::Small.required_size();
+        assert!(assert_disjoint(&[]).is_ok());
+        assert!(assert_disjoint(&[
+            (0, MapType::Small),
+            (small, MapType::Tiny),
+            (small + 8, MapType::Standard),
+        ])
+        .is_ok());
+
+        assert_eq!(
+            assert_disjoint(&[
+                (0, MapType::Tiny),
+                (small, MapType::Small),
+                (small * 2 - 8, MapType::Tiny),
+            ]),
+            Err(Overlap {
+                first: 1,
+                second: 2
+            })
+        );
+        // Containment counts as overlap too
+        assert_eq!(
+            assert_disjoint(&[(0, MapType::Max), (64, MapType::Tiny)]),
+            Err(Overlap {
+                first: 0,
+                second: 1
+            })
+        );
+    }
+}
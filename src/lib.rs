@@ -1,17 +1,160 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// The layout tests index their allocation logs by position on purpose
+#![cfg_attr(test, allow(clippy::needless_range_loop))]
+
+mod adopted_memory_map;
+mod affinity;
+mod allocator;
+#[cfg(feature = "solana")]
+mod anchor;
+mod backend;
+mod buddy;
+mod bytes;
+mod chained;
+mod checksum;
+mod convert;
+mod counters;
+mod critbit;
+mod debug;
+#[cfg(feature = "cli")]
+mod dump;
+mod dynamic_memory_map;
+mod effects;
+mod epoch;
+mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fixtures;
+#[cfg(feature = "std")]
+mod forecast;
+mod fragmentation;
+mod free_run;
+mod free_stack;
+mod freeze;
 mod get_first_zero_bit;
+mod grow;
+mod header;
+mod hier_memory_map;
+mod huge_memory_map;
+mod layout;
+mod map_ref;
 mod max_memory_map;
+mod medium_memory_map;
+#[cfg(feature = "merkle")]
+mod merkle;
+mod namespace;
+mod order_id;
+mod owned;
+mod padded;
+#[cfg(feature = "solana")]
+mod pda;
+mod pending;
+mod pinned;
+mod policy;
+mod pool;
+#[cfg(kani)]
+mod proofs;
+#[cfg(test)]
+mod proptests;
+mod quota;
+mod rank;
+mod region;
+mod reserve;
+mod ring_buffer;
+mod scan_cursor;
+mod seal;
+#[cfg(feature = "std")]
+mod slab;
+mod slice_query;
+mod slot_hash_map;
+mod slot_list;
+mod slot_map;
 mod small_memory_map;
+#[cfg(feature = "borsh")]
+mod snapshot;
+mod stats;
+#[cfg(feature = "testvectors")]
+pub mod testvectors;
+mod tiny_memory_map;
+mod tombstone;
+mod trace;
 mod trade_memory_map;
+mod verify;
+mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-use crate::{
-    max_memory_map::MaxMemoryMap, small_memory_map::SmallMemoryMap,
-    trade_memory_map::StandardMemoryMap,
-};
-use solana_program::account_info::AccountInfo;
-use std::{
+use crate::trace::OpSpan;
+pub use adopted_memory_map::AdoptedMemoryMap;
+pub use affinity::AffinityTable;
+pub use allocator::IndexAllocator;
+#[cfg(feature = "solana")]
+pub use anchor::ANCHOR_DISCRIMINATOR_LEN;
+pub use backend::{Backend, RawBackend, WordAccess};
+pub use buddy::{BuddyMemoryMap, MAX_BUDDY_ORDER};
+pub use chained::ChainedMemoryMap;
+use core::{
     mem::{align_of, size_of},
     ptr::NonNull,
 };
+pub use counters::AllocCounters;
+pub use critbit::{CritbitIter, CritbitTree};
+pub use dynamic_memory_map::DynamicMemoryMap;
+pub use effects::AllocEffects;
+pub use epoch::EpochTags;
+#[cfg(feature = "std")]
+pub use forecast::forecast_exhaustion;
+pub use fragmentation::MemoryMapStats;
+pub use free_stack::FreeStack;
+pub use header::{
+    migrate, LayoutVersion, MapHeader, DYNAMIC_MAP_TYPE, FLAG_CHECKSUM, FLAG_COUNTERS, FLAG_FROZEN,
+    FLAG_FROZEN_DEALLOC, FLAG_LEAF_HINT, FLAG_VERIFY_WRITES, HEADER_MAGIC, HEADER_SIZE,
+    KNOWN_FLAGS,
+};
+pub use hier_memory_map::{BatchDealloc, HierMemoryMap};
+pub use huge_memory_map::HugeMemoryMap;
+pub use layout::{
+    assert_disjoint, index_to_offset_before, DataPlacement, Overlap, SlotLayout, SlotLayoutBuilder,
+};
+pub use map_ref::MemoryMapRef;
+pub use max_memory_map::MaxMemoryMap;
+pub use medium_memory_map::MediumMemoryMap;
+#[cfg(feature = "merkle")]
+pub use merkle::{merkle_leaf, verify_allocated, MerkleTree, MERKLE_NODE_SIZE};
+pub use namespace::Namespaces;
+pub use order_id::{OrderId, ORDER_SEQ_BITS_U64, ORDER_SLOT_BITS};
+pub use owned::{OwnedMemoryMap, OWNER_SIZE};
+pub use padded::PaddedMemoryMap;
+#[cfg(feature = "solana")]
+pub use pda::{slot_pda, slot_pda_with_bump, slot_seed};
+pub use pending::PendingDeallocs;
+pub use pinned::PinnedMemoryMap;
+pub use policy::AllocPolicy;
+pub use pool::{Pod, Pool};
+pub use quota::{Quota, Quotas};
+pub use region::{Region, RegionManager, REGISTRY_MAGIC};
+pub use ring_buffer::{SlotRingBuffer, RING_MAGIC};
+pub use scan_cursor::{ScanBatch, ScanCursor};
+#[cfg(feature = "std")]
+pub use slab::{SizeClass, SlabAllocator};
+pub use slice_query::SliceQuery;
+pub use slot_hash_map::SlotHashMap;
+pub use slot_list::{ListHead, SlotList, SlotListIter};
+pub use slot_map::SlotMap;
+pub use small_memory_map::SmallMemoryMap;
+#[cfg(feature = "borsh")]
+pub use snapshot::OccupancySnapshot;
+#[cfg(feature = "solana")]
+use solana_program::account_info::AccountInfo;
+pub use stats::{StatsExtension, STATS_REGIONS};
+pub use tiny_memory_map::TinyMemoryMap;
+pub use tombstone::{SlotState, TombstonedMemoryMap, Tombstones};
+pub use trade_memory_map::{OrderSide, OrderSideMemoryMap, StandardMemoryMap};
+pub use verify::{CorruptionReport, WordMismatch, REPORTED_MISMATCHES};
+pub use view::MemoryMapView;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmMemoryMap;
 
 /// Error types that can occur during memory map operations
 #[derive(Debug, Clone, Copy)]
@@ -24,17 +167,154 @@ pub enum MemoryMapError {
     IndexOutOfBounds,
     InvalidMapType,
     NullPointer,
+    InvalidHeader,
+    UnsupportedVersion,
+    /// Account length differs from the one recorded in the header
+    LengthMismatch,
+    /// Allocated bit read back as clear with [`FLAG_VERIFY_WRITES`] set
+    WriteVerificationFailed,
+    /// Map region shares bytes with another region or a registry
+    RegionOverlap,
+    /// Slot past the requested capacity is still allocated
+    TailNotEmpty,
+    /// Word starts inside the region but its last byte lies past the end
+    PartialWord,
+    /// Slot requested by `alloc_at` is already allocated
+    SlotTaken,
+    /// Slot is owned by a different key than the signer
+    OwnerMismatch,
+    /// Slot is pinned and cannot be deallocated
+    SlotPinned,
+    /// Bitmap no longer matches the checksum recorded in the header
+    ChecksumMismatch,
+    /// Key is already mapped to a slot
+    DuplicateKey,
+    /// Owner already holds as many slots as its quota allows
+    QuotaExceeded,
+    /// Map is frozen by `MemoryMap::freeze`
+    MapFrozen,
 }
 
 /// Available memory map types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
 pub enum MapType {
     /// 3-level memory map with 64 bits in first level
-    Max,
+    Max = 0,
     /// 3-level memory map with 4 bits in first level
-    Standard,
+    Standard = 1,
     /// 2-level memory map
-    Small,
+    Small = 2,
+    /// 4-level memory map with 64 bits in every level
+    Huge = 3,
+    /// 1-level memory map with 64 slots
+    Tiny = 4,
+    /// 3-level memory map with 16 bits in first level
+    Medium = 5,
+}
+
+impl TryFrom<u8> for MapType {
+    type Error = MemoryMapError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Max),
+            1 => Ok(Self::Standard),
+            2 => Ok(Self::Small),
+            3 => Ok(Self::Huge),
+            4 => Ok(Self::Tiny),
+            5 => Ok(Self::Medium),
+            _ => Err(MemoryMapError::InvalidMapType),
+        }
+    }
+}
+
+impl MapType {
+    /// Number of bytes occupied by the bitmap
+    ///
+    /// Add this to the offset of the map to get the minimum account size.
+    pub const fn required_size(self) -> usize {
+        match self {
+            Self::Max => MaxMemoryMap::REQUIRED_SIZE,
+            Self::Standard => StandardMemoryMap::REQUIRED_SIZE,
+            Self::Small => SmallMemoryMap::REQUIRED_SIZE,
+            Self::Huge => HugeMemoryMap::REQUIRED_SIZE,
+            Self::Tiny => TinyMemoryMap::REQUIRED_SIZE,
+            Self::Medium => MediumMemoryMap::REQUIRED_SIZE,
+        }
+    }
+
+    /// Number of indices the map can hand out
+    ///
+    /// Valid indices are `0..capacity`.
+    pub const fn capacity(self) -> usize {
+        match self {
+            Self::Max => MaxMemoryMap::CAPACITY,
+            Self::Standard => StandardMemoryMap::CAPACITY,
+            Self::Small => SmallMemoryMap::CAPACITY,
+            Self::Huge => HugeMemoryMap::CAPACITY,
+            Self::Tiny => TinyMemoryMap::CAPACITY,
+            Self::Medium => MediumMemoryMap::CAPACITY,
+        }
+    }
+
+    /// Smallest map type able to hand out `slots` indices
+    pub const fn for_slots(slots: usize) -> Option<Self> {
+        // Ordered from smallest to largest capacity
+        const BY_CAPACITY: [MapType; 6] = [
+            MapType::Tiny,
+            MapType::Small,
+            MapType::Standard,
+            MapType::Medium,
+            MapType::Max,
+            MapType::Huge,
+        ];
+
+        let mut i = 0;
+        while i < BY_CAPACITY.len() {
+            if slots <= BY_CAPACITY[i].capacity() {
+                return Some(BY_CAPACITY[i]);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Number of levels in the hierarchy
+    pub(crate) const fn levels(self) -> usize {
+        match self {
+            Self::Huge => 4,
+            Self::Max | Self::Standard | Self::Medium => 3,
+            Self::Small => 2,
+            Self::Tiny => 1,
+        }
+    }
+
+    /// Shift turning an index into its first-level region
+    pub(crate) const fn region_shift(self) -> usize {
+        6 * (self.levels() - 1)
+    }
+
+    /// Number of usable bits in the first-level word
+    pub(crate) const fn first_level_bits(self) -> usize {
+        match self {
+            Self::Max | Self::Small | Self::Huge | Self::Tiny => 64,
+            Self::Standard => 4,
+            Self::Medium => 16,
+        }
+    }
+
+    /// Word offset of `level` within the bitmap
+    pub(crate) const fn level_start(self, level: usize) -> usize {
+        match self {
+            Self::Max => MaxMemoryMap::level_start(level),
+            Self::Standard => StandardMemoryMap::level_start(level),
+            Self::Small => SmallMemoryMap::level_start(level),
+            Self::Huge => HugeMemoryMap::level_start(level),
+            Self::Tiny => TinyMemoryMap::level_start(level),
+            Self::Medium => MediumMemoryMap::level_start(level),
+        }
+    }
 }
 
 /// Memory map implementations
@@ -46,6 +326,12 @@ pub enum MemoryMap {
     Standard(StandardMemoryMap),
     /// 2-level memory map
     Small(SmallMemoryMap),
+    /// 4-level memory map with 64 bits in every level
+    Huge(HugeMemoryMap),
+    /// 1-level memory map with 64 slots
+    Tiny(TinyMemoryMap),
+    /// 3-level memory map with 16 bits in first level
+    Medium(MediumMemoryMap),
 }
 
 impl MemoryMap {
@@ -56,6 +342,7 @@ impl MemoryMap {
     /// - Programs are single-threaded
     /// - AccountInfo lives for the entire process_instruction call
     /// - There's no concurrent access to the data
+    #[cfg(feature = "solana")]
     pub fn new(
         account: &AccountInfo,
         offset: usize,
@@ -71,50 +358,496 @@ impl MemoryMap {
         offset: usize,
         map_type: MapType,
     ) -> Result<Self, MemoryMapError> {
-        // Check offset validity
-        if offset >= data.len() {
-            return Err(MemoryMapError::InvalidOffset);
+        let (memory, remaining_size) = slice_memory(data, offset, align_of::<u64>())?;
+        // Safety: `memory` points into `data`, which has `remaining_size` bytes left
+        let backend = unsafe { RawBackend::new(memory, remaining_size) };
+        Self::from_backend(backend, map_type)
+    }
+
+    /// Create a new memory map from a mutable byte slice that is only 4-byte
+    /// aligned at `offset`
+    ///
+    /// Words are accessed as pairs of u32 halves; the bitmap bytes are the same
+    /// as with [`MemoryMap::new_from_slice`].
+    pub fn new_from_slice_u32(
+        data: &mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        let (memory, remaining_size) = slice_memory(data, offset, align_of::<u32>())?;
+        // Safety: `memory` points into `data`, which has `remaining_size` bytes left
+        let backend = unsafe { RawBackend::new_u32(memory, remaining_size) };
+        Self::from_backend(backend, map_type)
+    }
+
+    fn from_backend(backend: RawBackend, map_type: MapType) -> Result<Self, MemoryMapError> {
+        // Bytes past the bitmap are never reachable through the map
+        let backend = backend.clamped(map_type.required_size());
+        // Create the appropriate memory map implementation
+        match map_type {
+            MapType::Max => Ok(Self::Max(MaxMemoryMap::with_backend(backend)?)),
+            MapType::Standard => Ok(Self::Standard(StandardMemoryMap::with_backend(backend)?)),
+            MapType::Small => Ok(Self::Small(SmallMemoryMap::with_backend(backend)?)),
+            MapType::Huge => Ok(Self::Huge(HugeMemoryMap::with_backend(backend)?)),
+            MapType::Tiny => Ok(Self::Tiny(TinyMemoryMap::with_backend(backend)?)),
+            MapType::Medium => Ok(Self::Medium(MediumMemoryMap::with_backend(backend)?)),
         }
+    }
+
+    /// Initialize a map with a header at `offset`, clearing the bitmap
+    ///
+    /// The bitmap starts at `offset + HEADER_SIZE`.
+    pub fn init(data: &mut [u8], offset: usize, map_type: MapType) -> Result<Self, MemoryMapError> {
+        Self::init_with_flags(data, offset, map_type, 0)
+    }
 
-        // Check alignment for u64
-        let ptr = data[offset..].as_mut_ptr();
-        if (ptr as usize) % align_of::<u64>() != 0 {
-            return Err(MemoryMapError::AlignmentError);
+    /// Initialize a map like [`MemoryMap::init`], enabling the optional
+    /// header features in `flags` (e.g. [`FLAG_LEAF_HINT`])
+    pub fn init_with_flags(
+        data: &mut [u8],
+        offset: usize,
+        map_type: MapType,
+        flags: u8,
+    ) -> Result<Self, MemoryMapError> {
+        if flags & !header::KNOWN_FLAGS != 0 {
+            return Err(MemoryMapError::InvalidHeader);
         }
 
-        // Create NonNull pointer - guaranteed to be non-null
-        let memory = NonNull::new(ptr).ok_or(MemoryMapError::NullPointer)?;
+        let bitmap_offset = offset
+            .checked_add(HEADER_SIZE)
+            .ok_or(MemoryMapError::InvalidOffset)?;
+        let bitmap_end = bitmap_offset
+            .checked_add(map_type.required_size())
+            .ok_or(MemoryMapError::InvalidOffset)?;
+        if data.len() < bitmap_end {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
 
-        let remaining_size = data.len() - offset;
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        *header = MapHeader::new(map_type);
+        header.flags = flags;
+        header.data_len = data_len as u64;
+        let header = NonNull::from(header);
+        data[bitmap_offset..bitmap_end].fill(0);
 
-        // Create the appropriate memory map implementation
-        match map_type {
-            MapType::Max => Ok(Self::Max(MaxMemoryMap::new(memory, remaining_size)?)),
-            MapType::Standard => Ok(Self::Standard(StandardMemoryMap::new(
-                memory,
-                remaining_size,
-            )?)),
-            MapType::Small => Ok(Self::Small(SmallMemoryMap::new(memory, remaining_size)?)),
+        Ok(Self::new_from_slice(data, bitmap_offset, map_type)?.with_header(header))
+    }
+
+    /// Initialize the smallest map able to hold `slots` indices at `offset`
+    ///
+    /// Indices from `slots` up to the map capacity are marked allocated, so
+    /// alloc never hands out an index without a backing slot, and dealloc
+    /// rejects them. The map can be loaded again with [`MemoryMap::load_auto`].
+    ///
+    /// Only the slot data shrinks: the bitmap keeps the fixed layout of its map
+    /// type, so `data` still needs `HEADER_SIZE + map_type.required_size()`
+    /// bytes from `offset` on, e.g. 520 bitmap bytes for 100 slots.
+    pub fn with_capacity(
+        data: &mut [u8],
+        offset: usize,
+        slots: usize,
+    ) -> Result<Self, MemoryMapError> {
+        let map_type = MapType::for_slots(slots).ok_or(MemoryMapError::InvalidIndex)?;
+        let mut map = Self::init(data, offset, map_type)?;
+        match &mut map {
+            Self::Max(map) => map.reserve_tail(slots)?,
+            Self::Standard(map) => map.reserve_tail(slots)?,
+            Self::Small(map) => map.reserve_tail(slots)?,
+            Self::Huge(map) => map.reserve_tail(slots)?,
+            Self::Tiny(map) => map.reserve_tail(slots)?,
+            Self::Medium(map) => map.reserve_tail(slots)?,
+        }
+        if slots < map_type.capacity() {
+            if let Some(header) = map.header_mut() {
+                header.slot_limit = slots as u64;
+            }
+        }
+        map.recount()?;
+        Ok(map)
+    }
+
+    /// Load a map previously created with [`MemoryMap::init`]
+    pub fn load(data: &mut [u8], offset: usize, map_type: MapType) -> Result<Self, MemoryMapError> {
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        header.validate()?;
+        header.check_data_len(data_len)?;
+        if header.map_type()? != map_type {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        let header = NonNull::from(header);
+
+        Ok(Self::new_from_slice(data, offset + HEADER_SIZE, map_type)?.with_header(header))
+    }
+
+    /// Load a map previously created with [`MemoryMap::init`], taking the map
+    /// type from the header
+    pub fn load_auto(data: &mut [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        let data_len = data.len();
+        let header = header::header_mut(data, offset)?;
+        header.validate()?;
+        header.check_data_len(data_len)?;
+        let map_type = header.map_type()?;
+        let header = NonNull::from(header);
+
+        Ok(Self::new_from_slice(data, offset + HEADER_SIZE, map_type)?.with_header(header))
+    }
+
+    /// Attach the header holding the persisted allocation hints
+    fn with_header(mut self, header: NonNull<MapHeader>) -> Self {
+        match &mut self {
+            Self::Max(map) => map.set_header(header),
+            Self::Standard(map) => map.set_header(header),
+            Self::Small(map) => map.set_header(header),
+            Self::Huge(map) => map.set_header(header),
+            Self::Tiny(map) => map.set_header(header),
+            Self::Medium(map) => map.set_header(header),
+        }
+        self
+    }
+
+    /// Type of this map
+    pub const fn map_type(&self) -> MapType {
+        match self {
+            Self::Max(_) => MapType::Max,
+            Self::Standard(_) => MapType::Standard,
+            Self::Small(_) => MapType::Small,
+            Self::Huge(_) => MapType::Huge,
+            Self::Tiny(_) => MapType::Tiny,
+            Self::Medium(_) => MapType::Medium,
         }
     }
 
     /// Allocate a new slot
     pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
-        match self {
+        let span = OpSpan::enter("alloc", self.map_type());
+        let result = match self {
             Self::Max(map) => map.alloc(),
             Self::Standard(map) => map.alloc(),
             Self::Small(map) => map.alloc(),
+            Self::Huge(map) => map.alloc(),
+            Self::Tiny(map) => map.alloc(),
+            Self::Medium(map) => map.alloc(),
+        };
+        if let Ok(index) = result {
+            span.record_index(index);
+        }
+        span.finish(&result);
+        result
+    }
+
+    /// Number of indices the map can hand out
+    pub const fn capacity(&self) -> usize {
+        self.map_type().capacity()
+    }
+
+    /// Allocate a new slot, preferring the leaf word `block`
+    ///
+    /// Block `b` holds indices `b * 64..(b + 1) * 64`. Falls back to a regular
+    /// allocation when the block is full.
+    pub fn alloc_near(&mut self, block: usize) -> Result<usize, MemoryMapError> {
+        let span = OpSpan::enter("alloc_near", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.alloc_near(block),
+            Self::Standard(map) => map.alloc_near(block),
+            Self::Small(map) => map.alloc_near(block),
+            Self::Huge(map) => map.alloc_near(block),
+            Self::Tiny(map) => map.alloc_near(block),
+            Self::Medium(map) => map.alloc_near(block),
+        };
+        if let Ok(index) = result {
+            span.record_index(index);
+        }
+        span.finish(&result);
+        result
+    }
+
+    /// Allocate the lowest free slot at or above `floor`
+    ///
+    /// Lets callers assign index bands, e.g. one per account tier.
+    pub fn alloc_above(&mut self, floor: usize) -> Result<usize, MemoryMapError> {
+        let span = OpSpan::enter("alloc_above", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.alloc_above(floor),
+            Self::Standard(map) => map.alloc_above(floor),
+            Self::Small(map) => map.alloc_above(floor),
+            Self::Huge(map) => map.alloc_above(floor),
+            Self::Tiny(map) => map.alloc_above(floor),
+            Self::Medium(map) => map.alloc_above(floor),
+        };
+        if let Ok(index) = result {
+            span.record_index(index);
+        }
+        span.finish(&result);
+        result
+    }
+
+    /// Allocate two slots in the same leaf word, adjacent when possible
+    ///
+    /// Fails without allocating anything when no leaf word has two free slots.
+    pub fn alloc_twin(&mut self) -> Result<(usize, usize), MemoryMapError> {
+        let span = OpSpan::enter("alloc_twin", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.alloc_twin(),
+            Self::Standard(map) => map.alloc_twin(),
+            Self::Small(map) => map.alloc_twin(),
+            Self::Huge(map) => map.alloc_twin(),
+            Self::Tiny(map) => map.alloc_twin(),
+            Self::Medium(map) => map.alloc_twin(),
+        };
+        if let Ok((first, _)) = result {
+            span.record_index(first);
+        }
+        span.finish(&result);
+        result
+    }
+
+    /// Deallocate every slot of `indices`, writing each bitmap word at most once
+    ///
+    /// Leaf words are written in ascending order, then the summary words. The
+    /// result reports how many words were dirtied.
+    #[cfg(feature = "std")]
+    pub fn dealloc_batch(&mut self, indices: &[usize]) -> Result<BatchDealloc, MemoryMapError> {
+        let span = OpSpan::enter("dealloc_batch", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.dealloc_batch(indices),
+            Self::Standard(map) => map.dealloc_batch(indices),
+            Self::Small(map) => map.dealloc_batch(indices),
+            Self::Huge(map) => map.dealloc_batch(indices),
+            Self::Tiny(map) => map.dealloc_batch(indices),
+            Self::Medium(map) => map.dealloc_batch(indices),
+        };
+        span.finish(&result);
+        result
+    }
+
+    /// Allocate a whole free leaf word of 64 slots and return its first index
+    pub fn alloc_block(&mut self) -> Result<usize, MemoryMapError> {
+        let span = OpSpan::enter("alloc_block", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.alloc_block(),
+            Self::Standard(map) => map.alloc_block(),
+            Self::Small(map) => map.alloc_block(),
+            Self::Huge(map) => map.alloc_block(),
+            Self::Tiny(map) => map.alloc_block(),
+            Self::Medium(map) => map.alloc_block(),
+        };
+        if let Ok(base) = result {
+            span.record_index(base);
+        }
+        span.finish(&result);
+        result
+    }
+
+    /// Deallocate the 64 slots starting at `base`, as returned by `alloc_block`
+    pub fn dealloc_block(&mut self, base: usize) -> Result<(), MemoryMapError> {
+        let span = OpSpan::enter("dealloc_block", self.map_type());
+        span.record_index(base);
+        let result = match self {
+            Self::Max(map) => map.dealloc_block(base),
+            Self::Standard(map) => map.dealloc_block(base),
+            Self::Small(map) => map.dealloc_block(base),
+            Self::Huge(map) => map.dealloc_block(base),
+            Self::Tiny(map) => map.dealloc_block(base),
+            Self::Medium(map) => map.dealloc_block(base),
+        };
+        span.finish(&result);
+        result
+    }
+
+    /// Allocate a slot and copy `bytes` into it
+    ///
+    /// Slot `i` occupies `data_region[i * slot_size..(i + 1) * slot_size]`, the
+    /// part of the slot not covered by `bytes` is zeroed. If the slot does not
+    /// fit in `data_region` the allocation is rolled back.
+    pub fn alloc_and_write(
+        &mut self,
+        data_region: &mut [u8],
+        slot_size: usize,
+        bytes: &[u8],
+    ) -> Result<usize, MemoryMapError> {
+        if bytes.len() > slot_size {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        let index = self.alloc()?;
+        let slot = index
+            .checked_mul(slot_size)
+            .and_then(|start| data_region.get_mut(start..start.checked_add(slot_size)?));
+
+        let Some(slot) = slot else {
+            self.dealloc(index)?;
+            return Err(MemoryMapError::IndexOutOfBounds);
+        };
+
+        let (head, tail) = slot.split_at_mut(bytes.len());
+        head.copy_from_slice(bytes);
+        tail.fill(0);
+
+        Ok(index)
+    }
+
+    /// Header the map was initialized or loaded with
+    pub(crate) fn header(&self) -> Option<&MapHeader> {
+        // Safety: the header outlives the map, like the bitmap itself
+        self.header_ptr().map(|header| unsafe { &*header.as_ptr() })
+    }
+
+    /// Mutable header the map was initialized or loaded with
+    pub(crate) fn header_mut(&mut self) -> Option<&mut MapHeader> {
+        // Safety: the header outlives the map, like the bitmap itself
+        self.header_ptr()
+            .map(|header| unsafe { &mut *header.as_ptr() })
+    }
+
+    fn header_ptr(&self) -> Option<NonNull<MapHeader>> {
+        match self {
+            Self::Max(map) => map.header(),
+            Self::Standard(map) => map.header(),
+            Self::Small(map) => map.header(),
+            Self::Huge(map) => map.header(),
+            Self::Tiny(map) => map.header(),
+            Self::Medium(map) => map.header(),
+        }
+    }
+
+    /// Number of allocated slots
+    ///
+    /// Read from the header counter for maps created with [`MemoryMap::init`]
+    /// or [`MemoryMap::load`], counted from the leaf words otherwise. The tail
+    /// reserved by [`MemoryMap::with_capacity`] is not counted.
+    pub fn used_count(&self) -> Result<usize, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.used_count(),
+            Self::Standard(map) => map.used_count(),
+            Self::Small(map) => map.used_count(),
+            Self::Huge(map) => map.used_count(),
+            Self::Tiny(map) => map.used_count(),
+            Self::Medium(map) => map.used_count(),
+        }
+    }
+
+    /// Number of slots still available below the slot limit
+    pub fn free_count(&self) -> Result<usize, MemoryMapError> {
+        Ok(self.slot_limit().saturating_sub(self.used_count()?))
+    }
+
+    /// Recompute the header counter from the leaf words, repairing drift
+    pub fn recount(&mut self) -> Result<usize, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.recount(),
+            Self::Standard(map) => map.recount(),
+            Self::Small(map) => map.recount(),
+            Self::Huge(map) => map.recount(),
+            Self::Tiny(map) => map.recount(),
+            Self::Medium(map) => map.recount(),
+        }
+    }
+
+    /// Raw bitmap bytes of the map
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Max(map) => map.as_bytes(),
+            Self::Standard(map) => map.as_bytes(),
+            Self::Small(map) => map.as_bytes(),
+            Self::Huge(map) => map.as_bytes(),
+            Self::Tiny(map) => map.as_bytes(),
+            Self::Medium(map) => map.as_bytes(),
+        }
+    }
+
+    /// Check whether every slot is allocated
+    pub fn is_full(&self) -> bool {
+        match self {
+            Self::Max(map) => map.is_full(),
+            Self::Standard(map) => map.is_full(),
+            Self::Small(map) => map.is_full(),
+            Self::Huge(map) => map.is_full(),
+            Self::Tiny(map) => map.is_full(),
+            Self::Medium(map) => map.is_full(),
+        }
+    }
+
+    /// Check whether slot `index` is allocated
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.is_allocated(index),
+            Self::Standard(map) => map.is_allocated(index),
+            Self::Small(map) => map.is_allocated(index),
+            Self::Huge(map) => map.is_allocated(index),
+            Self::Tiny(map) => map.is_allocated(index),
+            Self::Medium(map) => map.is_allocated(index),
+        }
+    }
+
+    /// Check whether leaf word `block` (slots `block * 64..`) has no allocated slot
+    ///
+    /// Reads the summary word above the block and at most one leaf word.
+    pub fn block_is_empty(&self, block: usize) -> Result<bool, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.block_is_empty(block),
+            Self::Standard(map) => map.block_is_empty(block),
+            Self::Small(map) => map.block_is_empty(block),
+            Self::Huge(map) => map.block_is_empty(block),
+            Self::Tiny(map) => map.block_is_empty(block),
+            Self::Medium(map) => map.block_is_empty(block),
+        }
+    }
+
+    /// Check whether every slot of leaf word `block` is allocated
+    ///
+    /// Reads the summary word above the block and at most one leaf word.
+    pub fn block_is_full(&self, block: usize) -> Result<bool, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.block_is_full(block),
+            Self::Standard(map) => map.block_is_full(block),
+            Self::Small(map) => map.block_is_full(block),
+            Self::Huge(map) => map.block_is_full(block),
+            Self::Tiny(map) => map.block_is_full(block),
+            Self::Medium(map) => map.block_is_full(block),
         }
     }
 
     /// Deallocate a previously allocated slot
     pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
-        match self {
+        let span = OpSpan::enter("dealloc", self.map_type());
+        span.record_index(index);
+        let result = match self {
             Self::Max(map) => map.dealloc(index),
             Self::Standard(map) => map.dealloc(index),
             Self::Small(map) => map.dealloc(index),
-        }
+            Self::Huge(map) => map.dealloc(index),
+            Self::Tiny(map) => map.dealloc(index),
+            Self::Medium(map) => map.dealloc(index),
+        };
+        span.finish(&result);
+        result
+    }
+}
+
+/// Helper function to get a pointer to `data[offset..]` aligned to `align` and
+/// the number of bytes remaining after it
+pub(crate) fn slice_memory(
+    data: &mut [u8],
+    offset: usize,
+    align: usize,
+) -> Result<(NonNull<u8>, usize), MemoryMapError> {
+    // Check offset validity
+    if offset >= data.len() {
+        return Err(MemoryMapError::InvalidOffset);
     }
+
+    // Check alignment of the word type
+    let ptr = data[offset..].as_mut_ptr();
+    if !(ptr as usize).is_multiple_of(align) {
+        return Err(MemoryMapError::AlignmentError);
+    }
+
+    // Create NonNull pointer - guaranteed to be non-null
+    let memory = NonNull::new(ptr).ok_or(MemoryMapError::NullPointer)?;
+
+    Ok((memory, data.len() - offset))
 }
 
 /// Helper function to get mutable u64 at specified index
@@ -124,12 +857,9 @@ pub(crate) fn get_u64_mut<'a>(
     size: usize,
     index: usize,
 ) -> Result<&'a mut u64, MemoryMapError> {
-    if index * size_of::<u64>() >= size {
-        return Err(MemoryMapError::IndexOutOfBounds);
-    }
-
+    let start = word_offset(size, index)?;
     unsafe {
-        let ptr = memory.as_ptr().add(index * size_of::<u64>()) as *mut u64;
+        let ptr = memory.as_ptr().add(start) as *mut u64;
         Ok(&mut *ptr)
     }
 }
@@ -141,16 +871,28 @@ pub(crate) fn get_u64<'a>(
     size: usize,
     index: usize,
 ) -> Result<&'a u64, MemoryMapError> {
-    if index * size_of::<u64>() >= size {
-        return Err(MemoryMapError::IndexOutOfBounds);
-    }
-
+    let start = word_offset(size, index)?;
     unsafe {
-        let ptr = memory.as_ptr().add(index * size_of::<u64>()) as *const u64;
+        let ptr = memory.as_ptr().add(start) as *const u64;
         Ok(&*ptr)
     }
 }
 
+/// Byte offset of word `index`, once all 8 of its bytes lie within `size`
+#[inline]
+pub(crate) const fn word_offset(size: usize, index: usize) -> Result<usize, MemoryMapError> {
+    let Some(start) = index.checked_mul(size_of::<u64>()) else {
+        return Err(MemoryMapError::IndexOutOfBounds);
+    };
+    if start >= size {
+        return Err(MemoryMapError::IndexOutOfBounds);
+    }
+    if size - start < size_of::<u64>() {
+        return Err(MemoryMapError::PartialWord);
+    }
+    Ok(start)
+}
+
 #[cfg(test)]
 pub(crate) fn create_aligned_memory(size: usize) -> (Vec<u8>, NonNull<u8>) {
     let mut data = vec![0u8; size + 8]; // Add extra space for alignment
@@ -169,20 +911,21 @@ pub(crate) fn create_aligned_memory(size: usize) -> (Vec<u8>, NonNull<u8>) {
     (data, non_null_ptr)
 }
 
+#[cfg(any(test, feature = "testvectors"))]
+pub(crate) fn create_aligned_buffer(size: usize) -> Vec<u8> {
+    let mut data = vec![0u8; size + 8];
+    let ptr = data.as_ptr();
+    let misalignment = ptr as usize % 8;
+    if misalignment != 0 {
+        data.rotate_left(8 - misalignment);
+    }
+    data
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_aligned_buffer(size: usize) -> Vec<u8> {
-        let mut data = vec![0u8; size + 8];
-        let ptr = data.as_ptr();
-        let misalignment = ptr as usize % 8;
-        if misalignment != 0 {
-            data.rotate_left(8 - misalignment);
-        }
-        data
-    }
-
     #[test]
     fn test_memory_map_creation() {
         let mut buffer = create_aligned_buffer(1024);
@@ -218,4 +961,351 @@ mod tests {
         let idx3 = map.alloc().unwrap();
         assert_eq!(idx1, idx3);
     }
+
+    #[test]
+    fn test_load_checks_map_type() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Standard.required_size());
+        buffer.fill(0xff);
+
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Standard).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+
+        let mut map = MemoryMap::load(&mut buffer, 0, MapType::Standard).unwrap();
+        assert_eq!(map.alloc().unwrap(), 1);
+
+        assert!(matches!(
+            MemoryMap::load(&mut buffer, 0, MapType::Small),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+
+        // An offset near the end of the address space must not wrap
+        assert!(matches!(
+            MemoryMap::init(&mut buffer, usize::MAX - 8, MapType::Standard),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+    }
+
+    #[test]
+    fn test_load_auto() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Max.required_size());
+
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Standard).unwrap();
+        map.alloc().unwrap();
+
+        let mut map = MemoryMap::load_auto(&mut buffer, 0).unwrap();
+        assert_eq!(map.map_type(), MapType::Standard);
+        assert_eq!(map.alloc().unwrap(), 1);
+
+        // Corrupted map type byte
+        buffer[6] = 0xff;
+        assert!(matches!(
+            MemoryMap::load_auto(&mut buffer, 0),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+
+        // Never initialized
+        let mut fresh = create_aligned_buffer(HEADER_SIZE + MapType::Small.required_size());
+        assert!(matches!(
+            MemoryMap::load_auto(&mut fresh, 0),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_required_size() {
+        const MAX_SIZE: usize = MapType::Max.required_size();
+        assert_eq!(MAX_SIZE, 33_288);
+        assert_eq!(MapType::Standard.required_size(), 2_088);
+        assert_eq!(MapType::Small.required_size(), 520);
+        assert_eq!(MapType::Huge.required_size(), 2_130_440);
+        assert_eq!(MapType::Tiny.required_size(), 8);
+        assert_eq!(MapType::Medium.required_size(), 8_328);
+
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let exact = &mut buffer[..MapType::Standard.required_size()];
+        assert!(MemoryMap::new_from_slice(exact, 0, MapType::Standard).is_ok());
+        let short = &mut buffer[..MapType::Standard.required_size() - 8];
+        assert!(matches!(
+            MemoryMap::new_from_slice(short, 0, MapType::Standard),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_capacity() {
+        assert_eq!(MapType::Max.capacity(), 262_144);
+        assert_eq!(MapType::Standard.capacity(), 16_384);
+        assert_eq!(MapType::Small.capacity(), 4_096);
+        assert_eq!(MapType::Huge.capacity(), 16_777_216);
+        assert_eq!(MapType::Tiny.capacity(), 64);
+        assert_eq!(MapType::Medium.capacity(), 65_536);
+
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(map.capacity(), 4_096);
+
+        // Last valid index is accepted, capacity itself is not
+        assert!(map.dealloc(map.capacity() - 1).is_ok());
+        assert!(matches!(
+            map.dealloc(map.capacity()),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_for_slots() {
+        assert_eq!(MapType::for_slots(0), Some(MapType::Tiny));
+        assert_eq!(MapType::for_slots(64), Some(MapType::Tiny));
+        assert_eq!(MapType::for_slots(65), Some(MapType::Small));
+        assert_eq!(MapType::for_slots(4_096), Some(MapType::Small));
+        assert_eq!(MapType::for_slots(4_097), Some(MapType::Standard));
+        assert_eq!(MapType::for_slots(16_384), Some(MapType::Standard));
+        assert_eq!(MapType::for_slots(50_000), Some(MapType::Medium));
+        assert_eq!(MapType::for_slots(65_536), Some(MapType::Medium));
+        assert_eq!(MapType::for_slots(65_537), Some(MapType::Max));
+        assert_eq!(MapType::for_slots(262_144), Some(MapType::Max));
+        assert_eq!(MapType::for_slots(262_145), Some(MapType::Huge));
+        assert_eq!(MapType::for_slots(16_777_216), Some(MapType::Huge));
+        assert_eq!(MapType::for_slots(16_777_217), None);
+    }
+
+    #[test]
+    fn test_word_bounds_with_odd_sizes() {
+        let (_buffer, memory) = create_aligned_memory(24);
+        for size in [9, 12, 15] {
+            assert!(get_u64(memory, size, 0).is_ok());
+            assert!(matches!(
+                get_u64(memory, size, 1),
+                Err(MemoryMapError::PartialWord)
+            ));
+            assert!(matches!(
+                get_u64_mut(memory, size, 1),
+                Err(MemoryMapError::PartialWord)
+            ));
+            assert!(matches!(
+                get_u64(memory, size, 2),
+                Err(MemoryMapError::IndexOutOfBounds)
+            ));
+        }
+        assert!(get_u64(memory, 16, 1).is_ok());
+        assert!(matches!(
+            get_u64(memory, 16, usize::MAX),
+            Err(MemoryMapError::IndexOutOfBounds)
+        ));
+
+        // The u32 access path applies the same check
+        // Safety: the buffer behind `memory` holds at least 12 bytes
+        let mut backend = unsafe { RawBackend::new_u32(memory, 12) };
+        assert!(matches!(
+            backend.write_word(1, 1),
+            Err(MemoryMapError::PartialWord)
+        ));
+        assert!(backend.write_word(0, 1).is_ok());
+    }
+
+    #[test]
+    fn test_alloc_and_write() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0xee; 3 * 4];
+
+        assert_eq!(map.alloc_and_write(&mut region, 4, &[1, 2]).unwrap(), 0);
+        assert_eq!(
+            map.alloc_and_write(&mut region, 4, &[3, 4, 5, 6]).unwrap(),
+            1
+        );
+        assert_eq!(&region[..8], &[1, 2, 0, 0, 3, 4, 5, 6]);
+
+        // Payload larger than a slot is rejected before allocating
+        assert!(matches!(
+            map.alloc_and_write(&mut region, 4, &[0; 5]),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        assert_eq!(map.alloc_and_write(&mut region, 4, &[7]).unwrap(), 2);
+
+        // Slot 3 is past the end of the region, allocation is rolled back
+        assert!(matches!(
+            map.alloc_and_write(&mut region, 4, &[8]),
+            Err(MemoryMapError::IndexOutOfBounds)
+        ));
+        assert_eq!(map.alloc().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Standard.required_size());
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 4_100).unwrap();
+        assert_eq!(map.map_type(), MapType::Standard);
+
+        for expected in 0..4_100 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        // Reserved tail survives a reload
+        map.dealloc(7).unwrap();
+        let mut map = MemoryMap::load_auto(&mut buffer, 0).unwrap();
+        assert_eq!(map.alloc().unwrap(), 7);
+        assert!(map.is_full());
+
+        assert!(matches!(
+            MemoryMap::with_capacity(&mut buffer, 0, MapType::Huge.capacity() + 1),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_with_capacity_tail_cannot_be_freed() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Small.required_size());
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+
+        assert!(matches!(
+            map.dealloc(150),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            map.dealloc(100),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            map.dealloc_block(64),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            map.dealloc_block(128),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            map.dealloc_batch(&[3, 150]),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        let mut buddy = BuddyMemoryMap::new(map);
+        assert!(matches!(
+            buddy.dealloc(96, 3),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            buddy.dealloc(128, 0),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        let mut map = buddy.into_inner();
+
+        for expected in 0..100 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+        map.dealloc(99).unwrap();
+        map.dealloc_block(0).unwrap();
+        assert_eq!(map.used_count().unwrap(), 35);
+
+        // The tail reserved by try_shrink is guarded the same way
+        let mut map = MemoryMap::load_auto(&mut buffer, 0).unwrap();
+        let upper: Vec<_> = (64..99).collect();
+        assert_eq!(map.dealloc_batch(&upper).unwrap().freed, 35);
+        map.try_shrink(64).unwrap();
+        assert!(matches!(map.dealloc(80), Err(MemoryMapError::InvalidIndex)));
+        assert_eq!(map.free_count().unwrap(), 64);
+    }
+
+    #[test]
+    fn test_new_from_slice_u32() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size() + 4);
+        assert!(matches!(
+            MemoryMap::new_from_slice(&mut buffer, 4, MapType::Tiny),
+            Err(MemoryMapError::AlignmentError)
+        ));
+        assert!(matches!(
+            MemoryMap::new_from_slice_u32(&mut buffer, 2, MapType::Tiny),
+            Err(MemoryMapError::AlignmentError)
+        ));
+
+        let mut map = MemoryMap::new_from_slice_u32(&mut buffer, 4, MapType::Tiny).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+        assert_eq!(map.alloc().unwrap(), 1);
+        assert_eq!(buffer[4], 0b11);
+    }
+
+    #[test]
+    fn test_used_count() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer[..size], 0, MapType::Small).unwrap();
+        for _ in 0..100 {
+            map.alloc().unwrap();
+        }
+        map.alloc_twin().unwrap();
+        map.dealloc(7).unwrap();
+        // Freeing a free slot leaves the counter alone
+        map.dealloc(7).unwrap();
+        assert_eq!(map.used_count().unwrap(), 101);
+        assert_eq!(map.free_count().unwrap(), 4_096 - 101);
+
+        // Drifted counter is repaired from the bitmap
+        header::header_mut(&mut buffer, 0).unwrap().used_count = 5;
+        let mut map = MemoryMap::load(&mut buffer[..size], 0, MapType::Small).unwrap();
+        assert_eq!(map.used_count().unwrap(), 5);
+        assert_eq!(map.recount().unwrap(), 101);
+        assert_eq!(map.used_count().unwrap(), 101);
+
+        // Maps without a header count the leaf words
+        let map = MemoryMap::new_from_slice(&mut buffer, HEADER_SIZE, MapType::Small).unwrap();
+        assert_eq!(map.used_count().unwrap(), 101);
+
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Small.required_size());
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+        assert_eq!(map.used_count().unwrap(), 0);
+        assert_eq!(map.free_count().unwrap(), 100);
+        map.alloc().unwrap();
+        assert_eq!(map.recount().unwrap(), 1);
+        assert_eq!(map.free_count().unwrap(), 99);
+    }
+
+    #[test]
+    fn test_block_emptiness() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        assert!(map.block_is_empty(0).unwrap());
+        assert!(!map.block_is_full(0).unwrap());
+
+        map.alloc_block().unwrap();
+        map.alloc().unwrap();
+        assert!(map.block_is_full(0).unwrap());
+        assert!(!map.block_is_empty(0).unwrap());
+        assert!(!map.block_is_empty(1).unwrap());
+        assert!(!map.block_is_full(1).unwrap());
+        assert!(map.block_is_empty(2).unwrap());
+
+        let blocks = MapType::Small.capacity() / 64;
+        assert!(map.block_is_empty(blocks - 1).unwrap());
+        assert!(matches!(
+            map.block_is_empty(blocks),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        assert!(map.block_is_empty(0).unwrap());
+        map.alloc_block().unwrap();
+        assert!(map.block_is_full(0).unwrap());
+    }
+
+    #[test]
+    fn test_alloc_block_counts_slots() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Standard).unwrap();
+
+        map.alloc().unwrap();
+        let base = map.alloc_block().unwrap();
+        assert_eq!(base, 64);
+        assert_eq!(map.used_count().unwrap(), 65);
+        assert_eq!(map.alloc().unwrap(), 1);
+
+        map.dealloc_block(base).unwrap();
+        assert_eq!(map.used_count().unwrap(), 2);
+        assert_eq!(map.recount().unwrap(), 2);
+    }
 }
@@ -0,0 +1,163 @@
+//! Map handle whose lifetime is tied to the borrowed account bytes
+#![forbid(unsafe_code)]
+
+use crate::{allocator::IndexAllocator, MapType, MemoryMap, MemoryMapError, MemoryMapStats};
+use core::marker::PhantomData;
+
+/// [`MemoryMap`] that keeps `data` mutably borrowed for as long as it lives
+///
+/// The pointer-based [`MemoryMap`] relies on the caller to keep the account
+/// borrow alive. Here the borrow checker does it, at no runtime cost; the BPF
+/// hot path can keep using [`MemoryMap`] directly.
+pub struct MemoryMapRef<'a> {
+    map: MemoryMap,
+    _data: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> MemoryMapRef<'a> {
+    /// See [`MemoryMap::new_from_slice`]
+    pub fn new_from_slice(
+        data: &'a mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        MemoryMap::new_from_slice(data, offset, map_type).map(Self::wrap)
+    }
+
+    /// See [`MemoryMap::init`]
+    pub fn init(
+        data: &'a mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        MemoryMap::init(data, offset, map_type).map(Self::wrap)
+    }
+
+    /// See [`MemoryMap::load`]
+    pub fn load(
+        data: &'a mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        MemoryMap::load(data, offset, map_type).map(Self::wrap)
+    }
+
+    /// See [`MemoryMap::load_auto`]
+    pub fn load_auto(data: &'a mut [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        MemoryMap::load_auto(data, offset).map(Self::wrap)
+    }
+
+    const fn wrap(map: MemoryMap) -> Self {
+        Self {
+            map,
+            _data: PhantomData,
+        }
+    }
+
+    /// Type of this map
+    pub const fn map_type(&self) -> MapType {
+        self.map.map_type()
+    }
+
+    /// Number of slots the map can hand out
+    pub const fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Allocate a new slot
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        self.map.alloc()
+    }
+
+    /// Allocate a slot as close as possible to `block`
+    pub fn alloc_near(&mut self, block: usize) -> Result<usize, MemoryMapError> {
+        self.map.alloc_near(block)
+    }
+
+    /// Deallocate a previously allocated slot
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        self.map.dealloc(index)
+    }
+
+    /// Check whether slot `index` is allocated
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        self.map.is_allocated(index)
+    }
+
+    /// Check whether every slot is allocated
+    pub fn is_full(&self) -> bool {
+        self.map.is_full()
+    }
+
+    /// Number of allocated slots
+    pub fn used_count(&self) -> Result<usize, MemoryMapError> {
+        self.map.used_count()
+    }
+
+    /// Number of slots still available
+    pub fn free_count(&self) -> Result<usize, MemoryMapError> {
+        self.map.free_count()
+    }
+
+    /// Occupancy and fragmentation snapshot
+    pub fn stats(&self) -> Result<MemoryMapStats, MemoryMapError> {
+        self.map.stats()
+    }
+}
+
+impl IndexAllocator for MemoryMapRef<'_> {
+    fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        Self::alloc(self)
+    }
+
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        Self::dealloc(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        Self::capacity(self)
+    }
+
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        Self::is_allocated(self, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, HEADER_SIZE};
+
+    #[test]
+    fn test_ref_writes_through_borrow() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        {
+            let mut map = MemoryMapRef::init(&mut buffer, 0, MapType::Small).unwrap();
+            assert_eq!(map.alloc().unwrap(), 0);
+            assert_eq!(map.alloc().unwrap(), 1);
+            map.dealloc(0).unwrap();
+            assert_eq!(map.used_count().unwrap(), 1);
+        }
+        // The borrow has ended, the bytes are readable again
+        assert_ne!(buffer[HEADER_SIZE..size].iter().copied().max(), Some(0));
+
+        let mut map = MemoryMapRef::load_auto(&mut buffer, 0).unwrap();
+        assert_eq!(map.map_type(), MapType::Small);
+        assert!(map.is_allocated(1).unwrap());
+        assert_eq!(IndexAllocator::alloc(&mut map).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_ref_constructor_errors() {
+        let mut buffer = create_aligned_buffer(16);
+        assert!(matches!(
+            MemoryMapRef::new_from_slice(&mut buffer, 0, MapType::Small),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        assert!(matches!(
+            MemoryMapRef::load(&mut buffer, 0, MapType::Tiny),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -1,101 +1,17 @@
-use crate::{get_first_zero_bit::get_first_zero_bit, get_u64, get_u64_mut, MemoryMapError};
-use std::{mem::size_of, ptr::NonNull};
-/// Max memory map implementation (3 levels, 64 bits at first level)
-#[derive(Clone)]
-pub struct MaxMemoryMap {
-    memory: NonNull<u8>,
-    size: usize,
-}
-
-impl MaxMemoryMap {
-    /// Create a new Max memory map
-    pub(crate) fn new(memory: NonNull<u8>, size: usize) -> Result<Self, MemoryMapError> {
-        // Calculate required memory size for max map:
-        // - First level: 1 word to track available blocks in level 2
-        // - Second level: 64 words (one per bit in first level)
-        // - Third level: 64*64 words (one per bit in second level)
-        let required_size = (1 + 64 + 64 * 64) * size_of::<u64>();
-
-        // Check if there's enough memory
-        if size < required_size {
-            return Err(MemoryMapError::InsufficientMemory);
-        }
-
-        Ok(Self { memory, size })
-    }
-
-    /// Allocate a new slot
-    pub(crate) fn alloc(&mut self) -> Result<usize, MemoryMapError> {
-        // First level allocation (64 bits)
-        let first_word = get_u64(self.memory, self.size, 0)?;
-        let first = get_first_zero_bit(*first_word, 64)?;
-
-        // Second level allocation
-        let second_idx = 1 + first;
-        let second_word = get_u64(self.memory, self.size, second_idx)?;
-        let second = get_first_zero_bit(*second_word, 64)?;
-
-        // Third level allocation
-        let third_idx = 65 + (first * 64) + second;
-        let third_word = get_u64(self.memory, self.size, third_idx)?;
-        let third = get_first_zero_bit(*third_word, 64)?;
-
-        // Mark as allocated in third level
-        let third_word_mut = get_u64_mut(self.memory, self.size, third_idx)?;
-        *third_word_mut |= 1 << third;
-
-        // Update second level if needed
-        if *third_word_mut == u64::MAX {
-            let second_word_mut = get_u64_mut(self.memory, self.size, second_idx)?;
-            *second_word_mut |= 1 << second;
-
-            // Update first level if needed
-            if *second_word_mut == u64::MAX {
-                let first_word_mut = get_u64_mut(self.memory, self.size, 0)?;
-                *first_word_mut |= 1 << first;
-            }
-        }
-
-        Ok((first << 12) + (second << 6) + third)
-    }
-
-    /// Deallocate a previously allocated slot
-    pub(crate) fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
-        // Check upper bound
-        let max_index = (64 << 12) - 1; // 262143
-        if index > max_index {
-            return Err(MemoryMapError::InvalidIndex);
-        }
-
-        // max memory map - 3 levels
-        let first = index >> 12;
-        let second = (index & 0xfff) >> 6;
-        let second_idx = 1 + first;
-        let third_idx = 65 + (index >> 6);
-
-        // Clear allocation bits
-        let third_word = get_u64_mut(self.memory, self.size, third_idx)?;
-        *third_word &= !(1 << (index & 0x3f));
+use crate::hier_memory_map::HierMemoryMap;
 
-        let second_word = get_u64_mut(self.memory, self.size, second_idx)?;
-        *second_word &= !(1 << second);
-
-        let first_word = get_u64_mut(self.memory, self.size, 0)?;
-        *first_word &= !(1 << first);
-
-        Ok(())
-    }
-}
+/// Max memory map implementation (3 levels, 64 bits at first level)
+pub type MaxMemoryMap = HierMemoryMap<64, 3>;
 
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
-    use crate::create_aligned_memory;
+    use crate::{create_aligned_memory, MapType, MemoryMapError};
     use std::ptr::NonNull;
 
     // Calculate required memory size for max map
     fn get_required_size() -> usize {
-        (1 + 64 + 64 * 64) * size_of::<u64>()
+        MapType::Max.required_size()
     }
 
     #[test]
@@ -369,11 +285,31 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_full_map_short_circuit() {
+        let (mut data, ptr) = create_aligned_memory(get_required_size());
+        data.fill(0);
+        let mut map = MaxMemoryMap::new(ptr, data.len()).unwrap();
+        assert!(!map.is_full());
+
+        // Mark every first-level block as full
+        data[..8].fill(0xff);
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(5).unwrap();
+        assert!(!map.is_full());
+        assert_eq!(map.alloc().unwrap(), 0);
+    }
+
     #[test]
     fn test_multiple_maps_in_same_buffer() {
         let single_map_size = get_required_size();
         let (mut data, base_ptr) = create_aligned_memory(single_map_size * 2);
         data.fill(0);
+        assert!(
+            crate::assert_disjoint(&[(0, MapType::Max), (single_map_size, MapType::Max)]).is_ok()
+        );
 
         // Create first pointer for the first map
         let ptr1 = base_ptr;
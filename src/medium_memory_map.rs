@@ -0,0 +1,44 @@
+use crate::hier_memory_map::HierMemoryMap;
+
+/// Medium memory map implementation (3 levels, 16 bits at first level)
+pub type MediumMemoryMap = HierMemoryMap<16, 3>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_memory, MapType, MemoryMapError};
+
+    #[test]
+    fn test_medium_map_geometry() {
+        assert_eq!(MediumMemoryMap::CAPACITY, 16 * 64 * 64);
+        assert_eq!(MediumMemoryMap::REQUIRED_SIZE, (1 + 16 + 16 * 64) * 8);
+        assert_eq!(MapType::Medium.capacity(), MediumMemoryMap::CAPACITY);
+    }
+
+    #[test]
+    fn test_medium_map_full() {
+        let required_size = MapType::Medium.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+
+        assert!(matches!(
+            MediumMemoryMap::new(ptr, required_size - 8),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        let mut map = MediumMemoryMap::new(ptr, required_size).unwrap();
+        for expected in 0..MapType::Medium.capacity() {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(50_000).unwrap();
+        assert!(!map.is_full());
+        assert_eq!(map.alloc().unwrap(), 50_000);
+        assert!(matches!(
+            map.dealloc(MapType::Medium.capacity()),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+}
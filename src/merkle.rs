@@ -0,0 +1,240 @@
+//! Merkle commitment over the leaf words, for light clients and verifiers
+
+use crate::{MapType, MemoryMap, MemoryMapError};
+use solana_program::hash::hashv;
+
+/// Size of a node hash
+pub const MERKLE_NODE_SIZE: usize = 32;
+
+/// Domain separators keeping leaf and inner hashes apart
+const LEAF_DOMAIN: &[u8] = b"imm-leaf";
+const NODE_DOMAIN: &[u8] = b"imm-node";
+
+/// Persisted binary Merkle tree over the leaf words of a map
+///
+/// Nodes are stored heap-ordered (root at node 1, children of `n` at `2n` and
+/// `2n + 1`), with the leaf count padded to a power of two. Padding leaves
+/// commit to an empty word like any other free block.
+pub struct MerkleTree<'a> {
+    data: &'a mut [u8],
+    map_type: MapType,
+}
+
+impl<'a> MerkleTree<'a> {
+    /// Number of bytes needed for the tree of a `map_type` map
+    pub const fn size(map_type: MapType) -> usize {
+        2 * Self::width(map_type) * MERKLE_NODE_SIZE
+    }
+
+    /// Number of tree leaves, the leaf words rounded up to a power of two
+    const fn width(map_type: MapType) -> usize {
+        map_type.capacity().div_ceil(64).next_power_of_two()
+    }
+
+    /// Wrap a region written by [`MemoryMap::build_commitment`]
+    pub fn new(data: &'a mut [u8], map_type: MapType) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::size(map_type) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data, map_type })
+    }
+
+    /// Current root
+    pub fn root(&self) -> [u8; MERKLE_NODE_SIZE] {
+        self.node(1)
+    }
+
+    /// Sibling hashes from the leaf of `block` up to the root
+    pub fn proof(&self, block: usize) -> Result<Vec<[u8; MERKLE_NODE_SIZE]>, MemoryMapError> {
+        let width = Self::width(self.map_type);
+        if block >= width {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let mut proof = Vec::with_capacity(width.trailing_zeros() as usize);
+        let mut node = width + block;
+        while node > 1 {
+            proof.push(self.node(node ^ 1));
+            node >>= 1;
+        }
+        Ok(proof)
+    }
+
+    fn node(&self, node: usize) -> [u8; MERKLE_NODE_SIZE] {
+        let start = node * MERKLE_NODE_SIZE;
+        let mut hash = [0; MERKLE_NODE_SIZE];
+        hash.copy_from_slice(&self.data[start..start + MERKLE_NODE_SIZE]);
+        hash
+    }
+
+    fn set_node(&mut self, node: usize, hash: [u8; MERKLE_NODE_SIZE]) {
+        let start = node * MERKLE_NODE_SIZE;
+        self.data[start..start + MERKLE_NODE_SIZE].copy_from_slice(&hash);
+    }
+
+    /// Recompute the inner node `node` from its children
+    fn rehash_node(&mut self, node: usize) {
+        let hash = hashv(&[NODE_DOMAIN, &self.node(2 * node), &self.node(2 * node + 1)]);
+        self.set_node(node, hash.to_bytes());
+    }
+}
+
+/// Hash committing to leaf word `block` holding `word`
+pub fn merkle_leaf(block: usize, word: u64) -> [u8; MERKLE_NODE_SIZE] {
+    hashv(&[
+        LEAF_DOMAIN,
+        &(block as u64).to_le_bytes(),
+        &word.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Check that slot `index` is allocated in the map committed to by `root`
+///
+/// `word` is the leaf word holding the slot and `proof` its path from
+/// [`MerkleTree::proof`].
+pub fn verify_allocated(
+    root: &[u8; MERKLE_NODE_SIZE],
+    index: usize,
+    word: u64,
+    proof: &[[u8; MERKLE_NODE_SIZE]],
+) -> bool {
+    if word & (1 << (index & 0x3f)) == 0 {
+        return false;
+    }
+    let mut position = index >> 6;
+    let mut hash = merkle_leaf(position, word);
+    for sibling in proof {
+        let (left, right) = if position & 1 == 0 {
+            (&hash, sibling)
+        } else {
+            (sibling, &hash)
+        };
+        hash = hashv(&[NODE_DOMAIN, left, right]).to_bytes();
+        position >>= 1;
+    }
+    position == 0 && hash == *root
+}
+
+impl MemoryMap {
+    /// Build `tree` from every leaf word and return the root
+    pub fn build_commitment(
+        &self,
+        tree: &mut MerkleTree,
+    ) -> Result<[u8; MERKLE_NODE_SIZE], MemoryMapError> {
+        if tree.map_type != self.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        let width = MerkleTree::width(tree.map_type);
+        for block in 0..width {
+            tree.set_node(width + block, merkle_leaf(block, self.commit_word(block)?));
+        }
+        for node in (1..width).rev() {
+            tree.rehash_node(node);
+        }
+        Ok(tree.root())
+    }
+
+    /// Refresh the path of leaf word `block` after it changed, O(log n) hashes
+    pub fn update_commitment(
+        &self,
+        tree: &mut MerkleTree,
+        block: usize,
+    ) -> Result<[u8; MERKLE_NODE_SIZE], MemoryMapError> {
+        if tree.map_type != self.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        let width = MerkleTree::width(tree.map_type);
+        if block >= width {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let mut node = width + block;
+        tree.set_node(node, merkle_leaf(block, self.commit_word(block)?));
+        while node > 1 {
+            node >>= 1;
+            tree.rehash_node(node);
+        }
+        Ok(tree.root())
+    }
+
+    /// Root of `tree`, as kept up to date by the committed mutations
+    pub fn commitment(&self, tree: &MerkleTree) -> [u8; MERKLE_NODE_SIZE] {
+        tree.root()
+    }
+
+    /// Allocate a slot and update `tree`
+    pub fn alloc_committed(&mut self, tree: &mut MerkleTree) -> Result<usize, MemoryMapError> {
+        let index = self.alloc()?;
+        self.update_commitment(tree, index >> 6)?;
+        Ok(index)
+    }
+
+    /// Deallocate slot `index` and update `tree`
+    pub fn dealloc_committed(
+        &mut self,
+        tree: &mut MerkleTree,
+        index: usize,
+    ) -> Result<(), MemoryMapError> {
+        self.dealloc(index)?;
+        self.update_commitment(tree, index >> 6)?;
+        Ok(())
+    }
+
+    /// Leaf word `block` as committed, zero for padding leaves
+    fn commit_word(&self, block: usize) -> Result<u64, MemoryMapError> {
+        if block >= self.capacity().div_ceil(64) {
+            return Ok(0);
+        }
+        self.leaf_word(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_incremental_matches_rebuild() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = vec![0u8; MerkleTree::size(MapType::Small)];
+        let mut tree = MerkleTree::new(&mut region, MapType::Small).unwrap();
+        let empty = map.build_commitment(&mut tree).unwrap();
+
+        for _ in 0..100 {
+            map.alloc_committed(&mut tree).unwrap();
+        }
+        map.dealloc_committed(&mut tree, 70).unwrap();
+        let root = map.commitment(&tree);
+        assert_ne!(root, empty);
+
+        let mut fresh = vec![0u8; MerkleTree::size(MapType::Small)];
+        let mut rebuilt = MerkleTree::new(&mut fresh, MapType::Small).unwrap();
+        assert_eq!(map.build_commitment(&mut rebuilt).unwrap(), root);
+    }
+
+    #[test]
+    fn test_allocation_proof() {
+        let mut buffer = create_aligned_buffer(MapType::Medium.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Medium).unwrap();
+        let mut region = vec![0u8; MerkleTree::size(MapType::Medium)];
+        let mut tree = MerkleTree::new(&mut region, MapType::Medium).unwrap();
+        map.build_commitment(&mut tree).unwrap();
+        let index = map.alloc_above(5_000).unwrap();
+        let root = map.update_commitment(&mut tree, index >> 6).unwrap();
+
+        let word = 1 << (index & 0x3f);
+        let proof = tree.proof(index >> 6).unwrap();
+        assert!(verify_allocated(&root, index, word, &proof));
+        // Wrong slot, wrong word or a stale root do not verify
+        assert!(!verify_allocated(&root, index + 1, word, &proof));
+        assert!(!verify_allocated(&root, index, word | 2, &proof));
+        map.dealloc_committed(&mut tree, index).unwrap();
+        assert!(!verify_allocated(
+            &map.commitment(&tree),
+            index,
+            word,
+            &proof
+        ));
+    }
+}
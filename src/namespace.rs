@@ -0,0 +1,222 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+use core::ops::Range;
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Allocate the lowest free slot in `range`
+    fn alloc_within(&mut self, range: &Range<usize>) -> Result<usize, MemoryMapError> {
+        let index = self
+            .find_free_above(range.start)?
+            .filter(|index| *index < range.end)
+            .ok_or(MemoryMapError::NoAvailableSlots)?;
+        self.mark_allocated(index)?;
+        Ok(index)
+    }
+
+    /// Allocated slots in `range`, which starts on a block boundary
+    fn used_within(&self, range: &Range<usize>) -> Result<usize, MemoryMapError> {
+        let mut used = 0;
+        for block in range.start >> 6..range.end.div_ceil(64) {
+            let mut word = self.leaf_word(block)? & Self::LEAF_MASK;
+            let end = range.end - (block << 6);
+            if end < 64 {
+                word &= (1 << end) - 1;
+            }
+            used += word.count_ones() as usize;
+        }
+        Ok(used)
+    }
+}
+
+/// Map carved into `count` partitions of `width` slots, e.g. 16 markets of
+/// 1024 slots in one Standard map
+///
+/// Namespace `ns` owns indices `ns * width..(ns + 1) * width`. Indices stay
+/// global so data regions are addressed as with the plain map, but every
+/// operation is checked against the partition, so one market can neither
+/// take nor free another's slots. The partitions are explicit placements,
+/// a range set by `MemoryMap::reserve_range` is not skipped.
+pub struct Namespaces {
+    map: MemoryMap,
+    width: usize,
+    count: usize,
+}
+
+impl Namespaces {
+    /// Partition `map` into `count` namespaces of `width` slots
+    ///
+    /// `width` must be a non-zero multiple of 64 and the partitions must fit
+    /// the capacity, otherwise this fails with `InvalidIndex`.
+    pub fn new(map: MemoryMap, width: usize, count: usize) -> Result<Self, MemoryMapError> {
+        let fits = width
+            .checked_mul(count)
+            .is_some_and(|slots| slots <= map.capacity());
+        if width == 0 || !width.is_multiple_of(64) || !fits {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(Self { map, width, count })
+    }
+
+    /// Underlying map shared by all namespaces
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Number of namespaces
+    pub const fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Slots of each namespace
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Indices owned by namespace `ns`
+    pub fn range(&self, ns: usize) -> Result<Range<usize>, MemoryMapError> {
+        if ns >= self.count {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(ns * self.width..(ns + 1) * self.width)
+    }
+
+    /// Namespace owning `index`
+    pub fn namespace_of(&self, index: usize) -> Result<usize, MemoryMapError> {
+        let ns = index / self.width;
+        if ns >= self.count {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(ns)
+    }
+
+    /// Allocate the lowest free slot of namespace `ns`
+    ///
+    /// Fails with `NoAvailableSlots` once the namespace is full, even if
+    /// others still have room.
+    pub fn alloc_in(&mut self, ns: usize) -> Result<usize, MemoryMapError> {
+        let range = self.range(ns)?;
+        match &mut self.map {
+            MemoryMap::Max(map) => map.alloc_within(&range),
+            MemoryMap::Standard(map) => map.alloc_within(&range),
+            MemoryMap::Small(map) => map.alloc_within(&range),
+            MemoryMap::Huge(map) => map.alloc_within(&range),
+            MemoryMap::Tiny(map) => map.alloc_within(&range),
+            MemoryMap::Medium(map) => map.alloc_within(&range),
+        }
+    }
+
+    /// Free `index`, which must belong to namespace `ns`
+    pub fn dealloc_in(&mut self, ns: usize, index: usize) -> Result<(), MemoryMapError> {
+        if !self.range(ns)?.contains(&index) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.map.dealloc(index)
+    }
+
+    /// Usable indices of namespace `ns`, cut at the slot limit of a map
+    /// created with [`MemoryMap::with_capacity`]
+    fn usable_range(&self, ns: usize) -> Result<Range<usize>, MemoryMapError> {
+        let range = self.range(ns)?;
+        let end = range.end.min(self.map.slot_limit()).max(range.start);
+        Ok(range.start..end)
+    }
+
+    /// Allocated slots of namespace `ns`
+    ///
+    /// The reserved tail of a [`MemoryMap::with_capacity`] map is not counted.
+    pub fn used_in(&self, ns: usize) -> Result<usize, MemoryMapError> {
+        let range = self.usable_range(ns)?;
+        match &self.map {
+            MemoryMap::Max(map) => map.used_within(&range),
+            MemoryMap::Standard(map) => map.used_within(&range),
+            MemoryMap::Small(map) => map.used_within(&range),
+            MemoryMap::Huge(map) => map.used_within(&range),
+            MemoryMap::Tiny(map) => map.used_within(&range),
+            MemoryMap::Medium(map) => map.used_within(&range),
+        }
+    }
+
+    /// Free slots of namespace `ns`, below the slot limit
+    pub fn free_in(&self, ns: usize) -> Result<usize, MemoryMapError> {
+        Ok(self.usable_range(ns)?.len() - self.used_in(ns)?)
+    }
+
+    /// Give back the underlying map
+    pub fn into_inner(self) -> MemoryMap {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_partitions_are_isolated() {
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Standard).unwrap();
+        let mut markets = Namespaces::new(map, 1_024, 16).unwrap();
+
+        assert_eq!(markets.alloc_in(3).unwrap(), 3_072);
+        assert_eq!(markets.alloc_in(0).unwrap(), 0);
+        assert_eq!(markets.alloc_in(3).unwrap(), 3_073);
+        assert_eq!(markets.namespace_of(3_073).unwrap(), 3);
+        assert_eq!(markets.used_in(3).unwrap(), 2);
+        assert_eq!(markets.free_in(4).unwrap(), 1_024);
+
+        // Market 0 cannot free a slot of market 3
+        assert!(matches!(
+            markets.dealloc_in(0, 3_072),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        markets.dealloc_in(3, 3_072).unwrap();
+        assert_eq!(markets.used_in(3).unwrap(), 1);
+        assert!(matches!(
+            markets.alloc_in(16),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_full_namespace() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut pools = Namespaces::new(map, 64, 3).unwrap();
+        for expected in 64..128 {
+            assert_eq!(pools.alloc_in(1).unwrap(), expected);
+        }
+        assert!(matches!(
+            pools.alloc_in(1),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert_eq!(pools.free_in(1).unwrap(), 0);
+        assert_eq!(pools.alloc_in(2).unwrap(), 128);
+        assert_eq!(pools.into_inner().used_count().unwrap(), 65);
+
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        for (width, count) in [(0, 1), (32, 2), (64, 2)] {
+            let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+            assert!(matches!(
+                Namespaces::new(map, width, count),
+                Err(MemoryMapError::InvalidIndex)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_slot_limit() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Small.required_size());
+        let map = MemoryMap::with_capacity(&mut buffer, 0, 1_000).unwrap();
+        let mut pools = Namespaces::new(map, 1_024, 4).unwrap();
+
+        // The reserved tail from 1000 on is neither used nor free
+        assert_eq!(pools.used_in(0).unwrap(), 0);
+        assert_eq!(pools.free_in(0).unwrap(), 1_000);
+        assert_eq!(pools.used_in(1).unwrap(), 0);
+        assert_eq!(pools.free_in(1).unwrap(), 0);
+
+        pools.alloc_in(0).unwrap();
+        assert_eq!(pools.used_in(0).unwrap(), 1);
+        assert_eq!(pools.free_in(0).unwrap(), 999);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{MemoryMap, MemoryMapError, OrderSide};
+
+/// Bits of an order ID holding the slot index, enough for every map type
+pub const ORDER_SLOT_BITS: u32 = 24;
+
+/// Bits of a `u64` order ID holding the sequence number
+pub const ORDER_SEQ_BITS_U64: u32 = 64 - ORDER_SLOT_BITS - 1;
+
+const SLOT_MASK: u64 = (1 << ORDER_SLOT_BITS) - 1;
+const SIDE_BIT: u64 = 1 << ORDER_SLOT_BITS;
+
+/// Order ID made of a monotonic sequence number, the slot holding the order
+/// and its side
+///
+/// Packed IDs put the sequence in the high bits, so they sort in placement
+/// order and a reused slot never repeats an ID. The slot is recovered from
+/// the ID without a lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderId {
+    pub seq: u64,
+    pub slot: usize,
+    pub side: OrderSide,
+}
+
+impl OrderId {
+    /// Pack as `seq << 25 | side << 24 | slot`
+    ///
+    /// Fails with `InvalidIndex` if the sequence does not fit
+    /// [`ORDER_SEQ_BITS_U64`] bits or the slot [`ORDER_SLOT_BITS`] bits.
+    pub fn to_u64(self) -> Result<u64, MemoryMapError> {
+        if self.seq >> ORDER_SEQ_BITS_U64 != 0 {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(self.seq << (ORDER_SLOT_BITS + 1) | self.low_bits()?)
+    }
+
+    /// Unpack an ID created by [`OrderId::to_u64`]
+    pub const fn from_u64(id: u64) -> Self {
+        Self {
+            seq: id >> (ORDER_SLOT_BITS + 1),
+            slot: (id & SLOT_MASK) as usize,
+            side: side_of(id),
+        }
+    }
+
+    /// Pack as `seq << 64 | side << 24 | slot`, every sequence fits
+    pub fn to_u128(self) -> Result<u128, MemoryMapError> {
+        Ok((self.seq as u128) << 64 | self.low_bits()? as u128)
+    }
+
+    /// Unpack an ID created by [`OrderId::to_u128`]
+    pub const fn from_u128(id: u128) -> Self {
+        Self {
+            seq: (id >> 64) as u64,
+            slot: (id as u64 & SLOT_MASK) as usize,
+            side: side_of(id as u64),
+        }
+    }
+
+    fn low_bits(self) -> Result<u64, MemoryMapError> {
+        let slot = self.slot as u64;
+        if slot & !SLOT_MASK != 0 {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(match self.side {
+            OrderSide::Bid => slot,
+            OrderSide::Ask => slot | SIDE_BIT,
+        })
+    }
+}
+
+const fn side_of(low: u64) -> OrderSide {
+    if low & SIDE_BIT == 0 {
+        OrderSide::Bid
+    } else {
+        OrderSide::Ask
+    }
+}
+
+impl MemoryMap {
+    /// Sequence number the next order gets, `None` without a header
+    pub fn order_seq(&self) -> Option<u64> {
+        self.header().map(|header| header.order_seq)
+    }
+
+    /// Take the next sequence number from the header
+    ///
+    /// Only maps with a header keep the counter, others fail with
+    /// `InvalidHeader`.
+    pub fn next_order_seq(&mut self) -> Result<u64, MemoryMapError> {
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        let seq = header.order_seq;
+        header.order_seq = seq.wrapping_add(1);
+        Ok(seq)
+    }
+
+    /// Allocate a slot for an order on `side` and give it the next ID
+    pub fn alloc_order(&mut self, side: OrderSide) -> Result<OrderId, MemoryMapError> {
+        if self.header().is_none() {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        let slot = self.alloc()?;
+        let seq = self.next_order_seq()?;
+        Ok(OrderId { seq, slot, side })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let id = OrderId {
+            seq: 12_345,
+            slot: MapType::Huge.capacity() - 1,
+            side: OrderSide::Ask,
+        };
+        assert_eq!(OrderId::from_u64(id.to_u64().unwrap()), id);
+        assert_eq!(OrderId::from_u128(id.to_u128().unwrap()), id);
+
+        let wide = OrderId {
+            seq: u64::MAX,
+            slot: 3,
+            side: OrderSide::Bid,
+        };
+        assert_eq!(OrderId::from_u128(wide.to_u128().unwrap()), wide);
+        assert!(matches!(wide.to_u64(), Err(MemoryMapError::InvalidIndex)));
+        let far = OrderId {
+            slot: 1 << ORDER_SLOT_BITS,
+            ..id
+        };
+        assert!(matches!(far.to_u128(), Err(MemoryMapError::InvalidIndex)));
+
+        // Later sequence numbers sort after earlier ones whatever the slot
+        let early = OrderId { seq: 1, ..id }.to_u64().unwrap();
+        let late = OrderId { seq: 2, ..wide }.to_u64().unwrap();
+        assert!(early < late);
+    }
+
+    #[test]
+    fn test_alloc_order_persists_seq() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        let first = map.alloc_order(OrderSide::Bid).unwrap();
+        let second = map.alloc_order(OrderSide::Ask).unwrap();
+        assert_eq!((first.seq, first.slot), (0, 0));
+        assert_eq!((second.seq, second.slot), (1, 1));
+
+        // A freed slot comes back with a fresh ID
+        map.dealloc(first.slot).unwrap();
+        let mut map = MemoryMap::load(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(map.order_seq(), Some(2));
+        let third = map.alloc_order(OrderSide::Bid).unwrap();
+        assert_eq!((third.seq, third.slot), (2, 0));
+        assert_ne!(third.to_u64().unwrap(), first.to_u64().unwrap());
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert!(matches!(
+            map.alloc_order(OrderSide::Bid),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert_eq!(map.used_count().unwrap(), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{MapType, MemoryMap, MemoryMapError};
+
+/// Size of the owner key stored for each slot, a Solana `Pubkey`
+pub const OWNER_SIZE: usize = 32;
+
+/// Map that records an owner key per slot in a companion region and only
+/// lets that owner free the slot
+///
+/// The region holds one `OWNER_SIZE` entry per slot, indexed like the map.
+/// Pass a `Pubkey` as `key.to_bytes()`.
+pub struct OwnedMemoryMap<'a> {
+    map: MemoryMap,
+    owners: &'a mut [u8],
+}
+
+impl<'a> OwnedMemoryMap<'a> {
+    /// Number of bytes needed for the owner region of a `map_type` map
+    pub const fn required_size(map_type: MapType) -> usize {
+        map_type.capacity() * OWNER_SIZE
+    }
+
+    /// Pair `map` with an owner region previously zeroed or written by this type
+    pub fn new(map: MemoryMap, owners: &'a mut [u8]) -> Result<Self, MemoryMapError> {
+        if owners.len() < Self::required_size(map.map_type()) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { map, owners })
+    }
+
+    /// Map whose slots the owner region tracks
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Split back into the map and the owner region
+    pub fn into_parts(self) -> (MemoryMap, &'a mut [u8]) {
+        (self.map, self.owners)
+    }
+
+    /// Allocate a new slot owned by `owner`
+    pub fn alloc_owned(&mut self, owner: &[u8; OWNER_SIZE]) -> Result<usize, MemoryMapError> {
+        let index = self.map.alloc()?;
+        self.entry_mut(index).copy_from_slice(owner);
+        Ok(index)
+    }
+
+    /// Free slot `index` if `signer` owns it
+    ///
+    /// Fails with `OwnerMismatch` for any other key and with `InvalidIndex`
+    /// if the slot is not allocated, leaving the slot untouched.
+    pub fn dealloc_owned(
+        &mut self,
+        index: usize,
+        signer: &[u8; OWNER_SIZE],
+    ) -> Result<(), MemoryMapError> {
+        let owner = self.owner(index)?.ok_or(MemoryMapError::InvalidIndex)?;
+        if owner != *signer {
+            return Err(MemoryMapError::OwnerMismatch);
+        }
+        self.map.dealloc(index)?;
+        self.entry_mut(index).fill(0);
+        Ok(())
+    }
+
+    /// Owner of slot `index`, `None` if the slot is free
+    pub fn owner(&self, index: usize) -> Result<Option<[u8; OWNER_SIZE]>, MemoryMapError> {
+        if !self.map.is_allocated(index)? {
+            return Ok(None);
+        }
+        let start = index * OWNER_SIZE;
+        let mut owner = [0; OWNER_SIZE];
+        owner.copy_from_slice(&self.owners[start..start + OWNER_SIZE]);
+        Ok(Some(owner))
+    }
+
+    /// Owner entry of `index`, in range for every slot of the map
+    fn entry_mut(&mut self, index: usize) -> &mut [u8] {
+        let start = index * OWNER_SIZE;
+        &mut self.owners[start..start + OWNER_SIZE]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_only_owner_deallocs() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; OwnedMemoryMap::required_size(MapType::Tiny)];
+        let mut owned = OwnedMemoryMap::new(map, &mut region).unwrap();
+        let (alice, bob) = ([1; OWNER_SIZE], [2; OWNER_SIZE]);
+
+        assert_eq!(owned.alloc_owned(&alice).unwrap(), 0);
+        assert_eq!(owned.alloc_owned(&bob).unwrap(), 1);
+        assert_eq!(owned.owner(1).unwrap(), Some(bob));
+        assert_eq!(owned.owner(2).unwrap(), None);
+
+        assert!(matches!(
+            owned.dealloc_owned(0, &bob),
+            Err(MemoryMapError::OwnerMismatch)
+        ));
+        assert!(owned.map().is_allocated(0).unwrap());
+        owned.dealloc_owned(0, &alice).unwrap();
+        assert_eq!(owned.owner(0).unwrap(), None);
+        assert!(matches!(
+            owned.dealloc_owned(0, &alice),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        // The freed slot goes to the next owner with a clean entry
+        assert_eq!(owned.alloc_owned(&bob).unwrap(), 0);
+        let (_, region) = owned.into_parts();
+        assert_eq!(region[..OWNER_SIZE], bob);
+    }
+
+    #[test]
+    fn test_owner_region_too_small() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; OwnedMemoryMap::required_size(MapType::Tiny) - 1];
+        assert!(matches!(
+            OwnedMemoryMap::new(map, &mut region),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{MapType, MemoryMap, MemoryMapError};
+use core::mem::align_of;
+
+/// Map built at the first 8-byte aligned position at or after a requested
+/// offset, see [`MemoryMap::new_from_slice_padded`]
+pub struct PaddedMemoryMap {
+    map: MemoryMap,
+    padding: usize,
+    data_offset: usize,
+}
+
+impl PaddedMemoryMap {
+    /// Map built at the aligned offset
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Map built at the aligned offset, for allocating and freeing
+    pub fn map_mut(&mut self) -> &mut MemoryMap {
+        &mut self.map
+    }
+
+    /// Give back the underlying map
+    pub fn into_inner(self) -> MemoryMap {
+        self.map
+    }
+
+    /// Bytes skipped between the requested offset and the bitmap
+    pub const fn padding(&self) -> usize {
+        self.padding
+    }
+
+    /// Offset of the bitmap in the slice, requested offset plus padding
+    pub const fn data_offset(&self) -> usize {
+        self.data_offset
+    }
+
+    /// Offset of the first byte after the bitmap
+    pub const fn end_offset(&self) -> usize {
+        self.data_offset + self.map.map_type().required_size()
+    }
+}
+
+impl MemoryMap {
+    /// Create a map at `offset`, rounded up to the next 8-byte aligned address
+    ///
+    /// Saves hand-computing the padding after an Anchor discriminator and odd
+    /// sized fields. The padding must be the same every time the account is
+    /// opened, which holds as long as the account data itself is 8-byte
+    /// aligned, as it is in the Solana runtime.
+    pub fn new_from_slice_padded(
+        data: &mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<PaddedMemoryMap, MemoryMapError> {
+        let start = data
+            .get(offset..)
+            .ok_or(MemoryMapError::InvalidOffset)?
+            .as_ptr() as usize;
+        let padding = start.next_multiple_of(align_of::<u64>()) - start;
+        let data_offset = offset + padding;
+        if data_offset > data.len() {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        Ok(PaddedMemoryMap {
+            map: Self::new_from_slice(data, data_offset, map_type)?,
+            padding,
+            data_offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_padding_after_discriminator() {
+        for offset in 8..=16 {
+            let mut buffer = create_aligned_buffer(64 + MapType::Small.required_size());
+            let base = buffer.as_ptr() as usize % 8;
+            let mut padded =
+                MemoryMap::new_from_slice_padded(&mut buffer, offset, MapType::Small).unwrap();
+            assert_eq!((base + padded.data_offset()) % 8, 0);
+            assert_eq!(padded.data_offset(), offset + padded.padding());
+            assert!(padded.padding() < 8);
+            assert_eq!(
+                padded.end_offset(),
+                padded.data_offset() + MapType::Small.required_size()
+            );
+            assert_eq!(padded.map_mut().alloc().unwrap(), 0);
+            assert_eq!(padded.map().used_count().unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn test_padded_errors() {
+        let size = MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size + 16);
+        let aligned = (8 - buffer.as_ptr() as usize % 8) % 8;
+        let data = &mut buffer[aligned..aligned + 8 + size];
+        let padded = MemoryMap::new_from_slice_padded(data, 5, MapType::Small).unwrap();
+        assert_eq!(padded.padding(), 3);
+        assert_eq!(padded.into_inner().map_type(), MapType::Small);
+
+        assert!(matches!(
+            MemoryMap::new_from_slice_padded(data, 9, MapType::Small),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        assert!(matches!(
+            MemoryMap::new_from_slice_padded(data, data.len() + 1, MapType::Small),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+use solana_program::pubkey::{Pubkey, PubkeyError};
+
+/// Seed encoding a slot index, little-endian u64
+pub const fn slot_seed(index: usize) -> [u8; 8] {
+    (index as u64).to_le_bytes()
+}
+
+/// Find the PDA shadowing slot `index`, seeded with `[seeds_prefix, slot_seed(index)]`
+///
+/// Returns the address and its bump seed.
+pub fn slot_pda(index: usize, program_id: &Pubkey, seeds_prefix: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seeds_prefix, &slot_seed(index)], program_id)
+}
+
+/// Re-derive the PDA of slot `index` from a known bump seed
+///
+/// Much cheaper than [`slot_pda`] on-chain, use it to check an account passed
+/// in by the client.
+pub fn slot_pda_with_bump(
+    index: usize,
+    program_id: &Pubkey,
+    seeds_prefix: &[u8],
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[seeds_prefix, &slot_seed(index), &[bump]], program_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_pda_roundtrip() {
+        let program_id = Pubkey::new_from_array([7; 32]);
+        let (address, bump) = slot_pda(42, &program_id, b"order");
+
+        assert_eq!(
+            slot_pda_with_bump(42, &program_id, b"order", bump).unwrap(),
+            address
+        );
+        // Deterministic, and distinct per index and prefix
+        assert_eq!(slot_pda(42, &program_id, b"order"), (address, bump));
+        assert_ne!(slot_pda(43, &program_id, b"order").0, address);
+        assert_ne!(slot_pda(42, &program_id, b"fill").0, address);
+    }
+
+    #[test]
+    fn test_slot_seed() {
+        assert_eq!(slot_seed(0x0102), [2, 1, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{MapType, MemoryMap, MemoryMapError};
+
+/// Persisted set of slots waiting to be freed, one bit per slot
+///
+/// Lets settlement mark slots in one instruction and free them in a later one,
+/// the slots stay allocated in between. An all-zero region has nothing pending.
+pub struct PendingDeallocs<'a> {
+    data: &'a mut [u8],
+    map_type: MapType,
+}
+
+impl<'a> PendingDeallocs<'a> {
+    /// Number of bytes needed to track a map of type `map_type`
+    pub const fn size(map_type: MapType) -> usize {
+        map_type.capacity().div_ceil(8)
+    }
+
+    /// Wrap a region previously zeroed or written by this extension
+    pub fn new(data: &'a mut [u8], map_type: MapType) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::size(map_type) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data, map_type })
+    }
+
+    /// Check whether slot `index` is waiting to be freed
+    pub fn is_pending(&self, index: usize) -> bool {
+        index < self.map_type.capacity() && self.data[index >> 3] & (1 << (index & 7)) != 0
+    }
+
+    /// Number of slots waiting to be freed
+    pub fn pending_count(&self) -> usize {
+        self.data[..Self::size(self.map_type)]
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    fn set(&mut self, index: usize) {
+        self.data[index >> 3] |= 1 << (index & 7);
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.data[index >> 3] &= !(1 << (index & 7));
+    }
+}
+
+impl MemoryMap {
+    /// Record allocated slot `index` in `pending`, it stays allocated until
+    /// [`MemoryMap::finalize_deallocs`]
+    pub fn mark_for_dealloc(
+        &self,
+        pending: &mut PendingDeallocs,
+        index: usize,
+    ) -> Result<(), MemoryMapError> {
+        if pending.map_type != self.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        if !self.is_allocated(index)? {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        pending.set(index);
+        Ok(())
+    }
+
+    /// Free every slot marked in `pending` and return how many were freed
+    ///
+    /// Each mark is cleared as its slot is freed, so a failed call can be
+    /// retried.
+    pub fn finalize_deallocs(
+        &mut self,
+        pending: &mut PendingDeallocs,
+    ) -> Result<usize, MemoryMapError> {
+        if pending.map_type != self.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+
+        let mut freed = 0;
+        for byte in 0..PendingDeallocs::size(pending.map_type) {
+            let mut bits = pending.data[byte];
+            while bits != 0 {
+                let index = (byte << 3) + bits.trailing_zeros() as usize;
+                self.dealloc(index)?;
+                pending.clear(index);
+                freed += 1;
+                bits &= bits - 1;
+            }
+        }
+
+        Ok(freed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_two_phase_dealloc() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = vec![0u8; PendingDeallocs::size(MapType::Small)];
+        for _ in 0..100 {
+            map.alloc().unwrap();
+        }
+
+        let mut pending = PendingDeallocs::new(&mut region, MapType::Small).unwrap();
+        for index in [3, 64, 99] {
+            map.mark_for_dealloc(&mut pending, index).unwrap();
+        }
+        // Marking twice is harmless, free slots cannot be marked
+        map.mark_for_dealloc(&mut pending, 3).unwrap();
+        assert!(matches!(
+            map.mark_for_dealloc(&mut pending, 100),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(map.is_allocated(3).unwrap());
+        assert_eq!(map.alloc().unwrap(), 100);
+
+        // A later instruction picks the marks up from the same region
+        let mut pending = PendingDeallocs::new(&mut region, MapType::Small).unwrap();
+        assert_eq!(pending.pending_count(), 3);
+        assert!(pending.is_pending(64));
+        assert_eq!(map.finalize_deallocs(&mut pending).unwrap(), 3);
+        assert_eq!(pending.pending_count(), 0);
+        assert!(!map.is_allocated(64).unwrap());
+        assert_eq!(map.alloc().unwrap(), 3);
+        assert_eq!(map.finalize_deallocs(&mut pending).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pending_checks_map_type() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        map.alloc().unwrap();
+
+        let mut region = vec![0u8; PendingDeallocs::size(MapType::Small)];
+        let mut pending = PendingDeallocs::new(&mut region, MapType::Small).unwrap();
+        assert!(matches!(
+            map.mark_for_dealloc(&mut pending, 0),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+        assert!(matches!(
+            PendingDeallocs::new(&mut [0; 7], MapType::Tiny),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{allocator::IndexAllocator, MapType, MemoryMap, MemoryMapError};
+
+/// Map with a companion bitmap of pinned slots that cannot be freed
+///
+/// Pinning protects critical slots, e.g. the market authority's seat, from
+/// code paths that iterate and dealloc. The region holds one bit per slot,
+/// indexed like the map.
+pub struct PinnedMemoryMap<'a> {
+    map: MemoryMap,
+    pins: &'a mut [u8],
+}
+
+impl<'a> PinnedMemoryMap<'a> {
+    /// Number of bytes needed for the pin bitmap of a `map_type` map
+    pub const fn required_size(map_type: MapType) -> usize {
+        map_type.capacity().div_ceil(8)
+    }
+
+    /// Pair `map` with a pin bitmap previously zeroed or written by this type
+    pub fn new(map: MemoryMap, pins: &'a mut [u8]) -> Result<Self, MemoryMapError> {
+        if pins.len() < Self::required_size(map.map_type()) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { map, pins })
+    }
+
+    /// Map whose slots the pin bitmap guards
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Split back into the map and the pin bitmap
+    pub fn into_parts(self) -> (MemoryMap, &'a mut [u8]) {
+        (self.map, self.pins)
+    }
+
+    /// Allocate a new slot
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        self.map.alloc()
+    }
+
+    /// Deallocate slot `index`, failing with `SlotPinned` while it is pinned
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        if self.is_pinned(index)? {
+            return Err(MemoryMapError::SlotPinned);
+        }
+        self.map.dealloc(index)
+    }
+
+    /// Protect allocated slot `index` from deallocation
+    pub fn pin(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        if !self.map.is_allocated(index)? {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.pins[index >> 3] |= 1 << (index & 7);
+        Ok(())
+    }
+
+    /// Allow slot `index` to be freed again
+    pub fn unpin(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        if index >= self.map.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.pins[index >> 3] &= !(1 << (index & 7));
+        Ok(())
+    }
+
+    /// Check whether slot `index` is pinned
+    pub fn is_pinned(&self, index: usize) -> Result<bool, MemoryMapError> {
+        if index >= self.map.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(self.pins[index >> 3] & (1 << (index & 7)) != 0)
+    }
+}
+
+impl IndexAllocator for PinnedMemoryMap<'_> {
+    fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        Self::alloc(self)
+    }
+
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        Self::dealloc(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        self.map.is_allocated(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_pinned_slot_survives_sweep() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = vec![0u8; PinnedMemoryMap::required_size(MapType::Small)];
+        let mut pinned = PinnedMemoryMap::new(map, &mut region).unwrap();
+        for _ in 0..10 {
+            pinned.alloc().unwrap();
+        }
+        pinned.pin(3).unwrap();
+        assert!(matches!(pinned.pin(10), Err(MemoryMapError::InvalidIndex)));
+
+        // A sweep freeing everything it can leaves the pinned slot alone
+        for index in 0..10 {
+            let result = IndexAllocator::dealloc(&mut pinned, index);
+            assert_eq!(result.is_err(), index == 3);
+        }
+        assert!(matches!(pinned.dealloc(3), Err(MemoryMapError::SlotPinned)));
+        assert!(pinned.map().is_allocated(3).unwrap());
+        assert_eq!(pinned.map().used_count().unwrap(), 1);
+
+        pinned.unpin(3).unwrap();
+        assert!(!pinned.is_pinned(3).unwrap());
+        pinned.dealloc(3).unwrap();
+        assert_eq!(pinned.map().used_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pin_region_too_small() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        assert!(matches!(
+            PinnedMemoryMap::new(map, &mut [0u8; 7]),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
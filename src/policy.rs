@@ -0,0 +1,320 @@
+use crate::{
+    backend::Backend, hier_memory_map::HierMemoryMap, MapHeader, MemoryMap, MemoryMapError,
+};
+use core::ops::Range;
+
+/// Increment of the splitmix64 generator behind [`AllocPolicy::Random`]
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Where `alloc` places a new slot, persisted in the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocPolicy {
+    /// Lowest free slot, the default
+    FirstFit,
+    /// Highest free slot
+    LastFit,
+    /// First free slot of the next first-level subtree after the last
+    /// allocation, wrapping around, to spread writes across pages
+    RoundRobin,
+    /// First free slot at or after a pseudo-random position, wrapping around
+    Random { seed: u64 },
+    /// First free slot from the leaf word after the last one used, wrapping
+    /// around, so churn is spread over every leaf word instead of the first
+    RotatingLeaf,
+}
+
+impl AllocPolicy {
+    /// Policy recorded in `header`, the random variant with its current state
+    pub fn from_header(header: &MapHeader) -> Result<Self, MemoryMapError> {
+        match header.alloc_policy {
+            0 => Ok(Self::FirstFit),
+            1 => Ok(Self::LastFit),
+            2 => Ok(Self::RoundRobin),
+            3 => Ok(Self::Random {
+                seed: header.policy_state,
+            }),
+            4 => Ok(Self::RotatingLeaf),
+            _ => Err(MemoryMapError::InvalidHeader),
+        }
+    }
+
+    const fn code(self) -> u64 {
+        match self {
+            Self::FirstFit => 0,
+            Self::LastFit => 1,
+            Self::RoundRobin => 2,
+            Self::Random { .. } => 3,
+            Self::RotatingLeaf => 4,
+        }
+    }
+}
+
+/// Next output of the splitmix64 generator, advancing `state`
+const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(GOLDEN_GAMMA);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Free slot picked by the header's policy, `None` for first-fit or
+    /// without a header
+    ///
+    /// A candidate in the reserved range resumes the search in the policy's
+    /// own direction from the edge of the range. Advances the round-robin or
+    /// rotating cursor from the slot actually picked, and the random state.
+    pub(crate) fn policy_candidate(&mut self) -> Result<Option<usize>, MemoryMapError> {
+        let Some(mut header) = self.header() else {
+            return Ok(None);
+        };
+        let reserved = self.reserved_range();
+        // Safety: the header outlives the map, like the bitmap itself
+        let header = unsafe { header.as_mut() };
+        let start = match AllocPolicy::from_header(header)? {
+            AllocPolicy::FirstFit => return Ok(None),
+            AllocPolicy::LastFit => return self.last_free(reserved.as_ref()).map(Some),
+            AllocPolicy::RoundRobin => {
+                let subtree = Self::CAPACITY / L1_BITS;
+                let start = (header.policy_state as usize % L1_BITS) * subtree;
+                let index = self.free_from(start, reserved.as_ref())?;
+                header.policy_state = ((index / subtree + 1) % L1_BITS) as u64;
+                return Ok(Some(index));
+            }
+            AllocPolicy::Random { .. } => {
+                splitmix64(&mut header.policy_state) as usize % Self::CAPACITY
+            }
+            AllocPolicy::RotatingLeaf => {
+                // The cursor holds the last leaf word used
+                let start = (header.policy_state as usize + 1) % Self::LEAF_WORDS;
+                let index = self.free_from(start << 6, reserved.as_ref())?;
+                header.policy_state = (index >> 6) as u64;
+                return Ok(Some(index));
+            }
+        };
+        self.free_from(start, reserved.as_ref()).map(Some)
+    }
+
+    /// Highest free slot outside `reserved`
+    pub(crate) fn last_free(
+        &self,
+        reserved: Option<&Range<usize>>,
+    ) -> Result<usize, MemoryMapError> {
+        let index = self.find_last_free()?;
+        match reserved.filter(|reserved| reserved.contains(&index)) {
+            // Nothing above the range is free, continue downwards below it
+            Some(reserved) if reserved.start > 0 => self
+                .find_free_below(reserved.start - 1)?
+                .ok_or(MemoryMapError::NoAvailableSlots),
+            Some(_) => Err(MemoryMapError::NoAvailableSlots),
+            None => Ok(index),
+        }
+    }
+
+    /// First free slot at or after `start` outside `reserved`, wrapping around
+    /// to the lowest one
+    fn free_from(
+        &self,
+        start: usize,
+        reserved: Option<&Range<usize>>,
+    ) -> Result<usize, MemoryMapError> {
+        let index = match self.find_free_above(start)? {
+            Some(index) => index,
+            None => self.find_first_free()?,
+        };
+        let Some(reserved) = reserved.filter(|reserved| reserved.contains(&index)) else {
+            return Ok(index);
+        };
+
+        // Continue past the range, then wrap around to the slots before it
+        if reserved.end < Self::CAPACITY {
+            if let Some(index) = self.find_free_above(reserved.end)? {
+                return Ok(index);
+            }
+        }
+        let index = self.find_first_free()?;
+        if reserved.contains(&index) {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        Ok(index)
+    }
+}
+
+impl MemoryMap {
+    /// Placement policy used by `alloc`, first-fit for maps without a header
+    pub fn alloc_policy(&self) -> Result<AllocPolicy, MemoryMapError> {
+        self.header()
+            .map_or(Ok(AllocPolicy::FirstFit), AllocPolicy::from_header)
+    }
+
+    /// Choose where `alloc` places new slots from now on
+    ///
+    /// Only maps with a header persist a policy. Policies other than
+    /// first-fit skip the allocation hints; `alloc_near`, `alloc_above` and
+    /// the other explicit placements are not affected.
+    pub fn set_alloc_policy(&mut self, policy: AllocPolicy) -> Result<(), MemoryMapError> {
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        header.alloc_policy = policy.code();
+        header.policy_state = match policy {
+            AllocPolicy::Random { seed } => seed,
+            _ => 0,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    fn init_map(buffer: &mut [u8], map_type: MapType, policy: AllocPolicy) -> MemoryMap {
+        let mut map = MemoryMap::init(buffer, 0, map_type).unwrap();
+        map.set_alloc_policy(policy).unwrap();
+        map
+    }
+
+    #[test]
+    fn test_last_fit_and_round_robin() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Standard, AllocPolicy::LastFit);
+        assert_eq!(map.alloc().unwrap(), MapType::Standard.capacity() - 1);
+        assert_eq!(map.alloc().unwrap(), MapType::Standard.capacity() - 2);
+
+        // Standard has four first-level subtrees of 4096 slots
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Standard, AllocPolicy::RoundRobin);
+        let placed: Vec<_> = (0..6).map(|_| map.alloc().unwrap()).collect();
+        assert_eq!(placed, [0, 4096, 8192, 12288, 1, 4097]);
+
+        // The cursor is persisted with the policy
+        let mut map = MemoryMap::load(&mut buffer, 0, MapType::Standard).unwrap();
+        assert_eq!(map.alloc_policy().unwrap(), AllocPolicy::RoundRobin);
+        assert_eq!(map.alloc().unwrap(), 8193);
+    }
+
+    #[test]
+    fn test_rotating_leaf_wraps() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Small, AllocPolicy::RotatingLeaf);
+        // The cursor starts at leaf word 0, so the first scan begins at word 1
+        assert_eq!(map.alloc().unwrap(), 64);
+        assert_eq!(map.alloc().unwrap(), 128);
+        map.dealloc(64).unwrap();
+        assert_eq!(map.alloc().unwrap(), 192);
+
+        // The last leaf word wraps around to the first
+        for leaf in 4..64 {
+            assert_eq!(map.alloc().unwrap(), leaf << 6);
+        }
+        assert_eq!(map.alloc().unwrap(), 0);
+        assert_eq!(map.alloc().unwrap(), 64);
+
+        // With a single leaf word the policy degrades to first-fit
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Tiny.required_size());
+        let mut map = init_map(&mut buffer, MapType::Tiny, AllocPolicy::RotatingLeaf);
+        for expected in 0..64 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_random_is_seeded() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let run = |seed| {
+            let mut buffer = create_aligned_buffer(size);
+            let mut map = init_map(&mut buffer, MapType::Small, AllocPolicy::Random { seed });
+            (0..32).map(|_| map.alloc().unwrap()).collect::<Vec<_>>()
+        };
+        let placed = run(7);
+        assert_eq!(placed, run(7));
+        assert_ne!(placed, run(8));
+
+        // Every slot is still handed out exactly once
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Tiny.required_size());
+        let mut map = init_map(&mut buffer, MapType::Tiny, AllocPolicy::Random { seed: 1 });
+        let mut placed: Vec<_> = (0..64).map(|_| map.alloc().unwrap()).collect();
+        placed.sort_unstable();
+        assert!(placed.into_iter().eq(0..64));
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert_eq!(map.alloc_policy().unwrap(), AllocPolicy::FirstFit);
+        assert!(map.set_alloc_policy(AllocPolicy::LastFit).is_err());
+    }
+
+    #[test]
+    fn test_last_fit_skips_reserved_range() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Small, AllocPolicy::LastFit);
+        map.reserve_range(4_000, 4_096).unwrap();
+        assert_eq!(map.alloc().unwrap(), 3_999);
+        assert_eq!(map.alloc().unwrap(), 3_998);
+
+        // Crossing a leaf word and a range in the middle of the map
+        map.reserve_range(3_900, 3_998).unwrap();
+        assert_eq!(map.alloc().unwrap(), 4_095);
+        for index in 4_000..4_095 {
+            map.alloc_at(index).unwrap();
+        }
+        assert_eq!(map.alloc().unwrap(), 3_899);
+
+        map.reserve_range(0, 4_096).unwrap();
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+    }
+
+    #[test]
+    fn test_round_robin_skips_reserved_range() {
+        let size = HEADER_SIZE + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Standard, AllocPolicy::RoundRobin);
+        map.reserve_range(0, 4_100).unwrap();
+        let placed: Vec<_> = (0..5).map(|_| map.alloc().unwrap()).collect();
+        assert_eq!(placed, [4_100, 8_192, 12_288, 4_101, 8_193]);
+
+        // Wraps around to the slots before the range
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Standard, AllocPolicy::RoundRobin);
+        map.reserve_range(4_096, 16_384).unwrap();
+        map.alloc().unwrap();
+        assert_eq!(map.alloc().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_random_skips_reserved_range() {
+        let size = HEADER_SIZE + MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Tiny, AllocPolicy::Random { seed: 1 });
+        map.reserve_range(0, 32).unwrap();
+        let placed: Vec<_> = (0..32).map(|_| map.alloc().unwrap()).collect();
+        let mut sorted = placed.clone();
+        sorted.sort_unstable();
+        assert!(sorted.into_iter().eq(32..64));
+        // Still scattered rather than handed out lowest first
+        assert!(!placed.into_iter().eq(32..64));
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+    }
+
+    #[test]
+    fn test_rotating_leaf_skips_reserved_range() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = init_map(&mut buffer, MapType::Small, AllocPolicy::RotatingLeaf);
+        map.reserve_range(64, 192).unwrap();
+        assert_eq!(map.alloc().unwrap(), 192);
+        // The cursor follows the slot handed out, not the reserved candidate
+        assert_eq!(map.header().unwrap().policy_state, 3);
+        assert_eq!(map.alloc().unwrap(), 256);
+
+        // Past the last leaf word the search wraps to the slots before the range
+        map.reserve_range(64, 4_096).unwrap();
+        assert_eq!(map.alloc().unwrap(), 0);
+        assert_eq!(map.header().unwrap().policy_state, 0);
+        assert_eq!(map.alloc().unwrap(), 1);
+    }
+}
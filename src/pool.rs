@@ -0,0 +1,195 @@
+use crate::{DataPlacement, MapType, MemoryMap, MemoryMapError, SlotLayout};
+use core::{
+    marker::PhantomData,
+    mem::{align_of, size_of},
+};
+
+/// Plain old data that can be read back from any account bytes
+///
+/// # Safety
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid value and
+/// the type must have no padding: no `bool`, `char`, enums, references or
+/// pointers, only integers and `#[repr(C)]` structs and arrays of them.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        // Safety: integers are valid for every bit pattern and have no padding
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// Safety: an array of plain old data has no padding between its elements
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Typed object pool: a map and a data region of `T` slots in one account
+///
+/// The bitmap sits at the given offset and slot `i` of the data region right
+/// after it holds the value inserted at index `i`. `T` is stored as raw bytes
+/// and read back in place, so it must be [`Pod`], like the records a program
+/// keeps in an account.
+pub struct Pool<'a, T: Pod> {
+    map: MemoryMap,
+    slots: &'a mut [u8],
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T: Pod> Pool<'a, T> {
+    /// Layout of a pool of `map_type` starting at `offset`
+    pub fn layout(map_type: MapType, offset: usize) -> Result<SlotLayout, MemoryMapError> {
+        SlotLayout::builder(map_type, size_of::<T>())
+            .base_offset(offset)
+            .placement(DataPlacement::AfterBitmap)
+            .item_align(align_of::<T>())
+            .build()
+    }
+
+    /// Attach a pool of `map_type` at `data[offset..]`
+    ///
+    /// A zeroed region gives an empty pool, the same region reopens an
+    /// existing one.
+    pub fn new(
+        data: &'a mut [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        let layout = Self::layout(map_type, offset)?;
+        layout.validate(data.len())?;
+
+        let (head, tail) = data.split_at_mut(layout.data_offset());
+        let map = MemoryMap::new_from_slice(head, layout.bitmap_offset(), map_type)?;
+        let slots = &mut tail[..layout.slots() * size_of::<T>()];
+        if !(slots.as_ptr() as usize).is_multiple_of(align_of::<T>()) {
+            return Err(MemoryMapError::AlignmentError);
+        }
+
+        Ok(Self {
+            map,
+            slots,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Store `value` in a free slot and return its index
+    pub fn insert(&mut self, value: T) -> Result<usize, MemoryMapError> {
+        let index = self.map.alloc()?;
+        // Safety: `index` is below the capacity and slots are aligned for `T`
+        unsafe { self.slot_ptr(index).write(value) };
+        Ok(index)
+    }
+
+    /// Value stored at `index`
+    pub fn get(&self, index: usize) -> Result<&T, MemoryMapError> {
+        self.check_allocated(index)?;
+        // Safety: allocated slots hold a `T`, aligned and within the region
+        Ok(unsafe { &*(self.slots.as_ptr().add(index * size_of::<T>()) as *const T) })
+    }
+
+    /// Mutable access to the value stored at `index`
+    pub fn get_mut(&mut self, index: usize) -> Result<&mut T, MemoryMapError> {
+        self.check_allocated(index)?;
+        // Safety: allocated slots hold a `T`, aligned and within the region
+        Ok(unsafe { &mut *self.slot_ptr(index) })
+    }
+
+    /// Take the value out of `index` and free the slot
+    pub fn remove(&mut self, index: usize) -> Result<T, MemoryMapError> {
+        let value = *self.get(index)?;
+        self.map.dealloc(index)?;
+        Ok(value)
+    }
+
+    /// Map tracking the occupied slots
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    fn check_allocated(&self, index: usize) -> Result<(), MemoryMapError> {
+        if self.map.is_allocated(index)? {
+            Ok(())
+        } else {
+            Err(MemoryMapError::InvalidIndex)
+        }
+    }
+
+    fn slot_ptr(&mut self, index: usize) -> *mut T {
+        self.slots[index * size_of::<T>()..].as_mut_ptr() as *mut T
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(C)]
+    struct Order {
+        price: u64,
+        quantity: u32,
+        side: u32,
+    }
+
+    // Safety: integer fields only, laid out without padding
+    unsafe impl Pod for Order {}
+
+    #[test]
+    fn test_pool_roundtrip() {
+        let layout = Pool::<Order>::layout(MapType::Tiny, 8).unwrap();
+        assert_eq!(layout.item_size(), 16);
+        assert_eq!(layout.end_offset(), 8 + 8 + 64 * 16);
+
+        let mut buffer = create_aligned_buffer(layout.end_offset());
+        let mut pool = Pool::<Order>::new(&mut buffer, 8, MapType::Tiny).unwrap();
+        let order = Order {
+            price: 100,
+            quantity: 5,
+            side: 1,
+        };
+        assert_eq!(pool.insert(order).unwrap(), 0);
+        assert_eq!(pool.insert(Order { price: 7, ..order }).unwrap(), 1);
+        pool.get_mut(0).unwrap().quantity = 3;
+        assert_eq!(pool.get(0).unwrap().quantity, 3);
+
+        assert_eq!(pool.remove(1).unwrap().price, 7);
+        assert!(matches!(pool.get(1), Err(MemoryMapError::InvalidIndex)));
+        assert!(matches!(pool.remove(1), Err(MemoryMapError::InvalidIndex)));
+        assert!(matches!(pool.get(64), Err(MemoryMapError::InvalidIndex)));
+
+        // Values live in the account bytes and survive a reopen
+        let pool = Pool::<Order>::new(&mut buffer, 8, MapType::Tiny).unwrap();
+        assert_eq!(
+            *pool.get(0).unwrap(),
+            Order {
+                quantity: 3,
+                ..order
+            }
+        );
+        assert_eq!(pool.map().used_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_pool_bounds() {
+        let mut buffer = create_aligned_buffer(100);
+        assert!(matches!(
+            Pool::<u64>::new(&mut buffer, 0, MapType::Tiny),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        let size = Pool::<u128>::layout(MapType::Tiny, 0).unwrap().end_offset();
+        let mut buffer = create_aligned_buffer(size + 8);
+        // The data region is padded to a 16-byte offset either way, whether
+        // it is usable depends on the alignment of the buffer itself
+        let shifted = Pool::<u128>::layout(MapType::Tiny, 8).unwrap();
+        assert_eq!(shifted.data_offset(), 16);
+        assert_eq!(shifted.end_offset(), size);
+        let aligned = Pool::<u128>::new(&mut buffer, 0, MapType::Tiny).is_ok();
+        assert_eq!(
+            Pool::<u128>::new(&mut buffer, 8, MapType::Tiny).is_ok(),
+            aligned
+        );
+        assert_eq!(aligned, (buffer.as_ptr() as usize).is_multiple_of(16));
+    }
+}
@@ -0,0 +1,99 @@
+//! Kani harnesses for the bit twiddling behind alloc and dealloc
+//!
+//! Run with `cargo kani`; the module only exists under `cfg(kani)`.
+
+use crate::{
+    get_first_zero_bit::{get_first_zero_bit, get_last_zero_bit},
+    HierMemoryMap,
+};
+
+/// `get_first_zero_bit` and `get_last_zero_bit` pick the lowest and highest
+/// zero among the low `bits` bits, and fail only if there is none
+fn check_zero_bits(pattern: u64, bits: usize) {
+    let mask = if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    let is_zero = |bit: usize| pattern & (1 << bit) == 0;
+    match get_first_zero_bit(pattern, bits) {
+        Ok(bit) => {
+            assert!(bit < bits && is_zero(bit));
+            assert!((pattern | !(u64::MAX << bit)) == pattern);
+        }
+        Err(_) => assert!(pattern & mask == mask),
+    }
+    match get_last_zero_bit(pattern, bits) {
+        Ok(bit) => {
+            assert!(bit < bits && is_zero(bit));
+            assert!((!pattern & mask) >> bit == 1);
+        }
+        Err(_) => assert!(pattern & mask == mask),
+    }
+}
+
+/// Walking from `index` up the levels, as dealloc does, and back down, as
+/// alloc does, lands on the same index through in-range words
+fn check_index_encoding<const L1_BITS: usize, const LEVELS: usize>(index: usize) {
+    type Map<const L1: usize, const L: usize> = HierMemoryMap<L1, L>;
+    if index >= Map::<L1_BITS, LEVELS>::CAPACITY {
+        return;
+    }
+
+    let mut words = [0usize; 4];
+    let mut bits = [0usize; 4];
+    let mut position = index;
+    for level in (0..LEVELS).rev() {
+        words[level] = Map::<L1_BITS, LEVELS>::level_start(level) + (position >> 6);
+        bits[level] = position & 0x3f;
+        assert!(words[level] < Map::<L1_BITS, LEVELS>::level_start(level + 1));
+        position >>= 6;
+    }
+    assert!(position == 0 && bits[0] < L1_BITS);
+
+    let mut decoded = 0;
+    for level in 0..LEVELS {
+        assert!(words[level] == Map::<L1_BITS, LEVELS>::level_start(level) + decoded);
+        decoded = (decoded << 6) + bits[level];
+    }
+    assert!(decoded == index);
+}
+
+/// Every in-range leaf word and bit decodes to an index below the capacity
+fn check_leaf_decoding<const L1_BITS: usize, const LEVELS: usize>(block: usize, bit: usize) {
+    type Map<const L1: usize, const L: usize> = HierMemoryMap<L1, L>;
+    let leaf_words = Map::<L1_BITS, LEVELS>::level_start(LEVELS)
+        - Map::<L1_BITS, LEVELS>::level_start(LEVELS - 1);
+    let bits = if LEVELS == 1 { L1_BITS } else { 64 };
+    if block >= leaf_words || bit >= bits {
+        return;
+    }
+    assert!((block << 6) + bit < Map::<L1_BITS, LEVELS>::CAPACITY);
+}
+
+#[kani::proof]
+fn proof_zero_bits() {
+    let bits: usize = kani::any();
+    kani::assume((1..=64).contains(&bits));
+    check_zero_bits(kani::any(), bits);
+}
+
+macro_rules! encoding_proofs {
+    ($($name:ident: $l1:literal, $levels:literal;)*) => {$(
+        #[kani::proof]
+        #[kani::unwind(5)]
+        fn $name() {
+            check_index_encoding::<$l1, $levels>(kani::any());
+            check_leaf_decoding::<$l1, $levels>(kani::any(), kani::any());
+        }
+    )*};
+}
+
+encoding_proofs! {
+    proof_tiny_encoding: 64, 1;
+    proof_small_encoding: 64, 2;
+    proof_medium_encoding: 16, 3;
+    proof_standard_encoding: 4, 3;
+    proof_max_encoding: 64, 3;
+    proof_huge_encoding: 64, 4;
+}
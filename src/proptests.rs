@@ -0,0 +1,117 @@
+//! Randomized operation sequences checked against the core invariants
+
+use crate::{create_aligned_buffer, HierMemoryMap, MemoryMapError};
+use proptest::prelude::*;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Alloc,
+    Dealloc(usize),
+}
+
+/// Mostly allocs, with deallocs of indices below `range`
+fn ops(range: usize) -> impl Strategy<Value = Vec<Op>> {
+    prop::collection::vec(
+        prop_oneof![3 => Just(Op::Alloc), 2 => (0..range).prop_map(Op::Dealloc)],
+        0..200,
+    )
+}
+
+/// Every summary bit is set iff the word below it is full
+fn check_summaries<const L1_BITS: usize, const LEVELS: usize>(
+    map: &HierMemoryMap<L1_BITS, LEVELS>,
+) -> Result<(), TestCaseError> {
+    let mut positions = L1_BITS;
+    for level in 0..LEVELS - 1 {
+        let child_start = HierMemoryMap::<L1_BITS, LEVELS>::level_start(level + 1);
+        for position in 0..positions {
+            let full = map.word_at(child_start + position).unwrap() == u64::MAX;
+            prop_assert_eq!(
+                map.summary_bit(level, position).unwrap(),
+                full,
+                "level {} bit {}",
+                level,
+                position
+            );
+        }
+        positions *= 64;
+    }
+    Ok(())
+}
+
+/// Replay `ops` after `prefill` allocs, checking every step against a model
+fn run<const L1_BITS: usize, const LEVELS: usize>(
+    prefill: usize,
+    ops: &[Op],
+) -> Result<(), TestCaseError> {
+    let size = HierMemoryMap::<L1_BITS, LEVELS>::REQUIRED_SIZE;
+    let capacity = HierMemoryMap::<L1_BITS, LEVELS>::CAPACITY;
+    let mut buffer = create_aligned_buffer(size);
+    let mut map = HierMemoryMap::<L1_BITS, LEVELS>::new_from_slice(&mut buffer, 0).unwrap();
+    let mut model = BTreeSet::new();
+    for index in 0..prefill {
+        prop_assert_eq!(map.alloc().unwrap(), index);
+        model.insert(index);
+    }
+
+    for op in ops {
+        match *op {
+            Op::Alloc => match (0..capacity).find(|index| !model.contains(index)) {
+                Some(lowest) => {
+                    prop_assert_eq!(map.alloc().unwrap(), lowest);
+                    model.insert(lowest);
+                }
+                None => prop_assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots))),
+            },
+            Op::Dealloc(index) => {
+                map.dealloc(index).unwrap();
+                model.remove(&index);
+            }
+        }
+        prop_assert_eq!(map.used_count().unwrap(), model.len());
+        check_summaries(&map)?;
+    }
+
+    // Freeing any allocated slot and allocating again hands it back
+    if let Some(&index) = model.iter().next_back() {
+        let lowest = (0..).find(|index| !model.contains(index)).unwrap();
+        map.dealloc(index).unwrap();
+        prop_assert!(!map.is_allocated(index).unwrap());
+        prop_assert_eq!(map.alloc().unwrap(), index.min(lowest));
+    }
+    Ok(())
+}
+
+proptest! {
+    // Every step checks the whole hierarchy, keep debug test runs quick
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn prop_small(prefill in 0..4_096usize, ops in ops(4_096)) {
+        run::<64, 2>(prefill, &ops)?;
+    }
+
+    #[test]
+    fn prop_standard(prefill in 0..2_048usize, ops in ops(2_600)) {
+        run::<4, 3>(prefill, &ops)?;
+    }
+
+    #[test]
+    fn prop_max(prefill in 0..4_096usize, ops in ops(4_500)) {
+        run::<64, 3>(prefill, &ops)?;
+    }
+
+    #[test]
+    fn prop_full_map_rejects_alloc(free in 0..4_096usize) {
+        let mut buffer = create_aligned_buffer(HierMemoryMap::<64, 2>::REQUIRED_SIZE);
+        let mut map = HierMemoryMap::<64, 2>::new_from_slice(&mut buffer, 0).unwrap();
+        while map.alloc().is_ok() {}
+        prop_assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(free).unwrap();
+        check_summaries(&map)?;
+        prop_assert_eq!(map.alloc().unwrap(), free);
+        prop_assert!(map.is_full());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    bytes::{read_u32, write_u32},
+    MemoryMap, MemoryMapError,
+};
+
+/// Owner entry: tag, in-use marker, limit and slots held, all u32
+const ENTRY_SIZE: usize = 16;
+
+/// Slot limit of one owner and the slots it currently holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u32,
+    pub used: u32,
+}
+
+/// Per-owner slot limits, e.g. so one market maker cannot take every seat
+///
+/// The region holds `owners` entries keyed by a caller-chosen `u32` tag, so
+/// its size depends only on the number of owners and it works with every map
+/// type. Slots are not tagged: whoever frees a slot names its owner, which the
+/// program already knows from the record stored at the index. Owners without
+/// an entry are not limited. An all-zero region has no quotas.
+pub struct Quotas<'a> {
+    data: &'a mut [u8],
+    owners: usize,
+}
+
+impl<'a> Quotas<'a> {
+    /// Number of bytes needed for `owners` entries
+    pub const fn size(owners: usize) -> usize {
+        owners * ENTRY_SIZE
+    }
+
+    /// Wrap a region previously zeroed or written by this extension
+    ///
+    /// `owners` must match the value the region was created with. Lookups scan
+    /// the entries, so keep it to the owners that actually need a limit.
+    pub fn new(data: &'a mut [u8], owners: usize) -> Result<Self, MemoryMapError> {
+        let size = owners
+            .checked_mul(ENTRY_SIZE)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        if data.len() < size {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data, owners })
+    }
+
+    /// Limit `owner` to `limit` slots
+    ///
+    /// Lowering a limit below the slots held frees nothing, the owner just
+    /// cannot allocate until it is under the limit again. Fails with
+    /// `InsufficientMemory` if every entry is taken by other owners.
+    pub fn set_quota(&mut self, owner: u32, limit: u32) -> Result<(), MemoryMapError> {
+        let entry = match self.entry_of(owner) {
+            Some(entry) => entry,
+            None => {
+                let entry = (0..self.owners)
+                    .find(|&entry| read_u32(self.data, entry * ENTRY_SIZE + 4) == 0)
+                    .ok_or(MemoryMapError::InsufficientMemory)?;
+                let at = entry * ENTRY_SIZE;
+                write_u32(self.data, at, owner);
+                write_u32(self.data, at + 4, 1);
+                write_u32(self.data, at + 12, 0);
+                entry
+            }
+        };
+        write_u32(self.data, entry * ENTRY_SIZE + 8, limit);
+        Ok(())
+    }
+
+    /// Limit and usage of `owner`, `None` if it is not limited
+    pub fn quota(&self, owner: u32) -> Option<Quota> {
+        self.entry_of(owner).map(|entry| self.entry(entry))
+    }
+
+    fn entry_of(&self, owner: u32) -> Option<usize> {
+        (0..self.owners).find(|&entry| {
+            let at = entry * ENTRY_SIZE;
+            read_u32(self.data, at + 4) != 0 && read_u32(self.data, at) == owner
+        })
+    }
+
+    fn entry(&self, entry: usize) -> Quota {
+        let at = entry * ENTRY_SIZE;
+        Quota {
+            limit: read_u32(self.data, at + 8),
+            used: read_u32(self.data, at + 12),
+        }
+    }
+
+    fn set_used(&mut self, entry: usize, used: u32) {
+        write_u32(self.data, entry * ENTRY_SIZE + 12, used);
+    }
+}
+
+impl MemoryMap {
+    /// Allocate a slot on behalf of `owner`
+    ///
+    /// Fails with `QuotaExceeded` if the owner already holds as many slots as
+    /// its quota allows; owners without a quota are not limited.
+    pub fn alloc_for(&mut self, quotas: &mut Quotas, owner: u32) -> Result<usize, MemoryMapError> {
+        let Some(entry) = quotas.entry_of(owner) else {
+            return self.alloc();
+        };
+        let quota = quotas.entry(entry);
+        if quota.used >= quota.limit {
+            return Err(MemoryMapError::QuotaExceeded);
+        }
+        let index = self.alloc()?;
+        quotas.set_used(entry, quota.used + 1);
+        Ok(index)
+    }
+
+    /// Deallocate slot `index`, crediting `owner`, which it was allocated for
+    ///
+    /// Fails with `InvalidIndex` if the slot is free and with `OwnerMismatch`
+    /// if a limited owner holds no slots, leaving the map untouched.
+    pub fn dealloc_for(
+        &mut self,
+        quotas: &mut Quotas,
+        owner: u32,
+        index: usize,
+    ) -> Result<(), MemoryMapError> {
+        if !self.is_allocated(index)? {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let entry = quotas.entry_of(owner);
+        let used = entry.map(|entry| quotas.entry(entry).used);
+        if used == Some(0) {
+            return Err(MemoryMapError::OwnerMismatch);
+        }
+        self.dealloc(index)?;
+        if let (Some(entry), Some(used)) = (entry, used) {
+            quotas.set_used(entry, used - 1);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_owner_limited() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; Quotas::size(4)];
+        let mut quotas = Quotas::new(&mut region, 4).unwrap();
+        quotas.set_quota(7, 2).unwrap();
+
+        assert_eq!(map.alloc_for(&mut quotas, 7).unwrap(), 0);
+        assert_eq!(map.alloc_for(&mut quotas, 7).unwrap(), 1);
+        assert!(matches!(
+            map.alloc_for(&mut quotas, 7),
+            Err(MemoryMapError::QuotaExceeded)
+        ));
+        // Unlimited owners still get slots
+        assert_eq!(map.alloc_for(&mut quotas, 8).unwrap(), 2);
+        assert_eq!(quotas.quota(7), Some(Quota { limit: 2, used: 2 }));
+        assert_eq!(quotas.quota(8), None);
+
+        map.dealloc_for(&mut quotas, 7, 0).unwrap();
+        map.dealloc_for(&mut quotas, 8, 2).unwrap();
+        assert_eq!(quotas.quota(7).unwrap().used, 1);
+        assert!(matches!(
+            map.dealloc_for(&mut quotas, 7, 0),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert_eq!(map.alloc_for(&mut quotas, 7).unwrap(), 0);
+
+        // The table reopens with the same usage
+        let quotas = Quotas::new(&mut region, 4).unwrap();
+        assert_eq!(quotas.quota(7), Some(Quota { limit: 2, used: 2 }));
+    }
+
+    #[test]
+    fn test_table_limits() {
+        let mut region = vec![0u8; Quotas::size(2)];
+        let mut quotas = Quotas::new(&mut region, 2).unwrap();
+        quotas.set_quota(1, 5).unwrap();
+        quotas.set_quota(2, 5).unwrap();
+        quotas.set_quota(1, 0).unwrap();
+        assert!(matches!(
+            quotas.set_quota(3, 5),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        // The table does not grow with the map
+        let mut buffer = create_aligned_buffer(MapType::Max.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        assert!(matches!(
+            map.alloc_for(&mut quotas, 1),
+            Err(MemoryMapError::QuotaExceeded)
+        ));
+        assert_eq!(map.alloc_for(&mut quotas, 3).unwrap(), 0);
+        assert!(matches!(
+            map.dealloc_for(&mut quotas, 2, 0),
+            Err(MemoryMapError::OwnerMismatch)
+        ));
+        assert!(map.is_allocated(0).unwrap());
+        assert!(matches!(
+            Quotas::new(&mut region[..10], 2),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
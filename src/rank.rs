@@ -0,0 +1,154 @@
+use crate::{backend::Backend, hier_memory_map::HierMemoryMap, MemoryMap, MemoryMapError};
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Number of allocated slots at or below `index`
+    pub fn rank(&self, index: usize) -> Result<usize, MemoryMapError> {
+        if index >= Self::CAPACITY {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+
+        // Subtrees left of the path to `index`, at every level above the leaves
+        let mut rank = 0;
+        for level in 0..LEVELS - 1 {
+            let position = index >> (6 * (LEVELS - 1 - level));
+            for sibling in position & !0x3f..position {
+                rank += self.subtree_used(level, sibling)?;
+            }
+        }
+
+        let below = u64::MAX >> (63 - (index & 0x3f));
+        let leaf = self.leaf_word(index >> 6)? & Self::LEAF_MASK & below;
+        Ok(rank + leaf.count_ones() as usize)
+    }
+
+    /// Index of the allocated slot with `k` allocated slots before it
+    ///
+    /// Inverse of [`rank`](Self::rank): `rank(select(k)?)? == k + 1`.
+    pub fn select(&self, mut k: usize) -> Result<usize, MemoryMapError> {
+        let mut position = 0;
+        for level in 0..LEVELS - 1 {
+            let children = if level == 0 { L1_BITS } else { 64 };
+            let first = position << 6;
+            position = 'children: {
+                for child in first..first + children {
+                    let used = self.subtree_used(level, child)?;
+                    if k < used {
+                        break 'children child;
+                    }
+                    k -= used;
+                }
+                return Err(MemoryMapError::InvalidIndex);
+            };
+        }
+
+        let mut leaf = self.leaf_word(position)? & Self::LEAF_MASK;
+        if k >= leaf.count_ones() as usize {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        for _ in 0..k {
+            leaf &= leaf - 1;
+        }
+        Ok((position << 6) + leaf.trailing_zeros() as usize)
+    }
+
+    /// Allocated slots under bit `position` of summary level `level`
+    ///
+    /// Full subtrees are counted from their summary bit, the others from the
+    /// leaf popcounts below them.
+    fn subtree_used(&self, level: usize, position: usize) -> Result<usize, MemoryMapError> {
+        if self.summary_bit(level, position)? {
+            return Ok(1 << (6 * (LEVELS - 1 - level)));
+        }
+        // Bits of the last summary level stand for whole leaf words
+        if level == LEVELS - 2 {
+            return Ok((self.leaf_word(position)? & Self::LEAF_MASK).count_ones() as usize);
+        }
+        (position << 6..(position + 1) << 6).try_fold(0, |used, child| {
+            Ok(used + self.subtree_used(level + 1, child)?)
+        })
+    }
+}
+
+impl MemoryMap {
+    /// Number of allocated slots at or below `index`
+    ///
+    /// Together with [`MemoryMap::select`] this keys a dense companion array
+    /// by rank instead of by index.
+    pub fn rank(&self, index: usize) -> Result<usize, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.rank(index),
+            Self::Standard(map) => map.rank(index),
+            Self::Small(map) => map.rank(index),
+            Self::Huge(map) => map.rank(index),
+            Self::Tiny(map) => map.rank(index),
+            Self::Medium(map) => map.rank(index),
+        }
+    }
+
+    /// Index of the allocated slot with `k` allocated slots before it
+    ///
+    /// Fails with `InvalidIndex` when fewer than `k + 1` slots are allocated.
+    pub fn select(&self, k: usize) -> Result<usize, MemoryMapError> {
+        match self {
+            Self::Max(map) => map.select(k),
+            Self::Standard(map) => map.select(k),
+            Self::Small(map) => map.select(k),
+            Self::Huge(map) => map.select(k),
+            Self::Tiny(map) => map.select(k),
+            Self::Medium(map) => map.select(k),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_rank_select_roundtrip() {
+        for map_type in [MapType::Tiny, MapType::Small, MapType::Standard] {
+            let mut buffer = create_aligned_buffer(map_type.required_size());
+            let mut map = MemoryMap::new_from_slice(&mut buffer, 0, map_type).unwrap();
+            // A full block, then a sparse pattern
+            map.alloc_block().unwrap();
+            for block in [1, 63, 64, 140] {
+                if block < map_type.capacity() / 64 {
+                    map.alloc_near(block).unwrap();
+                }
+            }
+            let allocated: Vec<usize> = (0..map_type.capacity())
+                .filter(|&index| map.is_allocated(index).unwrap())
+                .collect();
+
+            for (k, &index) in allocated.iter().enumerate() {
+                assert_eq!(map.select(k).unwrap(), index);
+                assert_eq!(map.rank(index).unwrap(), k + 1);
+            }
+            assert!(matches!(
+                map.select(allocated.len()),
+                Err(MemoryMapError::InvalidIndex)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rank_counts_full_subtrees() {
+        let mut buffer = create_aligned_buffer(MapType::Max.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        for _ in 0..5_000 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(4_200).unwrap();
+
+        assert_eq!(map.rank(0).unwrap(), 1);
+        assert_eq!(map.rank(4_095).unwrap(), 4_096);
+        assert_eq!(map.rank(4_200).unwrap(), 4_200);
+        assert_eq!(map.rank(200_000).unwrap(), 4_999);
+        assert_eq!(map.select(4_200).unwrap(), 4_201);
+        assert!(matches!(
+            map.rank(MapType::Max.capacity()),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    bytes::{read_u32, read_u64, write_u32, write_u64},
+    MapType, MemoryMap, MemoryMapError,
+};
+
+/// Magic value marking an initialized region registry ("IREG")
+pub const REGISTRY_MAGIC: u32 = u32::from_le_bytes(*b"IREG");
+
+/// Map region recorded in a [`RegionManager`] registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// Caller-chosen purpose tag, unique within the registry
+    pub tag: u32,
+    pub offset: usize,
+    pub map_type: MapType,
+}
+
+impl Region {
+    /// First byte past the region's bitmap
+    pub const fn end_offset(&self) -> usize {
+        self.offset + self.map_type.required_size()
+    }
+}
+
+/// Registry of several maps sharing one account, e.g. bids, asks and open
+/// orders of a market
+///
+/// The registry sits at offset 0: an 8-byte header (magic and region count)
+/// followed by [`RegionManager::MAX_REGIONS`] entries of 16 bytes (offset,
+/// tag, map type). Regions never overlap each other or the registry.
+pub struct RegionManager<'a> {
+    data: &'a mut [u8],
+}
+
+impl<'a> RegionManager<'a> {
+    /// Most regions a registry can hold
+    pub const MAX_REGIONS: usize = 16;
+
+    const ENTRY_SIZE: usize = 16;
+
+    /// Bytes taken by the registry at the start of the account
+    pub const REGISTRY_SIZE: usize = 8 + Self::MAX_REGIONS * Self::ENTRY_SIZE;
+
+    /// Attach the registry at the start of `data`
+    ///
+    /// A zeroed registry is initialized empty. An existing one is checked: every
+    /// region must be known, fit in `data` and not overlap another.
+    pub fn new(data: &'a mut [u8]) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::REGISTRY_SIZE {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        match read_u32(data, 0) {
+            0 => write_u32(data, 0, REGISTRY_MAGIC),
+            REGISTRY_MAGIC => {}
+            _ => return Err(MemoryMapError::InvalidHeader),
+        }
+        if read_u32(data, 4) as usize > Self::MAX_REGIONS {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+
+        let manager = Self { data };
+        for entry in 0..manager.len() {
+            let region = manager.entry(entry)?;
+            if region.end_offset() > manager.data.len() {
+                return Err(MemoryMapError::InsufficientMemory);
+            }
+            manager.check_disjoint(&region, entry)?;
+        }
+
+        Ok(manager)
+    }
+
+    /// Number of registered regions
+    pub fn len(&self) -> usize {
+        read_u32(self.data, 4) as usize
+    }
+
+    /// Check whether no region is registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Registered regions in registration order
+    pub fn regions(&self) -> impl Iterator<Item = Region> + '_ {
+        (0..self.len()).filter_map(|entry| self.entry(entry).ok())
+    }
+
+    /// Region registered under `tag`
+    pub fn region(&self, tag: u32) -> Option<Region> {
+        self.regions().find(|region| region.tag == tag)
+    }
+
+    /// Register a `map_type` region under `tag` right after the last region,
+    /// returning its offset
+    pub fn add_region(&mut self, tag: u32, map_type: MapType) -> Result<usize, MemoryMapError> {
+        let end = self
+            .regions()
+            .map(|region| region.end_offset())
+            .fold(Self::REGISTRY_SIZE, usize::max);
+        // Bitmaps are read as u64 words
+        let offset = end.next_multiple_of(8);
+        self.add_region_at(tag, offset, map_type)?;
+        Ok(offset)
+    }
+
+    /// Register a `map_type` region under `tag` at `offset`
+    ///
+    /// `offset` must be a multiple of 8. Fails with `RegionOverlap` if the
+    /// region would share bytes with the registry or another region, and
+    /// with `InvalidIndex` if `tag` is taken.
+    pub fn add_region_at(
+        &mut self,
+        tag: u32,
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<(), MemoryMapError> {
+        if self.region(tag).is_some() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        if !offset.is_multiple_of(8) {
+            return Err(MemoryMapError::AlignmentError);
+        }
+        let count = self.len();
+        if count == Self::MAX_REGIONS {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        let region = Region {
+            tag,
+            offset,
+            map_type,
+        };
+        if region.end_offset() > self.data.len() {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        self.check_disjoint(&region, count)?;
+
+        let at = 8 + count * Self::ENTRY_SIZE;
+        write_u64(self.data, at, offset as u64);
+        write_u32(self.data, at + 8, tag);
+        self.data[at + 12] = map_type as u8;
+        write_u32(self.data, 4, count as u32 + 1);
+        Ok(())
+    }
+
+    /// Map view over the region registered under `tag`
+    pub fn map(&mut self, tag: u32) -> Result<MemoryMap, MemoryMapError> {
+        let region = self.region(tag).ok_or(MemoryMapError::InvalidIndex)?;
+        MemoryMap::new_from_slice(self.data, region.offset, region.map_type)
+    }
+
+    fn entry(&self, entry: usize) -> Result<Region, MemoryMapError> {
+        let at = 8 + entry * Self::ENTRY_SIZE;
+        Ok(Region {
+            tag: read_u32(self.data, at + 8),
+            offset: read_u64(self.data, at) as usize,
+            map_type: MapType::try_from(self.data[at + 12])?,
+        })
+    }
+
+    /// Check `region` against the registry and the first `entries` regions
+    fn check_disjoint(&self, region: &Region, entries: usize) -> Result<(), MemoryMapError> {
+        if region.offset < Self::REGISTRY_SIZE {
+            return Err(MemoryMapError::RegionOverlap);
+        }
+        for entry in 0..entries {
+            let other = self.entry(entry)?;
+            if region.offset < other.end_offset() && other.offset < region.end_offset() {
+                return Err(MemoryMapError::RegionOverlap);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    const BIDS: u32 = 1;
+    const ASKS: u32 = 2;
+    const ORDERS: u32 = 3;
+
+    #[test]
+    fn test_regions() {
+        let size = RegionManager::REGISTRY_SIZE
+            + 2 * MapType::Small.required_size()
+            + MapType::Standard.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut manager = RegionManager::new(&mut buffer).unwrap();
+
+        let bids = manager.add_region(BIDS, MapType::Small).unwrap();
+        let asks = manager.add_region(ASKS, MapType::Small).unwrap();
+        manager.add_region(ORDERS, MapType::Standard).unwrap();
+        assert_eq!(bids, RegionManager::REGISTRY_SIZE);
+        assert_eq!(asks, bids + MapType::Small.required_size());
+        assert!(matches!(
+            manager.add_region(4, MapType::Small),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        assert!(matches!(
+            manager.add_region(BIDS, MapType::Tiny),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        let mut bid_map = manager.map(BIDS).unwrap();
+        let mut ask_map = manager.map(ASKS).unwrap();
+        bid_map.alloc().unwrap();
+        bid_map.alloc().unwrap();
+        ask_map.alloc().unwrap();
+        assert!(matches!(manager.map(9), Err(MemoryMapError::InvalidIndex)));
+
+        // Reopened from the same bytes
+        let mut manager = RegionManager::new(&mut buffer).unwrap();
+        assert_eq!(manager.len(), 3);
+        assert_eq!(
+            manager.region(ASKS),
+            Some(Region {
+                tag: ASKS,
+                offset: asks,
+                map_type: MapType::Small
+            })
+        );
+        assert_eq!(manager.map(BIDS).unwrap().used_count().unwrap(), 2);
+        assert_eq!(manager.map(ASKS).unwrap().used_count().unwrap(), 1);
+        assert_eq!(manager.map(ORDERS).unwrap().used_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_overlap_is_rejected() {
+        let mut buffer = create_aligned_buffer(4_096);
+        let mut manager = RegionManager::new(&mut buffer).unwrap();
+        assert!(matches!(
+            manager.add_region_at(BIDS, 8, MapType::Tiny),
+            Err(MemoryMapError::RegionOverlap)
+        ));
+        manager.add_region_at(BIDS, 1_024, MapType::Small).unwrap();
+        assert!(matches!(
+            manager.add_region_at(ASKS, 1_032, MapType::Tiny),
+            Err(MemoryMapError::RegionOverlap)
+        ));
+        assert!(matches!(
+            manager.add_region_at(ASKS, 1_012, MapType::Tiny),
+            Err(MemoryMapError::AlignmentError)
+        ));
+        manager.add_region_at(ASKS, 1_016, MapType::Tiny).unwrap();
+        assert_eq!(manager.len(), 2);
+
+        // A corrupted registry is caught on open
+        buffer[8 + 16..8 + 24].copy_from_slice(&1_032u64.to_le_bytes());
+        assert!(matches!(
+            RegionManager::new(&mut buffer),
+            Err(MemoryMapError::RegionOverlap)
+        ));
+        buffer[0] = 0xff;
+        assert!(matches!(
+            RegionManager::new(&mut buffer),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    backend::Backend, header::FLAG_FROZEN, hier_memory_map::HierMemoryMap, trace::OpSpan,
+    MemoryMap, MemoryMapError,
+};
+use core::ops::Range;
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Slot range recorded in the header that `alloc` never hands out
+    pub(crate) fn reserved_range(&self) -> Option<Range<usize>> {
+        // Safety: the header outlives the map, like the bitmap itself
+        let header = unsafe { self.header()?.as_ref() };
+        (header.reserved_end > header.reserved_start)
+            .then_some(header.reserved_start as usize..header.reserved_end as usize)
+    }
+
+    /// Free slot `index` if it lies outside `reserved`, the lowest free slot
+    /// past the range otherwise
+    pub(crate) fn skip_reserved(
+        &self,
+        index: usize,
+        reserved: &Range<usize>,
+    ) -> Result<usize, MemoryMapError> {
+        if !reserved.contains(&index) {
+            return Ok(index);
+        }
+        if reserved.end >= Self::CAPACITY {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        self.find_free_above(reserved.end)?
+            .ok_or(MemoryMapError::NoAvailableSlots)
+    }
+
+    /// Bits of leaf word `block` that lie in `reserved`
+    pub(crate) fn reserved_bits(block: usize, reserved: Option<&Range<usize>>) -> u64 {
+        let Some(reserved) = reserved else {
+            return 0;
+        };
+        let start = reserved.start.clamp(block << 6, (block + 1) << 6) - (block << 6);
+        let end = reserved.end.clamp(block << 6, (block + 1) << 6) - (block << 6);
+        if start == end {
+            return 0;
+        }
+        (u64::MAX >> (64 - (end - start))) << start
+    }
+
+    /// Allocate the specific slot `index`, reserved or not
+    pub fn alloc_at(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        self.check_frozen(FLAG_FROZEN)?;
+        if self.is_allocated(index)? {
+            return Err(MemoryMapError::SlotTaken);
+        }
+        self.mark_allocated(index)
+    }
+}
+
+impl MemoryMap {
+    /// Keep `alloc` from handing out the slots `start..end`
+    ///
+    /// Meant for ranges owned by the protocol, e.g. seats `0..64`, which an
+    /// admin instruction claims with [`MemoryMap::alloc_at`]. `alloc`,
+    /// `alloc_near`, `alloc_above`, `alloc_twin`, `alloc_block`,
+    /// [`BuddyMemoryMap::alloc`] and the [`OrderSideMemoryMap`] sides skip
+    /// the range; `alloc_block` skips every leaf word it touches. The range
+    /// is persisted in the header, so only maps created with
+    /// [`MemoryMap::init`] or [`MemoryMap::load`] support it. An empty range
+    /// lifts the reservation.
+    ///
+    /// [`BuddyMemoryMap::alloc`]: crate::BuddyMemoryMap::alloc
+    /// [`OrderSideMemoryMap`]: crate::OrderSideMemoryMap
+    pub fn reserve_range(&mut self, start: usize, end: usize) -> Result<(), MemoryMapError> {
+        if start > end || end > self.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let header = self.header_mut().ok_or(MemoryMapError::InvalidHeader)?;
+        header.reserved_start = start as u64;
+        header.reserved_end = end as u64;
+        Ok(())
+    }
+
+    /// Slot range set by [`MemoryMap::reserve_range`], if any
+    pub fn reserved_range(&self) -> Option<Range<usize>> {
+        match self {
+            Self::Max(map) => map.reserved_range(),
+            Self::Standard(map) => map.reserved_range(),
+            Self::Small(map) => map.reserved_range(),
+            Self::Huge(map) => map.reserved_range(),
+            Self::Tiny(map) => map.reserved_range(),
+            Self::Medium(map) => map.reserved_range(),
+        }
+    }
+
+    /// Allocate the specific slot `index`, including reserved ones
+    ///
+    /// Fails with `SlotTaken` if the slot is already allocated.
+    pub fn alloc_at(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        let span = OpSpan::enter("alloc_at", self.map_type());
+        span.record_index(index);
+        let result = match self {
+            Self::Max(map) => map.alloc_at(index),
+            Self::Standard(map) => map.alloc_at(index),
+            Self::Small(map) => map.alloc_at(index),
+            Self::Huge(map) => map.alloc_at(index),
+            Self::Tiny(map) => map.alloc_at(index),
+            Self::Medium(map) => map.alloc_at(index),
+        };
+        span.finish(&result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, BuddyMemoryMap, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_alloc_skips_reserved_range() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        map.reserve_range(0, 64).unwrap();
+        assert_eq!(map.alloc().unwrap(), 64);
+        assert_eq!(map.alloc_near(0).unwrap(), 65);
+        assert_eq!(map.alloc_above(10).unwrap(), 66);
+
+        // Admin claims stay possible, but only once
+        map.alloc_at(3).unwrap();
+        assert!(matches!(map.alloc_at(3), Err(MemoryMapError::SlotTaken)));
+        map.dealloc(3).unwrap();
+
+        // The range survives a reload and can be lifted
+        let mut map = MemoryMap::load(&mut buffer, 0, MapType::Small).unwrap();
+        assert_eq!(map.reserved_range(), Some(0..64));
+        assert_eq!(map.alloc().unwrap(), 67);
+        map.reserve_range(0, 0).unwrap();
+        assert_eq!(map.reserved_range(), None);
+        assert_eq!(map.alloc().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_twin_and_block_skip_reserved_range() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        map.reserve_range(0, 64).unwrap();
+        assert_eq!(map.alloc_block().unwrap(), 64);
+        assert_eq!(map.alloc_twin().unwrap(), (128, 129));
+        assert!(!map.is_allocated(0).unwrap());
+
+        // A range ending mid-word leaves the rest of the word to alloc_twin,
+        // but alloc_block needs a word clear of the range
+        map.reserve_range(0, 62).unwrap();
+        assert_eq!(map.alloc_twin().unwrap(), (62, 63));
+        map.dealloc(62).unwrap();
+        map.dealloc(63).unwrap();
+        map.reserve_range(0, 100).unwrap();
+        assert_eq!(map.alloc_block().unwrap(), 192);
+        assert_eq!(map.used_count().unwrap(), 130);
+    }
+
+    #[test]
+    fn test_buddy_skips_reserved_range() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        map.reserve_range(0, 128).unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+        assert_eq!(buddy.alloc(3).unwrap(), 128);
+        assert_eq!(buddy.alloc(6).unwrap(), 192);
+
+        // Runs fit around a range ending mid-word
+        let mut map = buddy.into_inner();
+        map.reserve_range(0, 136).unwrap();
+        let mut buddy = BuddyMemoryMap::new(map);
+        assert_eq!(buddy.alloc(0).unwrap(), 136);
+        assert_eq!(buddy.alloc(4).unwrap(), 144);
+        assert!(!buddy.map().is_allocated(0).unwrap());
+    }
+
+    #[test]
+    fn test_reserved_tail_exhausts_map() {
+        let size = HEADER_SIZE + MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Tiny).unwrap();
+        map.reserve_range(60, 64).unwrap();
+        for expected in 0..60 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+        map.alloc_at(63).unwrap();
+        assert!(matches!(
+            map.reserve_range(0, 65),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        let mut plain = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut plain, 0, MapType::Tiny).unwrap();
+        assert!(matches!(
+            map.reserve_range(0, 8),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+}
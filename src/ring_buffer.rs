@@ -0,0 +1,282 @@
+use crate::{
+    bytes::{read_u16, read_u32, read_u64, write_u16, write_u32, write_u64},
+    header::LayoutVersion,
+    MemoryMapError,
+};
+
+/// Magic value marking an initialized ring buffer header ("IRNG")
+pub const RING_MAGIC: u32 = u32::from_le_bytes(*b"IRNG");
+
+/// Fixed-capacity event queue kept in an account, e.g. fills or order events
+///
+/// Layout from the given offset: a [`SlotRingBuffer::HEADER_SIZE`] header
+/// (magic, layout version, item size, capacity, head and tail sequence
+/// numbers), then `capacity` records of `item_size` bytes. Event `seq` lives
+/// in record `seq % capacity`; the queue holds sequence numbers `head..tail`.
+pub struct SlotRingBuffer<'a> {
+    header: &'a mut [u8],
+    items: &'a mut [u8],
+    item_size: usize,
+    capacity: usize,
+}
+
+impl<'a> SlotRingBuffer<'a> {
+    /// Bytes taken by the header in front of the records
+    pub const HEADER_SIZE: usize = 32;
+
+    /// Number of bytes taken by a buffer of `capacity` records
+    pub const fn required_size(item_size: usize, capacity: usize) -> usize {
+        Self::HEADER_SIZE + item_size * capacity
+    }
+
+    /// Attach a buffer at `data[offset..]`
+    ///
+    /// A zeroed region is initialized empty, a region holding a buffer is
+    /// reopened if it was created with the same item size and capacity.
+    pub fn new(
+        data: &'a mut [u8],
+        offset: usize,
+        item_size: usize,
+        capacity: usize,
+    ) -> Result<Self, MemoryMapError> {
+        if item_size == 0 || capacity == 0 || capacity > u32::MAX as usize {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let end = item_size
+            .checked_mul(capacity)
+            .and_then(|items| items.checked_add(Self::HEADER_SIZE))
+            .and_then(|size| size.checked_add(offset))
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        let region = data
+            .get_mut(offset..end)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        let (header, items) = region.split_at_mut(Self::HEADER_SIZE);
+
+        match read_u32(header, 0) {
+            0 => {
+                write_u32(header, 0, RING_MAGIC);
+                write_u16(header, 4, LayoutVersion::CURRENT as u16);
+                write_u32(header, 8, item_size as u32);
+                write_u32(header, 12, capacity as u32);
+            }
+            RING_MAGIC => {
+                LayoutVersion::try_from(read_u16(header, 4))?;
+                if read_u32(header, 8) as usize != item_size
+                    || read_u32(header, 12) as usize != capacity
+                {
+                    return Err(MemoryMapError::InvalidHeader);
+                }
+                // `len` relies on head <= tail <= head + capacity
+                let (head, tail) = (read_u64(header, 16), read_u64(header, 24));
+                if tail
+                    .checked_sub(head)
+                    .is_none_or(|len| len > capacity as u64)
+                {
+                    return Err(MemoryMapError::InvalidHeader);
+                }
+            }
+            _ => return Err(MemoryMapError::InvalidHeader),
+        }
+
+        Ok(Self {
+            header,
+            items,
+            item_size,
+            capacity,
+        })
+    }
+
+    /// Sequence number of the oldest unconsumed event
+    pub fn head(&self) -> u64 {
+        read_u64(self.header, 16)
+    }
+
+    /// Sequence number the next pushed event gets
+    pub fn tail(&self) -> u64 {
+        read_u64(self.header, 24)
+    }
+
+    /// Number of unconsumed events
+    pub fn len(&self) -> usize {
+        (self.tail() - self.head()) as usize
+    }
+
+    /// Check whether every pushed event has been consumed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check whether `push` fails until the consumer catches up
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity
+    }
+
+    /// Number of records the buffer holds
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Size of every record in bytes
+    pub const fn item_size(&self) -> usize {
+        self.item_size
+    }
+
+    /// Append an event, returning its sequence number
+    ///
+    /// `item` must be exactly `item_size` bytes. Fails with `NoAvailableSlots`
+    /// until the consumer catches up when the buffer is full.
+    pub fn push(&mut self, item: &[u8]) -> Result<u64, MemoryMapError> {
+        if item.len() != self.item_size {
+            return Err(MemoryMapError::LengthMismatch);
+        }
+        if self.is_full() {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        let seq = self.tail();
+        self.record_mut(seq).copy_from_slice(item);
+        write_u64(self.header, 24, seq + 1);
+        Ok(seq)
+    }
+
+    /// Event with sequence number `seq`, if it is still in the buffer
+    pub fn get(&self, seq: u64) -> Option<&[u8]> {
+        (self.head()..self.tail())
+            .contains(&seq)
+            .then(|| self.record(seq))
+    }
+
+    /// Unconsumed events in sequence order
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &[u8])> + '_ {
+        (self.head()..self.tail()).map(|seq| (seq, self.record(seq)))
+    }
+
+    /// Drop every event up to and including `seq`, returning how many were
+    /// dropped
+    ///
+    /// Already consumed sequence numbers are a no-op, sequence numbers not
+    /// pushed yet fail with `InvalidIndex`.
+    pub fn consume_up_to(&mut self, seq: u64) -> Result<usize, MemoryMapError> {
+        let (head, tail) = (self.head(), self.tail());
+        if seq >= tail {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        if seq < head {
+            return Ok(0);
+        }
+        write_u64(self.header, 16, seq + 1);
+        Ok((seq + 1 - head) as usize)
+    }
+
+    fn record(&self, seq: u64) -> &[u8] {
+        let at = (seq % self.capacity as u64) as usize * self.item_size;
+        &self.items[at..at + self.item_size]
+    }
+
+    fn record_mut(&mut self, seq: u64) -> &mut [u8] {
+        let at = (seq % self.capacity as u64) as usize * self.item_size;
+        &mut self.items[at..at + self.item_size]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, MemoryMap};
+
+    #[test]
+    fn test_push_and_consume() {
+        let mut buffer = create_aligned_buffer(SlotRingBuffer::required_size(8, 4));
+        let mut ring = SlotRingBuffer::new(&mut buffer, 0, 8, 4).unwrap();
+        for value in 0..4u64 {
+            assert_eq!(ring.push(&value.to_le_bytes()).unwrap(), value);
+        }
+        assert!(ring.is_full());
+        assert!(matches!(
+            ring.push(&[0; 8]),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert!(matches!(
+            ring.push(&[0; 4]),
+            Err(MemoryMapError::LengthMismatch)
+        ));
+
+        assert_eq!(ring.consume_up_to(1).unwrap(), 2);
+        assert_eq!(ring.consume_up_to(0).unwrap(), 0);
+        assert!(matches!(
+            ring.consume_up_to(4),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert_eq!(ring.get(1), None);
+
+        // Wraps around into the consumed records
+        assert_eq!(ring.push(&40u64.to_le_bytes()).unwrap(), 4);
+        assert_eq!(ring.push(&50u64.to_le_bytes()).unwrap(), 5);
+        assert_eq!(ring.get(4), Some(&40u64.to_le_bytes()[..]));
+
+        // Reopened from the same bytes
+        let ring = SlotRingBuffer::new(&mut buffer, 0, 8, 4).unwrap();
+        assert_eq!((ring.head(), ring.tail()), (2, 6));
+        let seqs: Vec<u64> = ring.iter().map(|(seq, _)| seq).collect();
+        assert_eq!(seqs, [2, 3, 4, 5]);
+        assert_eq!(ring.iter().last().unwrap().1, 50u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_header_is_checked() {
+        let mut buffer = create_aligned_buffer(SlotRingBuffer::required_size(16, 8));
+        SlotRingBuffer::new(&mut buffer, 0, 16, 8).unwrap();
+        assert!(matches!(
+            SlotRingBuffer::new(&mut buffer, 0, 16, 4),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert!(matches!(
+            SlotRingBuffer::new(&mut buffer, 0, 0, 8),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        // Head past tail, then more events than records
+        write_u64(&mut buffer, 16, 3);
+        assert!(matches!(
+            SlotRingBuffer::new(&mut buffer, 0, 16, 8),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        write_u64(&mut buffer, 16, 0);
+        write_u64(&mut buffer, 24, 9);
+        assert!(matches!(
+            SlotRingBuffer::new(&mut buffer, 0, 16, 8),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        write_u64(&mut buffer, 24, 8);
+        assert!(SlotRingBuffer::new(&mut buffer, 0, 16, 8)
+            .unwrap()
+            .is_full());
+
+        buffer[4] = 9;
+        assert!(matches!(
+            SlotRingBuffer::new(&mut buffer, 0, 16, 8),
+            Err(MemoryMapError::UnsupportedVersion)
+        ));
+        buffer[0] = 1;
+        assert!(matches!(
+            SlotRingBuffer::new(&mut buffer, 0, 16, 8),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_shares_account_with_map() {
+        let map_size = MapType::Small.required_size();
+        let ring_size = SlotRingBuffer::required_size(32, 16);
+
+        let mut buffer = create_aligned_buffer(map_size + ring_size);
+        let (bitmap, events) = buffer.split_at_mut(map_size);
+        let mut map = MemoryMap::new_from_slice(bitmap, 0, MapType::Small).unwrap();
+        let mut ring = SlotRingBuffer::new(events, 0, 32, 16).unwrap();
+
+        let index = map.alloc().unwrap();
+        let mut event = [0u8; 32];
+        event[..8].copy_from_slice(&(index as u64).to_le_bytes());
+        assert_eq!(ring.push(&event).unwrap(), 0);
+        assert_eq!(map.used_count().unwrap(), 1);
+    }
+}
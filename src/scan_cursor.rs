@@ -0,0 +1,201 @@
+use crate::{MemoryMap, MemoryMapError};
+
+/// Enumerates allocated slots across several instructions
+///
+/// The position lives in a word provided by the caller, typically stored in
+/// the account, so each instruction picks up where the previous one stopped
+/// and stays within its compute budget. Writing 0 to the word restarts the
+/// scan.
+pub struct ScanCursor<'a> {
+    map: &'a MemoryMap,
+    position: &'a mut u64,
+}
+
+impl<'a> ScanCursor<'a> {
+    /// Resume a scan of `map` from the slot recorded in `position`
+    pub fn new(map: &'a MemoryMap, position: &'a mut u64) -> Self {
+        Self { map, position }
+    }
+
+    /// Check whether every slot has been visited
+    pub fn is_done(&self) -> bool {
+        *self.position >= self.map.capacity() as u64
+    }
+
+    /// Up to `max_items` allocated slots in ascending order, reading at most
+    /// `max_words` leaf words, advancing the persisted position as they are
+    /// consumed
+    ///
+    /// The word budget bounds the cost of a call on sparse maps, where most
+    /// leaf words are empty. A batch that runs out of either leaves the
+    /// position where it stopped, so the next call continues from there.
+    pub fn next_batch(&mut self, max_items: usize, max_words: usize) -> ScanBatch<'_, 'a> {
+        ScanBatch {
+            cursor: self,
+            items_left: max_items,
+            words_left: max_words,
+        }
+    }
+
+    fn next_allocated(&mut self, words_left: &mut usize) -> Option<Result<usize, MemoryMapError>> {
+        while !self.is_done() && *words_left > 0 {
+            *words_left -= 1;
+            let position = *self.position as usize;
+            let block = position >> 6;
+            let word = match self.map.leaf_word(block) {
+                Ok(word) => word & (u64::MAX << (position & 0x3f)),
+                Err(err) => return Some(Err(err)),
+            };
+            if word != 0 {
+                let index = (block << 6) + word.trailing_zeros() as usize;
+                *self.position = index as u64 + 1;
+                return Some(Ok(index));
+            }
+            *self.position = ((block + 1) << 6) as u64;
+        }
+
+        None
+    }
+}
+
+/// Iterator returned by [`ScanCursor::next_batch`]
+pub struct ScanBatch<'c, 'a> {
+    cursor: &'c mut ScanCursor<'a>,
+    items_left: usize,
+    words_left: usize,
+}
+
+impl Iterator for ScanBatch<'_, '_> {
+    type Item = Result<usize, MemoryMapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.items_left == 0 {
+            return None;
+        }
+        self.items_left -= 1;
+        self.cursor.next_allocated(&mut self.words_left)
+    }
+}
+
+impl MemoryMap {
+    /// Up to `limit` allocated slots at or after `start_index`, ascending
+    ///
+    /// For paginating off-chain: the next page starts one past the last index
+    /// returned, an empty or short page means the end was reached. Reads as
+    /// many leaf words as it takes to fill the page.
+    #[cfg(feature = "std")]
+    pub fn allocated_page(
+        &self,
+        start_index: usize,
+        limit: usize,
+    ) -> Result<Vec<usize>, MemoryMapError> {
+        let mut position = start_index as u64;
+        ScanCursor::new(self, &mut position)
+            .next_batch(limit, usize::MAX)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_scan_resumes_across_calls() {
+        let mut buffer = create_aligned_buffer(MapType::Max.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        let allocated = [3, 64, 65, 4_000, 100_000, MapType::Max.capacity() - 1];
+        for &index in &allocated {
+            map.alloc_at(index).unwrap();
+        }
+
+        let mut position = 0;
+        let mut seen = Vec::new();
+        let mut calls = 0;
+        loop {
+            // A fresh cursor per instruction, only the word is carried over
+            let mut cursor = ScanCursor::new(&map, &mut position);
+            for index in cursor.next_batch(2, usize::MAX) {
+                seen.push(index.unwrap());
+            }
+            calls += 1;
+            if cursor.is_done() {
+                break;
+            }
+        }
+        assert_eq!(seen, allocated);
+        assert_eq!(calls, 3);
+
+        // Dropping part of a batch leaves the rest for the next call
+        position = 0;
+        let mut cursor = ScanCursor::new(&map, &mut position);
+        assert_eq!(cursor.next_batch(5, 8).next().unwrap().unwrap(), 3);
+        let batch: Result<Vec<_>, _> = cursor.next_batch(1, 8).collect();
+        assert_eq!(batch.unwrap(), [64]);
+        assert_eq!(position, 65);
+    }
+
+    #[test]
+    fn test_word_budget_bounds_sparse_scans() {
+        for map_type in [MapType::Max, MapType::Huge] {
+            let mut buffer = create_aligned_buffer(map_type.required_size());
+            let mut map = MemoryMap::new_from_slice(&mut buffer, 0, map_type).unwrap();
+            let last = map_type.capacity() - 1;
+            map.alloc_at(last).unwrap();
+
+            let leaf_words = map_type.capacity() / 64;
+            let mut position = 0;
+            let mut calls = 0;
+            let mut seen = Vec::new();
+            loop {
+                let mut cursor = ScanCursor::new(&map, &mut position);
+                for index in cursor.next_batch(1, 1_024) {
+                    seen.push(index.unwrap());
+                }
+                calls += 1;
+                if cursor.is_done() {
+                    break;
+                }
+                // An empty stretch stops at the budget, one word at a time
+                assert_eq!(position, (calls * 1_024 * 64) as u64);
+            }
+            assert_eq!(seen, [last]);
+            assert_eq!(calls, leaf_words / 1_024);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_allocated_pages() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        for _ in 0..250 {
+            map.alloc().unwrap();
+        }
+        for index in (0..250).step_by(3) {
+            map.dealloc(index).unwrap();
+        }
+
+        let mut pages = Vec::new();
+        let mut start = 0;
+        loop {
+            let page = map.allocated_page(start, 50).unwrap();
+            let Some(&last) = page.last() else {
+                break;
+            };
+            start = last + 1;
+            pages.push(page);
+        }
+        assert_eq!(pages.len(), 4);
+        assert!(pages
+            .concat()
+            .into_iter()
+            .eq((0..250).filter(|index| index % 3 != 0)));
+        assert_eq!(map.allocated_page(1, 3).unwrap(), [1, 2, 4]);
+        assert!(map
+            .allocated_page(MapType::Small.capacity(), 10)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{MemoryMap, MemoryMapError};
+use core::mem::size_of;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl MemoryMap {
+    /// Hash of the whole bitmap (FNV-1a over little-endian words)
+    ///
+    /// Cheap change detection, not a cryptographic commitment.
+    pub fn state_hash(&self) -> u64 {
+        self.as_bytes()
+            .chunks_exact(size_of::<u64>())
+            .fold(FNV_OFFSET, |hash, word| {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(word);
+                (hash ^ u64::from_le_bytes(bytes)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Record the current [`MemoryMap::state_hash`] in the header
+    ///
+    /// Only maps created with [`MemoryMap::init`] or [`MemoryMap::load`] have a
+    /// header to seal.
+    pub fn seal(&mut self) -> Result<u64, MemoryMapError> {
+        let hash = self.state_hash();
+        self.header_mut()
+            .ok_or(MemoryMapError::InvalidHeader)?
+            .seal_hash = hash;
+        Ok(hash)
+    }
+
+    /// Check whether the bitmap changed since the last [`MemoryMap::seal`]
+    pub fn changed_since_seal(&self) -> Result<bool, MemoryMapError> {
+        let header = self.header().ok_or(MemoryMapError::InvalidHeader)?;
+        Ok(header.seal_hash != self.state_hash())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_seal_and_detect_changes() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer[..size], 0, MapType::Small).unwrap();
+        assert!(map.changed_since_seal().unwrap());
+
+        map.alloc().unwrap();
+        let hash = map.seal().unwrap();
+        assert_eq!(hash, map.state_hash());
+        assert!(!map.changed_since_seal().unwrap());
+
+        // The sealed hash survives a reload
+        let mut map = MemoryMap::load(&mut buffer[..size], 0, MapType::Small).unwrap();
+        assert!(!map.changed_since_seal().unwrap());
+
+        let index = map.alloc().unwrap();
+        assert!(map.changed_since_seal().unwrap());
+        // Back to the sealed state
+        map.dealloc(index).unwrap();
+        assert!(!map.changed_since_seal().unwrap());
+    }
+
+    #[test]
+    fn test_seal_needs_header() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let empty = map.state_hash();
+        map.alloc().unwrap();
+        assert_ne!(map.state_hash(), empty);
+
+        assert!(matches!(map.seal(), Err(MemoryMapError::InvalidHeader)));
+        assert!(matches!(
+            map.changed_since_seal(),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{MapType, MemoryMap, MemoryMapError};
+
+/// Slots of `item_size` bytes handed out by a map of type `map_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeClass {
+    pub item_size: usize,
+    pub map_type: MapType,
+}
+
+/// Several maps in one account, one per size class
+///
+/// The bitmaps are laid out back to back in class order. An allocation goes
+/// to the smallest class its size fits in, moving up to larger classes when
+/// that one is full.
+#[derive(Clone)]
+pub struct SlabAllocator {
+    classes: Vec<(SizeClass, MemoryMap)>,
+}
+
+impl SlabAllocator {
+    /// Number of bytes taken by the bitmaps of `classes`
+    pub fn required_size(classes: &[SizeClass]) -> usize {
+        classes
+            .iter()
+            .map(|class| class.map_type.required_size())
+            .sum()
+    }
+
+    /// Attach the bitmaps of `classes` starting at `data[offset..]`
+    ///
+    /// Classes must be sorted by strictly increasing item size. A zeroed
+    /// region gives empty maps, the same region reopens existing ones.
+    pub fn new(
+        data: &mut [u8],
+        offset: usize,
+        classes: &[SizeClass],
+    ) -> Result<Self, MemoryMapError> {
+        let sorted = classes
+            .windows(2)
+            .all(|pair| pair[0].item_size < pair[1].item_size);
+        if classes.is_empty() || classes[0].item_size == 0 || !sorted {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        if data.len() < offset.saturating_add(Self::required_size(classes)) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+
+        let mut maps = Vec::with_capacity(classes.len());
+        let mut at = offset;
+        for &class in classes {
+            maps.push((class, MemoryMap::new_from_slice(data, at, class.map_type)?));
+            at += class.map_type.required_size();
+        }
+
+        Ok(Self { classes: maps })
+    }
+
+    /// Smallest class whose items hold `size` bytes
+    pub fn class_for(&self, size: usize) -> Option<usize> {
+        self.classes
+            .iter()
+            .position(|(class, _)| class.item_size >= size)
+    }
+
+    /// Allocate a slot of at least `size` bytes, returning `(class, index)`
+    pub fn alloc(&mut self, size: usize) -> Result<(usize, usize), MemoryMapError> {
+        let first = self
+            .class_for(size)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        for (class, (_, map)) in self.classes.iter_mut().enumerate().skip(first) {
+            match map.alloc() {
+                Ok(index) => return Ok((class, index)),
+                Err(MemoryMapError::NoAvailableSlots) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(MemoryMapError::NoAvailableSlots)
+    }
+
+    /// Deallocate slot `index` of class `class`
+    pub fn dealloc(&mut self, class: usize, index: usize) -> Result<(), MemoryMapError> {
+        let (_, map) = self
+            .classes
+            .get_mut(class)
+            .ok_or(MemoryMapError::InvalidIndex)?;
+        map.dealloc(index)
+    }
+
+    /// Description of class `class`
+    pub fn size_class(&self, class: usize) -> Option<SizeClass> {
+        self.classes.get(class).map(|&(class, _)| class)
+    }
+
+    /// Map of class `class`
+    pub fn map(&self, class: usize) -> Option<&MemoryMap> {
+        self.classes.get(class).map(|(_, map)| map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    const CLASSES: [SizeClass; 2] = [
+        SizeClass {
+            item_size: 32,
+            map_type: MapType::Tiny,
+        },
+        SizeClass {
+            item_size: 128,
+            map_type: MapType::Small,
+        },
+    ];
+
+    #[test]
+    fn test_slab_classes() {
+        let size = 8 + SlabAllocator::required_size(&CLASSES);
+        let mut buffer = create_aligned_buffer(size);
+        let mut slab = SlabAllocator::new(&mut buffer, 8, &CLASSES).unwrap();
+
+        assert_eq!(slab.alloc(1).unwrap(), (0, 0));
+        assert_eq!(slab.alloc(32).unwrap(), (0, 1));
+        assert_eq!(slab.alloc(33).unwrap(), (1, 0));
+        assert!(matches!(
+            slab.alloc(129),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        // A full class spills into the next one
+        while slab.alloc(16).unwrap().0 == 0 {}
+        assert!(slab.map(0).unwrap().is_full());
+        slab.dealloc(0, 5).unwrap();
+        assert_eq!(slab.alloc(16).unwrap(), (0, 5));
+        assert!(matches!(
+            slab.dealloc(2, 0),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        // The same region reopens the maps as they were left
+        let mut slab = SlabAllocator::new(&mut buffer, 8, &CLASSES).unwrap();
+        assert_eq!(slab.alloc(100).unwrap(), (1, 2));
+        assert_eq!(slab.size_class(1), Some(CLASSES[1]));
+    }
+
+    #[test]
+    fn test_slab_rejects_bad_classes() {
+        let mut buffer = create_aligned_buffer(SlabAllocator::required_size(&CLASSES));
+        let reversed = [CLASSES[1], CLASSES[0]];
+        assert!(matches!(
+            SlabAllocator::new(&mut buffer, 0, &reversed),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            SlabAllocator::new(&mut buffer, 0, &[]),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            SlabAllocator::new(&mut buffer, 16, &CLASSES),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+//! Read-only occupancy queries without any `unsafe` code
+#![forbid(unsafe_code)]
+
+use crate::{bytes::read_u64, get_first_zero_bit::get_first_zero_bit, MapType, MemoryMapError};
+use core::mem::size_of;
+
+/// Occupancy queries over an immutable bitmap snapshot
+///
+/// Words are decoded with `u64::from_le_bytes`, so the bitmap needs no
+/// alignment. Suited to wasm and other contexts where unsafe code is ruled out.
+#[derive(Clone, Copy)]
+pub struct SliceQuery<'a> {
+    bitmap: &'a [u8],
+    map_type: MapType,
+}
+
+impl<'a> SliceQuery<'a> {
+    /// Query the bitmap of a `map_type` map starting at `data[offset..]`
+    ///
+    /// For maps created with `MemoryMap::init` the bitmap follows the header,
+    /// at `offset + HEADER_SIZE`.
+    pub fn new(data: &'a [u8], offset: usize, map_type: MapType) -> Result<Self, MemoryMapError> {
+        let bitmap = data.get(offset..).ok_or(MemoryMapError::InvalidOffset)?;
+        let bitmap = bitmap
+            .get(..map_type.required_size())
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        Ok(Self { bitmap, map_type })
+    }
+
+    /// Type of the queried map
+    pub const fn map_type(&self) -> MapType {
+        self.map_type
+    }
+
+    /// Check whether slot `index` is allocated
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        if index >= self.map_type.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let leaf = self.word(self.leaf_start() + (index >> 6));
+        Ok(leaf & (1 << (index & 0x3f)) != 0)
+    }
+
+    /// Number of allocated slots, counted from the leaf words
+    pub fn used_count(&self) -> usize {
+        let leaf_mask = self.leaf_mask();
+        (self.leaf_start()..self.bitmap.len() / size_of::<u64>())
+            .map(|word| (self.word(word) & leaf_mask).count_ones() as usize)
+            .sum()
+    }
+
+    /// Number of slots still available
+    pub fn free_count(&self) -> usize {
+        self.map_type.capacity() - self.used_count()
+    }
+
+    /// Check whether every slot is allocated
+    pub fn is_full(&self) -> bool {
+        let mask = first_level_mask(self.map_type);
+        self.word(0) & mask == mask
+    }
+
+    /// Allocated slots in ascending order
+    pub fn iter_allocated(&self) -> impl Iterator<Item = usize> + '_ {
+        let leaf_start = self.leaf_start();
+        let leaf_mask = self.leaf_mask();
+        (0..self.map_type.capacity() / 64).flat_map(move |block| {
+            let mut leaf = self.word(leaf_start + block) & leaf_mask;
+            core::iter::from_fn(move || {
+                if leaf == 0 {
+                    return None;
+                }
+                let bit = leaf.trailing_zeros() as usize;
+                leaf &= leaf - 1;
+                Some((block << 6) + bit)
+            })
+        })
+    }
+
+    /// Slot the next `alloc` would hand out on a map without hints
+    pub fn first_free(&self) -> Option<usize> {
+        let mut index = 0;
+        for level in 0..self.map_type.levels() {
+            let bits = if level == 0 {
+                self.map_type.first_level_bits()
+            } else {
+                64
+            };
+            let word = self.word(self.map_type.level_start(level) + index);
+            index = (index << 6) + get_first_zero_bit(word, bits).ok()?;
+        }
+        Some(index)
+    }
+
+    /// Leaf word `block`, masked to the map's slots, for blocks below
+    /// `capacity / 64`
+    pub(crate) fn leaf_word(&self, block: usize) -> u64 {
+        self.word(self.leaf_start() + block) & self.leaf_mask()
+    }
+
+    fn leaf_start(&self) -> usize {
+        self.map_type.level_start(self.map_type.levels() - 1)
+    }
+
+    /// Bits of a leaf word that map to slots
+    pub(crate) fn leaf_mask(&self) -> u64 {
+        if self.map_type.levels() == 1 {
+            first_level_mask(self.map_type)
+        } else {
+            u64::MAX
+        }
+    }
+
+    /// Word `index` of the bitmap, always in range once constructed
+    fn word(&self, index: usize) -> u64 {
+        read_u64(self.bitmap, index * size_of::<u64>())
+    }
+}
+
+const fn first_level_mask(map_type: MapType) -> u64 {
+    u64::MAX >> (64 - map_type.first_level_bits())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MemoryMap, HEADER_SIZE};
+
+    #[test]
+    fn test_matches_memory_map() {
+        for map_type in [MapType::Tiny, MapType::Standard, MapType::Small] {
+            let size = HEADER_SIZE + map_type.required_size();
+            let mut buffer = create_aligned_buffer(size);
+            let mut map = MemoryMap::init(&mut buffer, 0, map_type).unwrap();
+            for _ in 0..40 {
+                map.alloc().unwrap();
+            }
+            map.dealloc(7).unwrap();
+
+            let query = SliceQuery::new(&buffer, HEADER_SIZE, map_type).unwrap();
+            assert_eq!(query.used_count(), 39);
+            assert_eq!(query.free_count(), map_type.capacity() - 39);
+            assert!(query.is_allocated(6).unwrap());
+            assert!(!query.is_allocated(7).unwrap());
+            assert_eq!(query.first_free(), Some(7));
+            assert!(!query.is_full());
+            assert!(query
+                .iter_allocated()
+                .eq((0..40).filter(|&index| index != 7)));
+            assert!(matches!(
+                query.is_allocated(map_type.capacity()),
+                Err(MemoryMapError::InvalidIndex)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_full_and_unaligned() {
+        let size = MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size + 1);
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        while map.alloc().is_ok() {}
+
+        // Shift the snapshot by one byte, no alignment is needed
+        let mut shifted = vec![0];
+        shifted.extend_from_slice(&buffer[..size]);
+        let query = SliceQuery::new(&shifted, 1, MapType::Tiny).unwrap();
+        assert!(query.is_full());
+        assert_eq!(query.first_free(), None);
+        assert_eq!(query.used_count(), 64);
+
+        assert!(matches!(
+            SliceQuery::new(&shifted, 2, MapType::Tiny),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        assert!(matches!(
+            SliceQuery::new(&shifted, 10, MapType::Tiny),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    bytes::{read_u32, read_u64, write_u32, write_u64},
+    MapType, MemoryMap, MemoryMapError,
+};
+
+/// Map type of the node allocator
+const NODE_MAP: MapType = MapType::Small;
+
+/// `u64` key -> slot index map kept in an account, e.g. order ID -> order slot
+///
+/// Bucket-chained: each bucket heads a singly linked list of nodes, and the
+/// nodes are slots of a [`MapType::Small`] map. Layout from the given offset:
+/// an 8-byte header (length and bucket count), the bitmap, the bucket heads
+/// (4 bytes each), then one [`SlotHashMap::NODE_SIZE`] record per node slot.
+pub struct SlotHashMap<'a> {
+    header: &'a mut [u8],
+    map: MemoryMap,
+    buckets: &'a mut [u8],
+    nodes: &'a mut [u8],
+}
+
+/// Decoded node record
+#[derive(Clone, Copy)]
+struct Node {
+    key: u64,
+    slot: u64,
+    next: Option<usize>,
+}
+
+impl<'a> SlotHashMap<'a> {
+    /// Bytes per node record
+    pub const NODE_SIZE: usize = 24;
+
+    /// Most keys a map can hold
+    pub const CAPACITY: usize = NODE_MAP.capacity();
+
+    const HEADER_SIZE: usize = 8;
+
+    /// Number of bytes taken by a map with `buckets` buckets
+    pub const fn required_size(buckets: usize) -> usize {
+        Self::HEADER_SIZE
+            + NODE_MAP.required_size()
+            + buckets * 4
+            + Self::CAPACITY * Self::NODE_SIZE
+    }
+
+    /// Attach a map at `data[offset..]`
+    ///
+    /// `buckets` must be a power of two. A zeroed region gives an empty map
+    /// and records the bucket count, reopening with a different count fails
+    /// with `InvalidHeader`.
+    pub fn new(data: &'a mut [u8], offset: usize, buckets: usize) -> Result<Self, MemoryMapError> {
+        if !buckets.is_power_of_two() || buckets > u32::MAX as usize {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let end = offset
+            .checked_add(Self::required_size(buckets))
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        let region = data
+            .get_mut(offset..end)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+
+        let (header, rest) = region.split_at_mut(Self::HEADER_SIZE);
+        match read_u32(header, 4) as usize {
+            0 => write_u32(header, 4, buckets as u32),
+            stored if stored != buckets => return Err(MemoryMapError::InvalidHeader),
+            _ => {}
+        }
+        let (bitmap, rest) = rest.split_at_mut(NODE_MAP.required_size());
+        let (buckets, nodes) = rest.split_at_mut(buckets * 4);
+        let map = MemoryMap::new_from_slice(bitmap, 0, NODE_MAP)?;
+
+        Ok(Self {
+            header,
+            map,
+            buckets,
+            nodes,
+        })
+    }
+
+    /// Number of keys in the map
+    pub fn len(&self) -> usize {
+        read_u32(self.header, 0) as usize
+    }
+
+    /// Check whether the map has no keys
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Slot index stored for `key`
+    pub fn get(&self, key: u64) -> Option<usize> {
+        self.find(key)
+            .map(|(node, _)| self.node(node).slot as usize)
+    }
+
+    /// Map `key` to `slot`, returning the slot it replaced
+    pub fn insert(&mut self, key: u64, slot: usize) -> Result<Option<usize>, MemoryMapError> {
+        if let Some((node, _)) = self.find(key) {
+            let mut record = self.node(node);
+            let old = record.slot as usize;
+            record.slot = slot as u64;
+            self.set_node(node, record);
+            return Ok(Some(old));
+        }
+
+        let node = self.map.alloc()?;
+        let bucket = self.bucket(key);
+        self.set_node(
+            node,
+            Node {
+                key,
+                slot: slot as u64,
+                next: self.head(bucket),
+            },
+        );
+        self.set_head(bucket, Some(node));
+        self.set_len(self.len() + 1);
+
+        Ok(None)
+    }
+
+    /// Remove `key`, returning its slot index
+    pub fn remove(&mut self, key: u64) -> Result<Option<usize>, MemoryMapError> {
+        let Some((node, prev)) = self.find(key) else {
+            return Ok(None);
+        };
+        let record = self.node(node);
+        match prev {
+            None => self.set_head(self.bucket(key), record.next),
+            Some(prev) => {
+                let mut before = self.node(prev);
+                before.next = record.next;
+                self.set_node(prev, before);
+            }
+        }
+        self.map.dealloc(node)?;
+        self.set_len(self.len() - 1);
+
+        Ok(Some(record.slot as usize))
+    }
+
+    /// Map holding the node slots
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Node holding `key` and the node before it in the chain
+    fn find(&self, key: u64) -> Option<(usize, Option<usize>)> {
+        let mut prev = None;
+        let mut current = self.head(self.bucket(key));
+        while let Some(node) = current {
+            let record = self.node(node);
+            if record.key == key {
+                return Some((node, prev));
+            }
+            prev = current;
+            current = record.next;
+        }
+        None
+    }
+
+    /// Fibonacci hashing, the high half of the product picks the bucket
+    fn bucket(&self, key: u64) -> usize {
+        let count = self.buckets.len() / 4;
+        let hash = key.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        (hash >> 32) as usize & (count - 1)
+    }
+
+    fn head(&self, bucket: usize) -> Option<usize> {
+        // Node index plus one, zero for an empty bucket
+        (read_u32(self.buckets, bucket * 4) as usize).checked_sub(1)
+    }
+
+    fn set_head(&mut self, bucket: usize, node: Option<usize>) {
+        write_u32(self.buckets, bucket * 4, encode(node));
+    }
+
+    fn set_len(&mut self, len: usize) {
+        write_u32(self.header, 0, len as u32);
+    }
+
+    fn node(&self, node: usize) -> Node {
+        let record = &self.nodes[node * Self::NODE_SIZE..(node + 1) * Self::NODE_SIZE];
+        Node {
+            key: read_u64(record, 0),
+            slot: read_u64(record, 8),
+            next: (read_u32(record, 16) as usize).checked_sub(1),
+        }
+    }
+
+    fn set_node(&mut self, node: usize, value: Node) {
+        let record = &mut self.nodes[node * Self::NODE_SIZE..(node + 1) * Self::NODE_SIZE];
+        write_u64(record, 0, value.key);
+        write_u64(record, 8, value.slot);
+        write_u32(record, 16, encode(value.next));
+    }
+}
+
+const fn encode(node: Option<usize>) -> u32 {
+    match node {
+        Some(node) => node as u32 + 1,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut buffer = create_aligned_buffer(SlotHashMap::required_size(16));
+        let mut map = SlotHashMap::new(&mut buffer, 0, 16).unwrap();
+        assert!(map.is_empty());
+
+        // More keys than buckets, so chains form
+        for key in 0..200u64 {
+            let order_id = key.wrapping_mul(0x1_0000_0001);
+            assert_eq!(map.insert(order_id, key as usize).unwrap(), None);
+        }
+        assert_eq!(map.len(), 200);
+        assert_eq!(map.map().used_count().unwrap(), 200);
+        assert_eq!(map.insert(0x1_0000_0001 * 7, 900).unwrap(), Some(7));
+        assert_eq!(map.get(0x1_0000_0001 * 7), Some(900));
+        assert_eq!(map.get(1), None);
+
+        for key in (0..200u64).step_by(2) {
+            let order_id = key.wrapping_mul(0x1_0000_0001);
+            assert!(map.remove(order_id).unwrap().is_some());
+            assert_eq!(map.remove(order_id).unwrap(), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.map().used_count().unwrap(), 100);
+
+        // Reopened from the same bytes
+        let map = SlotHashMap::new(&mut buffer, 0, 16).unwrap();
+        for key in 0..200u64 {
+            let expected = match key {
+                7 => Some(900),
+                key if key % 2 == 0 => None,
+                key => Some(key as usize),
+            };
+            assert_eq!(map.get(key.wrapping_mul(0x1_0000_0001)), expected);
+        }
+    }
+
+    #[test]
+    fn test_full_map() {
+        let mut buffer = create_aligned_buffer(SlotHashMap::required_size(1));
+        let mut map = SlotHashMap::new(&mut buffer, 0, 1).unwrap();
+        for key in 0..SlotHashMap::CAPACITY as u64 {
+            map.insert(key, 0).unwrap();
+        }
+        assert!(matches!(
+            map.insert(u64::MAX, 0),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert_eq!(map.get(u64::MAX), None);
+        assert_eq!(map.remove(0).unwrap(), Some(0));
+        assert_eq!(map.insert(u64::MAX, 5).unwrap(), None);
+        assert_eq!(map.get(u64::MAX), Some(5));
+    }
+
+    #[test]
+    fn test_bucket_count_is_checked() {
+        let mut buffer = create_aligned_buffer(SlotHashMap::required_size(64));
+        assert!(matches!(
+            SlotHashMap::new(&mut buffer, 0, 48),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        SlotHashMap::new(&mut buffer, 0, 64).unwrap();
+        assert!(matches!(
+            SlotHashMap::new(&mut buffer, 0, 32),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert!(matches!(
+            SlotHashMap::new(&mut buffer, 16, 64),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    bytes::{read_u32, write_u32},
+    MapType, MemoryMap, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Ends and length of one list threaded through a [`SlotList`]
+///
+/// Kept by the caller, e.g. in the record of a price level. The default value
+/// is an empty list, and an all-zero record reads back as one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct ListHead {
+    // Slot index plus one, zero for none
+    head: u32,
+    tail: u32,
+    len: u32,
+}
+
+impl ListHead {
+    /// Number of slots in the list
+    pub const fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Check whether the list has no slots
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// First slot of the list
+    pub const fn front(&self) -> Option<usize> {
+        decode(self.head)
+    }
+
+    /// Last slot of the list
+    pub const fn back(&self) -> Option<usize> {
+        decode(self.tail)
+    }
+}
+
+/// Set in the prev link of every slot that is in a list
+///
+/// Slot indices stay below `1 << 24`, so the top bit of a link is never part
+/// of one.
+const LINKED: u32 = 1 << 31;
+
+/// Persisted prev/next links of every slot of a map, kept next to it
+///
+/// Chains allocated slots into doubly linked lists, e.g. orders of a price
+/// level in time priority, with O(1) removal. Each slot belongs to at most one
+/// list at a time: pushing a slot that is already linked is rejected. An
+/// all-zero region has every slot unlinked.
+pub struct SlotList<'a> {
+    data: &'a mut [u8],
+    map_type: MapType,
+}
+
+impl<'a> SlotList<'a> {
+    /// Number of bytes needed for the links of a map of type `map_type`
+    pub const fn size(map_type: MapType) -> usize {
+        map_type.capacity() * 2 * size_of::<u32>()
+    }
+
+    /// Wrap a region previously zeroed or written by this extension
+    pub fn new(data: &'a mut [u8], map_type: MapType) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::size(map_type) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data, map_type })
+    }
+
+    /// Append slot `index` to the list
+    ///
+    /// Fails with `InvalidIndex` if the slot is already in this or another list.
+    pub fn push_back(&mut self, list: &mut ListHead, index: usize) -> Result<(), MemoryMapError> {
+        self.check_unlinked(index)?;
+        self.set_links(index, list.tail | LINKED, 0);
+        match list.back() {
+            Some(tail) => self.set_next(tail, encode(index)),
+            None => list.head = encode(index),
+        }
+        list.tail = encode(index);
+        list.len += 1;
+        Ok(())
+    }
+
+    /// Prepend slot `index` to the list
+    ///
+    /// Fails with `InvalidIndex` if the slot is already in this or another list.
+    pub fn push_front(&mut self, list: &mut ListHead, index: usize) -> Result<(), MemoryMapError> {
+        self.check_unlinked(index)?;
+        self.set_links(index, LINKED, list.head);
+        match list.front() {
+            Some(head) => self.set_prev(head, encode(index)),
+            None => list.tail = encode(index),
+        }
+        list.head = encode(index);
+        list.len += 1;
+        Ok(())
+    }
+
+    /// Unlink slot `index` from the list
+    ///
+    /// A slot whose links do not lead back to `list` is rejected.
+    pub fn remove(&mut self, list: &mut ListHead, index: usize) -> Result<(), MemoryMapError> {
+        self.check_index(index)?;
+        let (prev, next) = self.links(index);
+        let linked_prev = match decode(prev) {
+            Some(prev) => self.links(prev).1 == encode(index),
+            None => list.head == encode(index),
+        };
+        let linked_next = match decode(next) {
+            Some(next) => self.links(next).0 == encode(index),
+            None => list.tail == encode(index),
+        };
+        if list.is_empty() || !self.is_linked(index) || !linked_prev || !linked_next {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+
+        match decode(prev) {
+            Some(prev) => self.set_next(prev, next),
+            None => list.head = next,
+        }
+        match decode(next) {
+            Some(next) => self.set_prev(next, prev),
+            None => list.tail = prev,
+        }
+        self.set_links(index, 0, 0);
+        list.len -= 1;
+        Ok(())
+    }
+
+    /// Allocate a slot in `map` and append it to the list
+    pub fn alloc_back(
+        &mut self,
+        map: &mut MemoryMap,
+        list: &mut ListHead,
+    ) -> Result<usize, MemoryMapError> {
+        if map.map_type() != self.map_type {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        let index = map.alloc()?;
+        self.push_back(list, index)?;
+        Ok(index)
+    }
+
+    /// Unlink slot `index` from the list and free it in `map`
+    pub fn dealloc(
+        &mut self,
+        map: &mut MemoryMap,
+        list: &mut ListHead,
+        index: usize,
+    ) -> Result<(), MemoryMapError> {
+        if map.map_type() != self.map_type {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        self.remove(list, index)?;
+        map.dealloc(index)
+    }
+
+    /// Slots of the list from front to back
+    pub fn iter<'l>(&'l self, list: &ListHead) -> SlotListIter<'l, 'a> {
+        SlotListIter {
+            links: self,
+            next: list.front(),
+        }
+    }
+
+    /// Check whether slot `index` is in a list
+    pub fn is_linked(&self, index: usize) -> bool {
+        index < self.map_type.capacity() && self.word(index * 2) & LINKED != 0
+    }
+
+    /// Slot after `index` in its list
+    pub fn next(&self, index: usize) -> Option<usize> {
+        (index < self.map_type.capacity())
+            .then(|| decode(self.links(index).1))
+            .flatten()
+    }
+
+    /// Slot before `index` in its list
+    pub fn prev(&self, index: usize) -> Option<usize> {
+        (index < self.map_type.capacity())
+            .then(|| decode(self.links(index).0))
+            .flatten()
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), MemoryMapError> {
+        if index >= self.map_type.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(())
+    }
+
+    fn check_unlinked(&self, index: usize) -> Result<(), MemoryMapError> {
+        self.check_index(index)?;
+        if self.is_linked(index) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        Ok(())
+    }
+
+    /// Prev and next link of `index`, without the linked flag
+    fn links(&self, index: usize) -> (u32, u32) {
+        (self.word(index * 2) & !LINKED, self.word(index * 2 + 1))
+    }
+
+    /// Overwrite both links of `index`, the linked flag included in `prev`
+    fn set_links(&mut self, index: usize, prev: u32, next: u32) {
+        self.set_word(index * 2, prev);
+        self.set_next(index, next);
+    }
+
+    /// Point the prev link of `index` at `prev`, keeping its linked flag
+    fn set_prev(&mut self, index: usize, prev: u32) {
+        let flag = self.word(index * 2) & LINKED;
+        self.set_word(index * 2, prev | flag);
+    }
+
+    fn set_next(&mut self, index: usize, next: u32) {
+        self.set_word(index * 2 + 1, next);
+    }
+
+    fn word(&self, word: usize) -> u32 {
+        read_u32(self.data, word * size_of::<u32>())
+    }
+
+    fn set_word(&mut self, word: usize, value: u32) {
+        write_u32(self.data, word * size_of::<u32>(), value);
+    }
+}
+
+/// Iterator over the slots of one list, see [`SlotList::iter`]
+pub struct SlotListIter<'l, 'a> {
+    links: &'l SlotList<'a>,
+    next: Option<usize>,
+}
+
+impl Iterator for SlotListIter<'_, '_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let index = self.next?;
+        self.next = self.links.next(index);
+        Some(index)
+    }
+}
+
+/// Slot index stored plus one, so zero means no slot
+const fn encode(index: usize) -> u32 {
+    index as u32 + 1
+}
+
+const fn decode(link: u32) -> Option<usize> {
+    match link {
+        0 => None,
+        link => Some(link as usize - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_push_remove_iterate() {
+        let mut region = vec![0u8; SlotList::size(MapType::Tiny)];
+        let mut links = SlotList::new(&mut region, MapType::Tiny).unwrap();
+        let mut list = ListHead::default();
+
+        links.push_back(&mut list, 5).unwrap();
+        links.push_back(&mut list, 9).unwrap();
+        links.push_front(&mut list, 2).unwrap();
+        assert_eq!(links.iter(&list).collect::<Vec<_>>(), [2, 5, 9]);
+        assert_eq!(
+            (list.front(), list.back(), list.len()),
+            (Some(2), Some(9), 3)
+        );
+
+        links.remove(&mut list, 5).unwrap();
+        assert_eq!(links.iter(&list).collect::<Vec<_>>(), [2, 9]);
+        assert_eq!(links.prev(9), Some(2));
+        // Unlinked slots and slots of another list are rejected
+        assert!(matches!(
+            links.remove(&mut list, 5),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        let mut other = ListHead::default();
+        links.push_back(&mut other, 30).unwrap();
+        assert!(matches!(
+            links.remove(&mut list, 30),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        links.remove(&mut list, 2).unwrap();
+        links.remove(&mut list, 9).unwrap();
+        assert!(list.is_empty());
+        assert_eq!(list, ListHead::default());
+        assert_eq!(links.iter(&list).next(), None);
+        assert!(matches!(
+            links.push_back(&mut list, 64),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_push_rejects_linked_slots() {
+        let mut region = vec![0u8; SlotList::size(MapType::Tiny)];
+        let mut links = SlotList::new(&mut region, MapType::Tiny).unwrap();
+        let (mut a, mut b) = (ListHead::default(), ListHead::default());
+
+        // A single slot has no neighbours but is still linked
+        links.push_back(&mut a, 5).unwrap();
+        assert!(links.is_linked(5));
+        assert!(matches!(
+            links.push_back(&mut a, 5),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            links.push_front(&mut a, 5),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert_eq!(links.iter(&a).collect::<Vec<_>>(), [5]);
+
+        // Members of another list are left where they are
+        links.push_back(&mut b, 7).unwrap();
+        links.push_front(&mut b, 3).unwrap();
+        assert!(matches!(
+            links.push_back(&mut a, 3),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert!(matches!(
+            links.push_front(&mut a, 7),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        assert_eq!(links.iter(&b).collect::<Vec<_>>(), [3, 7]);
+        assert_eq!(b.len(), 2);
+
+        // Once removed the slot may join another list
+        links.remove(&mut b, 3).unwrap();
+        assert!(!links.is_linked(3));
+        links.push_back(&mut a, 3).unwrap();
+        assert_eq!(links.iter(&a).collect::<Vec<_>>(), [5, 3]);
+        assert_eq!(links.iter(&b).collect::<Vec<_>>(), [7]);
+        assert!(!links.is_linked(64));
+    }
+
+    #[test]
+    fn test_with_map() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = vec![0u8; SlotList::size(MapType::Small)];
+        let mut links = SlotList::new(&mut region, MapType::Small).unwrap();
+        let (mut bids, mut asks) = (ListHead::default(), ListHead::default());
+
+        for _ in 0..3 {
+            links.alloc_back(&mut map, &mut bids).unwrap();
+            links.alloc_back(&mut map, &mut asks).unwrap();
+        }
+        assert_eq!(links.iter(&bids).collect::<Vec<_>>(), [0, 2, 4]);
+        assert_eq!(links.iter(&asks).collect::<Vec<_>>(), [1, 3, 5]);
+
+        links.dealloc(&mut map, &mut bids, 2).unwrap();
+        assert!(!map.is_allocated(2).unwrap());
+        assert_eq!(links.alloc_back(&mut map, &mut asks).unwrap(), 2);
+        assert_eq!(links.iter(&asks).collect::<Vec<_>>(), [1, 3, 5, 2]);
+        assert_eq!(links.iter(&bids).collect::<Vec<_>>(), [0, 4]);
+    }
+}
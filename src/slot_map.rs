@@ -0,0 +1,160 @@
+use crate::{
+    bytes::{read_u64, write_u64},
+    MemoryMap, MemoryMapError, SlotHashMap,
+};
+use core::mem::size_of;
+
+/// Map whose slots are allocated for caller keys, e.g. order IDs, with both
+/// directions of the mapping kept in the account
+///
+/// Key -> slot lives in a [`SlotHashMap`], slot -> key in one `u64` per slot
+/// after it. Both are only changed together with the allocation, so they
+/// cannot drift apart as long as the map is only mutated through this type.
+/// Holds at most [`SlotHashMap::CAPACITY`] keys.
+pub struct SlotMap<'a> {
+    map: MemoryMap,
+    index: SlotHashMap<'a>,
+    keys: &'a mut [u8],
+}
+
+impl<'a> SlotMap<'a> {
+    /// Number of bytes needed after the offset for the mapping of `map` with
+    /// `buckets` hash buckets
+    pub const fn required_size(map: &MemoryMap, buckets: usize) -> usize {
+        SlotHashMap::required_size(buckets) + map.capacity() * size_of::<u64>()
+    }
+
+    /// Attach the mapping at `data[offset..]` to `map`
+    ///
+    /// See [`SlotHashMap::new`] for the bucket count rules.
+    pub fn new(
+        map: MemoryMap,
+        data: &'a mut [u8],
+        offset: usize,
+        buckets: usize,
+    ) -> Result<Self, MemoryMapError> {
+        let index_end = offset
+            .checked_add(SlotHashMap::required_size(buckets))
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        let keys_len = map.capacity() * size_of::<u64>();
+        if data.len().saturating_sub(index_end) < keys_len {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        let (head, tail) = data.split_at_mut(index_end);
+        Ok(Self {
+            index: SlotHashMap::new(head, offset, buckets)?,
+            keys: &mut tail[..keys_len],
+            map,
+        })
+    }
+
+    /// Map handing out the slots behind the keys
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Number of mapped keys
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Check whether no key is mapped
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Allocate a slot for `key`
+    ///
+    /// Fails with `DuplicateKey` if `key` already has a slot.
+    pub fn alloc(&mut self, key: u64) -> Result<usize, MemoryMapError> {
+        if self.index.get(key).is_some() {
+            return Err(MemoryMapError::DuplicateKey);
+        }
+        let slot = self.map.alloc()?;
+        if let Err(err) = self.index.insert(key, slot) {
+            self.map.dealloc(slot)?;
+            return Err(err);
+        }
+        write_u64(self.keys, slot * size_of::<u64>(), key);
+        Ok(slot)
+    }
+
+    /// Free the slot of `key` and return it
+    pub fn dealloc_key(&mut self, key: u64) -> Result<usize, MemoryMapError> {
+        let slot = self.index.get(key).ok_or(MemoryMapError::InvalidIndex)?;
+        self.map.dealloc(slot)?;
+        self.index.remove(key)?;
+        Ok(slot)
+    }
+
+    /// Free `slot` and return the key it was allocated for
+    pub fn dealloc(&mut self, slot: usize) -> Result<u64, MemoryMapError> {
+        let key = self.key(slot)?.ok_or(MemoryMapError::InvalidIndex)?;
+        self.dealloc_key(key)?;
+        Ok(key)
+    }
+
+    /// Slot allocated for `key`
+    pub fn slot(&self, key: u64) -> Option<usize> {
+        self.index.get(key)
+    }
+
+    /// Key `slot` was allocated for, `None` if the slot is free
+    pub fn key(&self, slot: usize) -> Result<Option<u64>, MemoryMapError> {
+        if !self.map.is_allocated(slot)? {
+            return Ok(None);
+        }
+        Ok(Some(read_u64(self.keys, slot * size_of::<u64>())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType};
+
+    #[test]
+    fn test_bidirectional_mapping() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = create_aligned_buffer(SlotMap::required_size(&map, 64));
+        let mut slots = SlotMap::new(map, &mut region, 0, 64).unwrap();
+
+        for order_id in 1_000..1_100 {
+            slots.alloc(order_id).unwrap();
+        }
+        assert!(matches!(
+            slots.alloc(1_050),
+            Err(MemoryMapError::DuplicateKey)
+        ));
+        assert_eq!(slots.len(), 100);
+        assert_eq!(slots.slot(1_042), Some(42));
+        assert_eq!(slots.key(42).unwrap(), Some(1_042));
+
+        assert_eq!(slots.dealloc_key(1_042).unwrap(), 42);
+        assert_eq!(slots.dealloc(7).unwrap(), 1_007);
+        assert_eq!(slots.slot(1_007), None);
+        assert_eq!(slots.key(42).unwrap(), None);
+        assert!(matches!(
+            slots.dealloc(42),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        // Freed slots are reused for new keys
+        assert_eq!(slots.alloc(7_000).unwrap(), 7);
+        assert_eq!(slots.key(7).unwrap(), Some(7_000));
+        assert_eq!(slots.map().used_count().unwrap(), 99);
+    }
+
+    #[test]
+    fn test_region_too_small() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let size = SlotMap::required_size(&map, 4);
+        let mut region = create_aligned_buffer(size);
+        assert!(matches!(
+            SlotMap::new(map, &mut region[..size - 1], 0, 4),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
@@ -1,84 +1,16 @@
-use crate::{get_first_zero_bit::get_first_zero_bit, get_u64, get_u64_mut, MemoryMapError};
-use std::{mem::size_of, ptr::NonNull};
+use crate::hier_memory_map::HierMemoryMap;
 
 /// Small memory map implementation (2 levels)
-#[derive(Clone)]
-pub struct SmallMemoryMap {
-    memory: NonNull<u8>,
-    size: usize,
-}
-
-impl SmallMemoryMap {
-    /// Create a new small memory map
-    pub fn new(memory: NonNull<u8>, size: usize) -> Result<Self, MemoryMapError> {
-        // Calculate required memory size for small map:
-        // - First level: 1 word to track available blocks in level 2
-        // - Second level: 64 words (one per bit in first level)
-        let required_size = (1 + 64) * size_of::<u64>();
-
-        // Check if there's enough memory
-        if size < required_size {
-            return Err(MemoryMapError::InsufficientMemory);
-        }
-
-        Ok(Self { memory, size })
-    }
-
-    /// Allocate a new slot
-    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
-        // First level allocation
-        let first_word = get_u64(self.memory, self.size, 0)?;
-        let first = get_first_zero_bit(*first_word, 64)?;
-
-        // Second level allocation
-        let second_idx = 1 + first;
-        let second_word = get_u64(self.memory, self.size, second_idx)?;
-        let second = get_first_zero_bit(*second_word, 64)?;
-
-        // Mark as allocated
-        let second_word_mut = get_u64_mut(self.memory, self.size, second_idx)?;
-        *second_word_mut |= 1 << second;
-
-        if *second_word_mut == u64::MAX {
-            let first_word_mut = get_u64_mut(self.memory, self.size, 0)?;
-            *first_word_mut |= 1 << first;
-        }
-
-        Ok((first << 6) + second)
-    }
-
-    /// Deallocate a previously allocated slot
-    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
-        // Check upper bound
-        let max_index = (64 << 6) - 1; // 4095
-        if index > max_index {
-            return Err(MemoryMapError::InvalidIndex);
-        }
-
-        // Small memory map - 2 levels
-        let first = index >> 6;
-        let second_idx = 1 + first;
-
-        // Clear allocation bits
-        let second_word = get_u64_mut(self.memory, self.size, second_idx)?;
-        *second_word &= !(1 << (index & 0x3f));
-
-        let first_word = get_u64_mut(self.memory, self.size, 0)?;
-        *first_word &= !(1 << first);
-
-        Ok(())
-    }
-}
+pub type SmallMemoryMap = HierMemoryMap<64, 2>;
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::create_aligned_memory;
-    use std::mem::size_of;
+    use crate::{create_aligned_memory, MapType, MemoryMapError};
 
     #[test]
     fn test_small_map_basic_operations() {
-        let required_size = (1 + 64) * size_of::<u64>();
+        let required_size = MapType::Small.required_size();
         let (mut data, ptr) = create_aligned_memory(required_size * 2);
 
         // 1. Test creation
@@ -124,7 +56,7 @@ mod tests {
 
     #[test]
     fn test_small_map_level_transition() {
-        let required_size = (1 + 64) * size_of::<u64>();
+        let required_size = MapType::Small.required_size();
         let (mut data, ptr) = create_aligned_memory(required_size * 2);
 
         data.fill(0); // Clear the memory
@@ -170,7 +102,7 @@ mod tests {
 
     #[test]
     fn test_deallocation_and_reuse() {
-        let required_size = (1 + 64) * size_of::<u64>();
+        let required_size = MapType::Small.required_size();
         let (mut data, ptr) = create_aligned_memory(required_size);
 
         data.fill(0);
@@ -200,4 +132,19 @@ mod tests {
             "Should start from beginning after deallocating all"
         );
     }
+
+    #[test]
+    fn test_small_map_full() {
+        let required_size = MapType::Small.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+        let mut map = SmallMemoryMap::new(ptr, data.len()).unwrap();
+
+        for expected in 0..MapType::Small.capacity() {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+    }
 }
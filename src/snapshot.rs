@@ -0,0 +1,105 @@
+//! Borsh types for shipping occupancy in instruction data and events
+
+use crate::{MapType, MemoryMap, MemoryMapError};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Compact occupancy of a map: only the leaf words with allocated slots
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct OccupancySnapshot {
+    pub map_type: u8,
+    /// Nonzero leaf words as `(block, word)`, ascending by block
+    pub words: Vec<(u32, u64)>,
+}
+
+impl OccupancySnapshot {
+    /// Type of the map the snapshot was taken from
+    pub fn map_type(&self) -> Result<MapType, MemoryMapError> {
+        MapType::try_from(self.map_type)
+    }
+
+    /// Number of allocated slots
+    pub fn used_count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|&(_, word)| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Check whether slot `index` was allocated
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        if index >= self.map_type()?.capacity() {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let block = (index >> 6) as u32;
+        Ok(self
+            .words
+            .binary_search_by_key(&block, |&(block, _)| block)
+            .is_ok_and(|at| self.words[at].1 & (1 << (index & 0x3f)) != 0))
+    }
+}
+
+impl MemoryMap {
+    /// Occupancy snapshot that serializes to a few bytes for sparse maps
+    pub fn occupancy_snapshot(&self) -> Result<OccupancySnapshot, MemoryMapError> {
+        let words = self
+            .to_bitvec()?
+            .chunks(8)
+            .enumerate()
+            .filter_map(|(block, chunk)| {
+                let mut bytes = [0; 8];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                let word = u64::from_le_bytes(bytes);
+                (word != 0).then_some((block as u32, word))
+            })
+            .collect();
+        Ok(OccupancySnapshot {
+            map_type: self.map_type() as u8,
+            words,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapHeader, HEADER_SIZE};
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Standard).unwrap();
+        for _ in 0..70 {
+            map.alloc().unwrap();
+        }
+        assert_eq!(map.alloc_near(100).unwrap(), 6_400);
+        map.dealloc(3).unwrap();
+
+        let snapshot = map.occupancy_snapshot().unwrap();
+        assert_eq!(snapshot.words.len(), 3);
+        let bytes = borsh::to_vec(&snapshot).unwrap();
+        assert_eq!(bytes.len(), 1 + 4 + 3 * 12);
+
+        let decoded = OccupancySnapshot::try_from_slice(&bytes).unwrap();
+        assert_eq!(decoded, snapshot);
+        assert_eq!(decoded.map_type().unwrap(), MapType::Standard);
+        assert_eq!(decoded.used_count(), 70);
+        assert!(decoded.is_allocated(69).unwrap());
+        assert!(!decoded.is_allocated(3).unwrap());
+        assert!(decoded.is_allocated(6_400).unwrap());
+        assert!(matches!(
+            decoded.is_allocated(MapType::Standard.capacity()),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let mut header = MapHeader::new(MapType::Small);
+        header.used_count = 12;
+        header.slot_limit = 300;
+
+        let bytes = borsh::to_vec(&header).unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE);
+        assert_eq!(MapHeader::try_from_slice(&bytes).unwrap(), header);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    bytes::{read_u64, write_u64},
+    MapType, MemoryMap, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Number of first-level regions tracked by the histogram
+pub const STATS_REGIONS: usize = 64;
+
+/// Persisted allocation statistics kept next to a map
+///
+/// Holds a coarse histogram counting allocations per first-level region, so
+/// clustered allocations can be spotted after the fact without snapshots of
+/// the bitmap. An all-zero region is an empty histogram.
+pub struct StatsExtension<'a> {
+    data: &'a mut [u8],
+}
+
+impl<'a> StatsExtension<'a> {
+    /// Number of bytes needed for the extension
+    pub const SIZE: usize = STATS_REGIONS * size_of::<u64>();
+
+    /// Wrap a region previously zeroed or written by this extension
+    pub fn new(data: &'a mut [u8]) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::SIZE {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        Ok(Self { data })
+    }
+
+    /// Count an allocation of `index` in a map of type `map_type`
+    pub fn record_alloc(&mut self, map_type: MapType, index: usize) {
+        let region = index >> map_type.region_shift();
+        if region < STATS_REGIONS {
+            self.set_word(region, self.word(region).saturating_add(1));
+        }
+    }
+
+    /// Number of allocations recorded in first-level region `region`
+    pub fn region_allocs(&self, region: usize) -> u64 {
+        if region < STATS_REGIONS {
+            self.word(region)
+        } else {
+            0
+        }
+    }
+
+    /// Allocation counts of every first-level region
+    pub fn histogram(&self) -> [u64; STATS_REGIONS] {
+        let mut histogram = [0; STATS_REGIONS];
+        for (region, count) in histogram.iter_mut().enumerate() {
+            *count = self.word(region);
+        }
+        histogram
+    }
+
+    /// Clear the histogram
+    pub fn reset(&mut self) {
+        self.data[..Self::SIZE].fill(0);
+    }
+
+    fn word(&self, word: usize) -> u64 {
+        read_u64(self.data, word * size_of::<u64>())
+    }
+
+    fn set_word(&mut self, word: usize, value: u64) {
+        write_u64(self.data, word * size_of::<u64>(), value);
+    }
+}
+
+impl MemoryMap {
+    /// Allocate a new slot and count it in `stats`
+    pub fn alloc_with_stats(
+        &mut self,
+        stats: &mut StatsExtension,
+    ) -> Result<usize, MemoryMapError> {
+        let index = self.alloc()?;
+        stats.record_alloc(self.map_type(), index);
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_histogram_per_region() {
+        let mut buffer = create_aligned_buffer(MapType::Standard.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Standard).unwrap();
+        let mut region = vec![0u8; StatsExtension::SIZE];
+        let mut stats = StatsExtension::new(&mut region).unwrap();
+
+        // Standard regions hold 4096 slots each
+        for _ in 0..4_100 {
+            map.alloc_with_stats(&mut stats).unwrap();
+        }
+        map.dealloc(10).unwrap();
+        map.alloc_with_stats(&mut stats).unwrap();
+
+        assert_eq!(stats.region_allocs(0), 4_097);
+        assert_eq!(stats.region_allocs(1), 4);
+        assert_eq!(stats.region_allocs(2), 0);
+        assert_eq!(stats.region_allocs(STATS_REGIONS), 0);
+        assert_eq!(stats.histogram().iter().sum::<u64>(), 4_101);
+
+        stats.reset();
+        assert_eq!(stats.histogram(), [0; STATS_REGIONS]);
+    }
+
+    #[test]
+    fn test_histogram_is_persisted() {
+        let mut region = vec![0u8; StatsExtension::SIZE];
+        StatsExtension::new(&mut region)
+            .unwrap()
+            .record_alloc(MapType::Tiny, 63);
+
+        let stats = StatsExtension::new(&mut region).unwrap();
+        assert_eq!(stats.region_allocs(63), 1);
+
+        let mut short = vec![0u8; StatsExtension::SIZE - 1];
+        assert!(matches!(
+            StatsExtension::new(&mut short),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+}
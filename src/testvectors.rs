@@ -0,0 +1,275 @@
+//! Canonical operation-sequence test vectors
+//!
+//! A vector records a sequence of operations against a fresh map together
+//! with the outcome of every operation and the final bitmap words. Vectors can
+//! be exchanged as JSON or Borsh so other implementations of the allocator can
+//! check they behave bit-for-bit like this crate.
+
+use crate::{bytes::read_u64, create_aligned_buffer, MapType, MemoryMap, MemoryMapError};
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use std::mem::size_of;
+
+/// Single operation applied to the map
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+pub enum Op {
+    Alloc,
+    Dealloc(u64),
+}
+
+/// Observable result of an operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub enum Outcome {
+    Allocated(u64),
+    Deallocated,
+    /// Error variant name, e.g. `NoAvailableSlots`
+    Error(String),
+}
+
+/// Operation sequence with the expected outcomes and final state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct TestVector {
+    /// `MapType` discriminant
+    pub map_type: u8,
+    pub ops: Vec<Op>,
+    pub outcomes: Vec<Outcome>,
+    /// Bitmap words after the last operation
+    pub final_words: Vec<u64>,
+}
+
+/// Where a replayed vector diverged from its expectations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    InvalidMapType,
+    /// Number of recorded outcomes or words differs from the replay
+    Length,
+    /// Outcome of the operation at this position differs
+    Outcome {
+        step: usize,
+        expected: Outcome,
+        actual: Outcome,
+    },
+    /// Bitmap word at this position differs after the last operation
+    Word {
+        word: usize,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl TestVector {
+    /// Run `ops` against a fresh map and record what happened
+    pub fn record(map_type: MapType, ops: &[Op]) -> Self {
+        let (outcomes, final_words) = run(map_type, ops);
+        Self {
+            map_type: map_type as u8,
+            ops: ops.to_vec(),
+            outcomes,
+            final_words,
+        }
+    }
+
+    /// Run the operations again and compare against the recorded expectations
+    pub fn replay(&self) -> Result<(), Mismatch> {
+        let map_type = MapType::try_from(self.map_type).map_err(|_| Mismatch::InvalidMapType)?;
+        let (outcomes, final_words) = run(map_type, &self.ops);
+        if outcomes.len() != self.outcomes.len() || final_words.len() != self.final_words.len() {
+            return Err(Mismatch::Length);
+        }
+
+        for (step, (expected, actual)) in self.outcomes.iter().zip(&outcomes).enumerate() {
+            if expected != actual {
+                return Err(Mismatch::Outcome {
+                    step,
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+
+        for (word, (&expected, &actual)) in self.final_words.iter().zip(&final_words).enumerate() {
+            if expected != actual {
+                return Err(Mismatch::Word {
+                    word,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_borsh(&self) -> std::io::Result<Vec<u8>> {
+        borsh::to_vec(self)
+    }
+
+    pub fn from_borsh(bytes: &[u8]) -> std::io::Result<Self> {
+        borsh::from_slice(bytes)
+    }
+}
+
+/// Deterministic operation sequence derived from `seed`
+///
+/// Uses xorshift64 so other implementations can regenerate the same
+/// sequence: each step draws one value, values with the low two bits set
+/// dealloc `(value >> 2) % capacity`, anything else allocates.
+pub fn ops_from_seed(map_type: MapType, seed: u64, count: usize) -> Vec<Op> {
+    let mut state = seed.max(1);
+    let capacity = map_type.capacity() as u64;
+
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            if state & 0b11 == 0b11 {
+                Op::Dealloc((state >> 2) % capacity)
+            } else {
+                Op::Alloc
+            }
+        })
+        .collect()
+}
+
+/// Canonical vectors covering every map type
+pub fn canonical_vectors() -> Vec<TestVector> {
+    [
+        MapType::Max,
+        MapType::Standard,
+        MapType::Small,
+        MapType::Huge,
+        MapType::Tiny,
+        MapType::Medium,
+    ]
+    .into_iter()
+    .flat_map(|map_type| {
+        let capacity = map_type.capacity() as u64;
+        // Cross a leaf boundary, free a few slots and reuse them
+        let mut crossing = vec![Op::Alloc; 130];
+        crossing.extend([Op::Dealloc(3), Op::Dealloc(64), Op::Dealloc(capacity)]);
+        crossing.extend([Op::Alloc; 3]);
+
+        [
+            TestVector::record(map_type, &crossing),
+            TestVector::record(map_type, &ops_from_seed(map_type, 0x1d3a_5eed, 512)),
+        ]
+    })
+    .collect()
+}
+
+fn run(map_type: MapType, ops: &[Op]) -> (Vec<Outcome>, Vec<u64>) {
+    let mut bytes = create_aligned_buffer(map_type.required_size());
+    let bytes = &mut bytes[..map_type.required_size()];
+
+    // The map views `bytes` only inside this block, the words are read after
+    let outcomes = {
+        let mut map =
+            MemoryMap::new_from_slice(bytes, 0, map_type).expect("buffer sized for map type");
+        ops.iter()
+            .map(|op| match *op {
+                Op::Alloc => map.alloc().map(|index| Outcome::Allocated(index as u64)),
+                Op::Dealloc(index) => map.dealloc(index as usize).map(|()| Outcome::Deallocated),
+            })
+            .map(|result| {
+                result.unwrap_or_else(|err: MemoryMapError| Outcome::Error(format!("{err:?}")))
+            })
+            .collect()
+    };
+
+    let words = (0..bytes.len())
+        .step_by(size_of::<u64>())
+        .map(|at| read_u64(bytes, at))
+        .collect();
+    (outcomes, words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        for vector in canonical_vectors() {
+            assert_eq!(vector.ops.len(), vector.outcomes.len());
+            assert!(vector.replay().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_crossing_vector_outcomes() {
+        let ops = [
+            Op::Alloc,
+            Op::Alloc,
+            Op::Dealloc(0),
+            Op::Alloc,
+            Op::Dealloc(5000),
+        ];
+        let vector = TestVector::record(MapType::Small, &ops);
+
+        assert_eq!(
+            vector.outcomes,
+            vec![
+                Outcome::Allocated(0),
+                Outcome::Allocated(1),
+                Outcome::Deallocated,
+                Outcome::Allocated(0),
+                Outcome::Error("InvalidIndex".to_string()),
+            ]
+        );
+        // First leaf word holds slots 0 and 1
+        assert_eq!(vector.final_words[1], 0b11);
+    }
+
+    #[test]
+    fn test_json_and_borsh_roundtrip() {
+        let vector =
+            TestVector::record(MapType::Standard, &ops_from_seed(MapType::Standard, 7, 64));
+
+        let json = vector.to_json().unwrap();
+        assert_eq!(TestVector::from_json(&json).unwrap(), vector);
+
+        let bytes = vector.to_borsh().unwrap();
+        assert_eq!(TestVector::from_borsh(&bytes).unwrap(), vector);
+    }
+
+    #[test]
+    fn test_replay_detects_mismatch() {
+        let mut vector = TestVector::record(MapType::Small, &[Op::Alloc, Op::Alloc]);
+        vector.outcomes[1] = Outcome::Allocated(5);
+        assert!(matches!(
+            vector.replay(),
+            Err(Mismatch::Outcome { step: 1, .. })
+        ));
+
+        let mut vector = TestVector::record(MapType::Small, &[Op::Alloc]);
+        vector.final_words[1] = 0;
+        assert_eq!(
+            vector.replay(),
+            Err(Mismatch::Word {
+                word: 1,
+                expected: 0,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_ops_from_seed_is_deterministic() {
+        let a = ops_from_seed(MapType::Max, 42, 100);
+        let b = ops_from_seed(MapType::Max, 42, 100);
+        assert_eq!(a, b);
+        assert!(a.iter().any(|op| matches!(op, Op::Dealloc(_))));
+        assert!(a.contains(&Op::Alloc));
+    }
+}
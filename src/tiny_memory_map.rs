@@ -0,0 +1,33 @@
+use crate::hier_memory_map::HierMemoryMap;
+
+/// Tiny memory map implementation (1 level, 64 bits)
+pub type TinyMemoryMap = HierMemoryMap<64, 1>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_memory, MapType, MemoryMapError};
+
+    #[test]
+    fn test_tiny_map_operations() {
+        let (mut data, ptr) = create_aligned_memory(MapType::Tiny.required_size());
+        data.fill(0);
+
+        assert!(matches!(
+            TinyMemoryMap::new(ptr, 4),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+
+        let mut map = TinyMemoryMap::new(ptr, 8).unwrap();
+        for expected in 0..64 {
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(17).unwrap();
+        assert_eq!(map.alloc_near(3).unwrap(), 17);
+
+        assert!(matches!(map.dealloc(64), Err(MemoryMapError::InvalidIndex)));
+    }
+}
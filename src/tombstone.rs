@@ -0,0 +1,338 @@
+use crate::{
+    allocator::IndexAllocator,
+    bytes::{read_u32, read_u64, write_u32, write_u64},
+    MapType, MemoryMap, MemoryMapError,
+};
+use core::mem::size_of;
+
+/// Size of one tombstone record: remove sequence, then slot index
+const RECORD_SIZE: usize = size_of::<u64>() + size_of::<u32>();
+
+/// Bytes before the tombstone bitmap: the number of records
+const COUNT_SIZE: usize = size_of::<u64>();
+
+/// State of a slot once tombstones are taken into account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotState {
+    Free,
+    Allocated,
+    /// Removed, but kept allocated until [`TombstonedMemoryMap::reclaim`]
+    /// frees it
+    Tombstoned,
+}
+
+/// Persisted tombstones for deferred reclamation
+///
+/// A removed slot stays allocated in the map and gets its bit set in a
+/// tombstone bitmap, so with the map bit each slot takes two bits: Free,
+/// Allocated or Tombstoned. Each remove also appends a record of the slot and
+/// its sequence, so a reclaim pass recycles exactly the slots removed before a
+/// given sequence and off-chain readers never see an index reused within that
+/// window. An all-zero region has no tombstones.
+pub struct Tombstones<'a> {
+    data: &'a mut [u8],
+    map_type: MapType,
+}
+
+impl<'a> Tombstones<'a> {
+    /// Number of bytes needed to track a map of type `map_type` with up to
+    /// `depth` slots tombstoned at once
+    pub const fn size(map_type: MapType, depth: usize) -> usize {
+        COUNT_SIZE + Self::bitmap_size(map_type) + depth * RECORD_SIZE
+    }
+
+    const fn bitmap_size(map_type: MapType) -> usize {
+        map_type.capacity().div_ceil(8)
+    }
+
+    /// Wrap a region previously zeroed or written by this extension
+    ///
+    /// The depth is taken from the region's length.
+    pub fn new(data: &'a mut [u8], map_type: MapType) -> Result<Self, MemoryMapError> {
+        if data.len() < Self::size(map_type, 1) {
+            return Err(MemoryMapError::InsufficientMemory);
+        }
+        let tombstones = Self { data, map_type };
+        if tombstones.tombstone_count() > tombstones.depth() {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        Ok(tombstones)
+    }
+
+    /// Maximum number of slots tombstoned at once
+    pub fn depth(&self) -> usize {
+        (self.data.len() - Self::size(self.map_type, 0)) / RECORD_SIZE
+    }
+
+    /// Check whether slot `index` is tombstoned
+    pub fn is_tombstoned(&self, index: usize) -> bool {
+        index < self.map_type.capacity()
+            && self.data[COUNT_SIZE + (index >> 3)] & (1 << (index & 7)) != 0
+    }
+
+    /// Number of tombstoned slots
+    pub fn tombstone_count(&self) -> usize {
+        read_u64(self.data, 0) as usize
+    }
+
+    fn set_count(&mut self, count: usize) {
+        write_u64(self.data, 0, count as u64);
+    }
+
+    fn flip(&mut self, index: usize) {
+        self.data[COUNT_SIZE + (index >> 3)] ^= 1 << (index & 7);
+    }
+
+    /// Sequence and slot of record `record`
+    fn record(&self, record: usize) -> (u64, usize) {
+        let at = Self::size(self.map_type, record);
+        let seq = read_u64(self.data, at);
+        (seq, read_u32(self.data, at + size_of::<u64>()) as usize)
+    }
+
+    fn set_record(&mut self, record: usize, seq: u64, index: usize) {
+        let at = Self::size(self.map_type, record);
+        write_u64(self.data, at, seq);
+        write_u32(self.data, at + size_of::<u64>(), index as u32);
+    }
+}
+
+/// Map whose removed slots are tombstoned and only freed by a reclaim pass
+///
+/// Owning the map keeps a tombstoned slot from being freed and handed out
+/// again behind the tombstones' back, which a later reclaim would then free
+/// while it is live.
+pub struct TombstonedMemoryMap<'a> {
+    map: MemoryMap,
+    tombstones: Tombstones<'a>,
+}
+
+impl<'a> TombstonedMemoryMap<'a> {
+    /// Pair `map` with tombstones of the same map type
+    pub fn new(map: MemoryMap, tombstones: Tombstones<'a>) -> Result<Self, MemoryMapError> {
+        if tombstones.map_type != map.map_type() {
+            return Err(MemoryMapError::InvalidMapType);
+        }
+        Ok(Self { map, tombstones })
+    }
+
+    /// Underlying map, for read-only queries
+    pub const fn map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Tombstones kept for the map
+    pub const fn tombstones(&self) -> &Tombstones<'a> {
+        &self.tombstones
+    }
+
+    /// Split back into the map and the tombstones
+    pub fn into_parts(self) -> (MemoryMap, Tombstones<'a>) {
+        (self.map, self.tombstones)
+    }
+
+    /// Allocate a new slot, tombstoned slots stay taken
+    pub fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        self.map.alloc()
+    }
+
+    /// Free live slot `index` right away, without a tombstone
+    ///
+    /// Fails with `InvalidIndex` for a tombstoned slot, which only
+    /// [`TombstonedMemoryMap::reclaim`] frees.
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        if self.tombstones.is_tombstoned(index) {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        self.map.dealloc(index)
+    }
+
+    /// State of slot `index`, tombstones included
+    pub fn slot_state(&self, index: usize) -> Result<SlotState, MemoryMapError> {
+        if !self.map.is_allocated(index)? {
+            Ok(SlotState::Free)
+        } else if self.tombstones.is_tombstoned(index) {
+            Ok(SlotState::Tombstoned)
+        } else {
+            Ok(SlotState::Allocated)
+        }
+    }
+
+    /// Tombstone allocated slot `index`, removed at sequence `seq`
+    ///
+    /// The slot stays allocated until a [`TombstonedMemoryMap::reclaim`] with
+    /// a later sequence frees it. Only allocated, live slots can be removed;
+    /// fails with `NoAvailableSlots` while `depth` slots are tombstoned.
+    pub fn remove(&mut self, index: usize, seq: u64) -> Result<(), MemoryMapError> {
+        if self.slot_state(index)? != SlotState::Allocated {
+            return Err(MemoryMapError::InvalidIndex);
+        }
+        let tombstones = &mut self.tombstones;
+        let count = tombstones.tombstone_count();
+        if count == tombstones.depth() {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        tombstones.set_record(count, seq, index);
+        tombstones.flip(index);
+        tombstones.set_count(count + 1);
+        Ok(())
+    }
+
+    /// Free every slot removed before `before_seq`, return how many
+    ///
+    /// Reads each record once, `12 * tombstone_count` bytes, whatever the map
+    /// type. Later tombstones stay in place for a following pass.
+    pub fn reclaim(&mut self, before_seq: u64) -> Result<usize, MemoryMapError> {
+        let tombstones = &mut self.tombstones;
+        let mut freed = 0;
+        let mut record = 0;
+        while record < tombstones.tombstone_count() {
+            let (seq, index) = tombstones.record(record);
+            if seq >= before_seq {
+                record += 1;
+                continue;
+            }
+            self.map.dealloc(index)?;
+            // Move the last record into the gap so a failed pass can be retried
+            let last = tombstones.tombstone_count() - 1;
+            let (last_seq, last_index) = tombstones.record(last);
+            tombstones.set_record(record, last_seq, last_index);
+            tombstones.set_count(last);
+            tombstones.flip(index);
+            freed += 1;
+        }
+
+        Ok(freed)
+    }
+}
+
+impl IndexAllocator for TombstonedMemoryMap<'_> {
+    fn alloc(&mut self) -> Result<usize, MemoryMapError> {
+        Self::alloc(self)
+    }
+
+    fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        Self::dealloc(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        self.map.is_allocated(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_aligned_buffer;
+
+    #[test]
+    fn test_reclaim_respects_sequence() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+        let mut region = vec![0u8; Tombstones::size(MapType::Small, 8)];
+        let tombstones = Tombstones::new(&mut region, MapType::Small).unwrap();
+        let mut map = TombstonedMemoryMap::new(map, tombstones).unwrap();
+        assert_eq!(map.tombstones().depth(), 8);
+        for _ in 0..10 {
+            map.alloc().unwrap();
+        }
+
+        map.remove(2, 100).unwrap();
+        map.remove(5, 101).unwrap();
+        assert_eq!(map.slot_state(2).unwrap(), SlotState::Tombstoned);
+        assert_eq!(map.slot_state(3).unwrap(), SlotState::Allocated);
+        assert!(matches!(
+            map.remove(2, 102),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+        // Tombstoned slots are not recycled
+        assert_eq!(map.alloc().unwrap(), 10);
+
+        // Each slot is reclaimed on its own sequence
+        assert_eq!(map.reclaim(100).unwrap(), 0);
+        assert_eq!(map.reclaim(101).unwrap(), 1);
+        assert_eq!(map.slot_state(2).unwrap(), SlotState::Free);
+        assert_eq!(map.slot_state(5).unwrap(), SlotState::Tombstoned);
+        assert_eq!(map.alloc().unwrap(), 2);
+
+        // Out of order sequences are reclaimed all the same
+        map.remove(7, 150).unwrap();
+        map.remove(8, 90).unwrap();
+        assert_eq!(map.tombstones().tombstone_count(), 3);
+        assert_eq!(map.reclaim(120).unwrap(), 2);
+        assert!(map.tombstones().is_tombstoned(7));
+        assert!(!map.tombstones().is_tombstoned(8));
+
+        assert_eq!(map.reclaim(151).unwrap(), 1);
+        assert_eq!(map.tombstones().tombstone_count(), 0);
+        assert_eq!(map.reclaim(u64::MAX).unwrap(), 0);
+        assert_eq!(map.map().used_count().unwrap(), 8);
+    }
+
+    #[test]
+    fn test_remove_fails_past_depth() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; Tombstones::size(MapType::Tiny, 2)];
+        let tombstones = Tombstones::new(&mut region, MapType::Tiny).unwrap();
+        let mut map = TombstonedMemoryMap::new(map, tombstones).unwrap();
+        for _ in 0..3 {
+            map.alloc().unwrap();
+        }
+
+        map.remove(0, 1).unwrap();
+        map.remove(1, 2).unwrap();
+        assert!(matches!(
+            map.remove(2, 3),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert_eq!(map.slot_state(2).unwrap(), SlotState::Allocated);
+        assert_eq!(map.reclaim(2).unwrap(), 1);
+        map.remove(2, 3).unwrap();
+
+        // A count past the depth is a corrupt region
+        write_u64(&mut region, 0, 3);
+        assert!(matches!(
+            Tombstones::new(&mut region, MapType::Tiny),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_tombstoned_slot_not_freed_early() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        let mut region = vec![0u8; Tombstones::size(MapType::Tiny, 4)];
+        let tombstones = Tombstones::new(&mut region, MapType::Tiny).unwrap();
+        let mut map = TombstonedMemoryMap::new(map, tombstones).unwrap();
+        map.alloc().unwrap();
+        map.alloc().unwrap();
+        map.remove(0, 1).unwrap();
+
+        // A plain dealloc cannot free the slot for reuse before the reclaim
+        assert!(matches!(map.dealloc(0), Err(MemoryMapError::InvalidIndex)));
+        assert_eq!(map.alloc().unwrap(), 2);
+        map.dealloc(1).unwrap();
+        assert_eq!(map.reclaim(2).unwrap(), 1);
+        assert_eq!(map.map().used_count().unwrap(), 1);
+        assert!(map.map().is_allocated(2).unwrap());
+    }
+
+    #[test]
+    fn test_tombstones_check_map_type() {
+        let mut buffer = create_aligned_buffer(MapType::Tiny.required_size());
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+
+        let mut region = vec![0u8; Tombstones::size(MapType::Small, 1)];
+        let tombstones = Tombstones::new(&mut region, MapType::Small).unwrap();
+        assert!(matches!(
+            TombstonedMemoryMap::new(map, tombstones),
+            Err(MemoryMapError::InvalidMapType)
+        ));
+        assert!(Tombstones::new(&mut [0u8; 16], MapType::Tiny).is_err());
+    }
+}
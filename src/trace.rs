@@ -0,0 +1,190 @@
+//! Per-operation spans for off-chain profiling
+//!
+//! With the `tracing` feature every map operation runs inside a
+//! `memory_map` span carrying the map type, the index involved and the
+//! elapsed time. Without the feature all of this compiles to nothing.
+//!
+//! The `trace` feature logs every alloc, dealloc and word write with
+//! `msg!`, for debugging inside the BPF VM. Off-chain builds that also enable
+//! `tracing` get them as `tracing` events instead.
+
+use crate::{MapType, MemoryMapError};
+
+/// Log an allocator event with the `trace` feature, nothing otherwise
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        $crate::trace::emit(format_args!($($arg)*));
+    };
+}
+pub(crate) use trace_event;
+
+#[cfg(feature = "trace")]
+pub(crate) fn emit(args: core::fmt::Arguments<'_>) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "index_mem_alloc", "{args}");
+    #[cfg(not(feature = "tracing"))]
+    solana_program::msg!("{}", args);
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) struct OpSpan {
+    span: tracing::span::EnteredSpan,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl OpSpan {
+    pub(crate) fn enter(op: &'static str, map_type: MapType) -> Self {
+        let span = tracing::debug_span!(
+            "memory_map",
+            op,
+            map_type = ?map_type,
+            index = tracing::field::Empty,
+            duration_ns = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        Self {
+            span: span.entered(),
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub(crate) fn record_index(&self, index: usize) {
+        self.span.record("index", index);
+    }
+
+    pub(crate) fn finish<T>(self, result: &Result<T, MemoryMapError>) {
+        self.span
+            .record("duration_ns", self.start.elapsed().as_nanos() as u64);
+        if let Err(err) = result {
+            self.span.record("error", tracing::field::debug(err));
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct OpSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl OpSpan {
+    #[inline(always)]
+    pub(crate) const fn enter(_op: &'static str, _map_type: MapType) -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    pub(crate) const fn record_index(&self, _index: usize) {}
+
+    #[inline(always)]
+    pub(crate) const fn finish<T>(self, _result: &Result<T, MemoryMapError>) {}
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::{create_aligned_buffer, MapType, MemoryMap};
+    use core::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Field names and values recorded on one span
+    type SpanFields = Vec<(String, String)>;
+
+    /// Fields recorded on each span, in creation order, and event messages
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<SpanFields>>>,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields<'a>(&'a mut SpanFields);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push((field.name().into(), value.into()));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.push((field.name().into(), format!("{value:?}")));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut fields = vec![("name".into(), span.metadata().name().into())];
+            span.record(&mut Fields(&mut fields));
+            spans.push(fields);
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut Fields(&mut spans[id.into_u64() as usize - 1]));
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Vec::new();
+            event.record(&mut Fields(&mut fields));
+            self.events
+                .lock()
+                .unwrap()
+                .extend(fields.into_iter().map(|(_, message)| message));
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    fn field<'a>(fields: &'a SpanFields, name: &str) -> Option<&'a str> {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_span_records_operation() {
+        let recorder = Recorder::default();
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Small).unwrap();
+            map.alloc().unwrap();
+            map.alloc().unwrap();
+            map.dealloc(1 << 20).unwrap_err();
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        assert_eq!(spans.len(), 3);
+        let alloc = &spans[1];
+        assert_eq!(field(alloc, "name"), Some("memory_map"));
+        assert_eq!(field(alloc, "op"), Some("alloc"));
+        assert_eq!(field(alloc, "map_type"), Some("Small"));
+        assert_eq!(field(alloc, "index"), Some("1"));
+        assert!(field(alloc, "duration_ns").is_some());
+        assert_eq!(field(alloc, "error"), None);
+
+        let dealloc = &spans[2];
+        assert_eq!(field(dealloc, "op"), Some("dealloc"));
+        assert_eq!(field(dealloc, "index"), Some("1048576"));
+        assert_eq!(field(dealloc, "error"), Some("InvalidIndex"));
+
+        #[cfg(feature = "trace")]
+        assert!(recorder
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|message| message == "imm alloc index=1"));
+    }
+}
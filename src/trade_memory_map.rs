@@ -1,100 +1,113 @@
-use crate::{get_first_zero_bit::get_first_zero_bit, get_u64, get_u64_mut, MemoryMapError};
-use std::{mem::size_of, ptr::NonNull};
+use crate::{hier_memory_map::HierMemoryMap, MemoryMapError};
 
 /// Standard memory map implementation (3 levels, 4 bits at first level)
+pub type StandardMemoryMap = HierMemoryMap<4, 3>;
+
+/// Side of the orderbook an index belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+/// Standard map split between the two sides of an orderbook
+///
+/// Bids take indices `0..split` from the bottom up, asks take
+/// `split..capacity` from the top down, so each side stays contiguous and can
+/// be walked in price-time order cheaply.
 #[derive(Clone)]
-pub struct StandardMemoryMap {
-    memory: NonNull<u8>,
-    size: usize,
+pub struct OrderSideMemoryMap {
+    map: StandardMemoryMap,
+    split: usize,
 }
 
-impl StandardMemoryMap {
-    /// Create a new standard memory map
-    pub(crate) fn new(memory: NonNull<u8>, size: usize) -> Result<Self, MemoryMapError> {
-        // Calculate required memory size for standard map:
-        // - First level: 1 word to track available blocks in level 2
-        // - Second level: 4 words (one per bit in first level)
-        // - Third level: 4*64 words (one per bit in second level)
-        let required_size = (1 + 4 + 4 * 64) * size_of::<u64>();
-
-        // Check if there's enough memory
-        if size < required_size {
-            return Err(MemoryMapError::InsufficientMemory);
+impl OrderSideMemoryMap {
+    /// Split `map` so that bids own indices below `split`
+    pub fn new(map: StandardMemoryMap, split: usize) -> Result<Self, MemoryMapError> {
+        if split > StandardMemoryMap::CAPACITY {
+            return Err(MemoryMapError::InvalidIndex);
         }
-
-        Ok(Self { memory, size })
+        Ok(Self { map, split })
     }
 
-    /// Allocate a new slot
-    pub(crate) fn alloc(&mut self) -> Result<usize, MemoryMapError> {
-        // First level allocation (4 bits)
-        let first_word = get_u64(self.memory, self.size, 0)?;
-        let first = get_first_zero_bit(*first_word, 4)?;
-
-        // Second level allocation
-        let second_idx = 1 + first;
-        let second_word = get_u64(self.memory, self.size, second_idx)?;
-        let second = get_first_zero_bit(*second_word, 64)?;
-
-        // Third level allocation
-        let third_idx = 5 + (first * 64) + second;
-        let third_word = get_u64(self.memory, self.size, third_idx)?;
-        let third = get_first_zero_bit(*third_word, 64)?;
-
-        // Mark as allocated
-        let third_word_mut = get_u64_mut(self.memory, self.size, third_idx)?;
-        *third_word_mut |= 1 << third;
-
-        if *third_word_mut == u64::MAX {
-            let second_word_mut = get_u64_mut(self.memory, self.size, second_idx)?;
-            *second_word_mut |= 1 << second;
-
-            if *second_word_mut == u64::MAX {
-                let first_word_mut = get_u64_mut(self.memory, self.size, 0)?;
-                *first_word_mut |= 1 << first;
-            }
+    /// Split `map` into two halves of equal capacity
+    pub fn halves(map: StandardMemoryMap) -> Self {
+        Self {
+            map,
+            split: StandardMemoryMap::CAPACITY / 2,
         }
+    }
 
-        Ok((first << 12) + (second << 6) + third)
+    /// First index owned by the ask side
+    pub const fn split(&self) -> usize {
+        self.split
     }
 
-    /// Deallocate a previously allocated slot
-    pub(crate) fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
-        // Check upper bound
-        let max_index = (4 << 12) - 1; // 16383
-        if index > max_index {
-            return Err(MemoryMapError::InvalidIndex);
+    /// Side owning `index`
+    pub fn side(&self, index: usize) -> Result<OrderSide, MemoryMapError> {
+        if index >= StandardMemoryMap::CAPACITY {
+            Err(MemoryMapError::InvalidIndex)
+        } else if index < self.split {
+            Ok(OrderSide::Bid)
+        } else {
+            Ok(OrderSide::Ask)
         }
+    }
 
-        // standard memory map - 3 levels
-        let first = index >> 12;
-        let second = (index & 0xfff) >> 6;
-        let second_idx = 1 + first;
-        let third_idx = 5 + (index >> 6);
+    /// Allocate the lowest free bid index outside the reserved range
+    pub fn alloc_bid(&mut self) -> Result<usize, MemoryMapError> {
+        let mut index = self.map.find_first_free()?;
+        if let Some(reserved) = &self.map.reserved_range() {
+            index = self.map.skip_reserved(index, reserved)?;
+        }
+        if index >= self.split {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        self.map.mark_allocated(index)?;
+        Ok(index)
+    }
 
-        // Clear allocation bits
-        let third_word = get_u64_mut(self.memory, self.size, third_idx)?;
-        *third_word &= !(1 << (index & 0x3f));
+    /// Allocate the highest free ask index outside the reserved range
+    pub fn alloc_ask(&mut self) -> Result<usize, MemoryMapError> {
+        let index = self.map.last_free(self.map.reserved_range().as_ref())?;
+        if index < self.split {
+            return Err(MemoryMapError::NoAvailableSlots);
+        }
+        self.map.mark_allocated(index)?;
+        Ok(index)
+    }
 
-        let second_word = get_u64_mut(self.memory, self.size, second_idx)?;
-        *second_word &= !(1 << second);
+    /// Allocate an index on `side`
+    pub fn alloc(&mut self, side: OrderSide) -> Result<usize, MemoryMapError> {
+        match side {
+            OrderSide::Bid => self.alloc_bid(),
+            OrderSide::Ask => self.alloc_ask(),
+        }
+    }
 
-        let first_word = get_u64_mut(self.memory, self.size, 0)?;
-        *first_word &= !(1 << first);
+    /// Deallocate an index of either side
+    pub fn dealloc(&mut self, index: usize) -> Result<(), MemoryMapError> {
+        self.map.dealloc(index)
+    }
 
-        Ok(())
+    /// Give back the underlying map
+    pub fn into_inner(self) -> StandardMemoryMap {
+        self.map
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::create_aligned_memory;
+    use crate::{
+        create_aligned_buffer, create_aligned_memory, MapType, MemoryMap, MemoryMapError,
+        HEADER_SIZE,
+    };
 
     #[test]
     fn test_standard_map_basic_operations() {
         // Create memory with sufficient size for StandardMemoryMap
-        let required_size = (1 + 4 + 4 * 64) * size_of::<u64>();
+        let required_size = MapType::Standard.required_size();
         let (data, ptr) = create_aligned_memory(required_size * 2);
 
         // Test creation and basic memory allocation
@@ -116,7 +129,7 @@ mod tests {
     #[test]
     fn test_standard_map_level_transitions() {
         // Create memory with sufficient size for level transitions
-        let required_size = (1 + 4 + 4 * 64) * size_of::<u64>();
+        let required_size = MapType::Standard.required_size();
         let (mut data, ptr) = create_aligned_memory(required_size * 2);
 
         data.fill(0);
@@ -160,7 +173,7 @@ mod tests {
 
     #[test]
     fn test_standard_map_allocation_and_deallocation() {
-        let required_size = (1 + 4 + 4 * 64) * size_of::<u64>();
+        let required_size = MapType::Standard.required_size();
         let (mut data, ptr) = create_aligned_memory(required_size);
 
         data.fill(0);
@@ -189,7 +202,7 @@ mod tests {
 
     #[test]
     fn test_standard_map_capacity() {
-        let required_size = (1 + 4 + 4 * 64) * size_of::<u64>();
+        let required_size = MapType::Standard.required_size();
         let (mut data, ptr) = create_aligned_memory(required_size);
 
         data.fill(0);
@@ -219,4 +232,118 @@ mod tests {
         let idx = map.alloc().unwrap();
         assert_eq!(idx, 0, "After deallocating all, should start from 0");
     }
+
+    #[test]
+    fn test_standard_map_full() {
+        let required_size = MapType::Standard.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+        let mut map = StandardMemoryMap::new(ptr, data.len()).unwrap();
+
+        for expected in 0..MapType::Standard.capacity() {
+            assert!(!map.is_full());
+            assert_eq!(map.alloc().unwrap(), expected);
+        }
+
+        assert!(map.is_full());
+        assert!(matches!(map.alloc(), Err(MemoryMapError::NoAvailableSlots)));
+
+        map.dealloc(9000).unwrap();
+        assert!(!map.is_full());
+        assert_eq!(map.alloc().unwrap(), 9000);
+    }
+
+    #[test]
+    fn test_order_sides() {
+        let required_size = MapType::Standard.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+        let map = StandardMemoryMap::new(ptr, data.len()).unwrap();
+        let mut book = OrderSideMemoryMap::new(map, 100).unwrap();
+
+        assert_eq!(book.alloc_bid().unwrap(), 0);
+        assert_eq!(book.alloc_bid().unwrap(), 1);
+        assert_eq!(book.alloc_ask().unwrap(), 16_383);
+        assert_eq!(book.alloc(OrderSide::Ask).unwrap(), 16_382);
+
+        assert_eq!(book.side(1).unwrap(), OrderSide::Bid);
+        assert_eq!(book.side(99).unwrap(), OrderSide::Bid);
+        assert_eq!(book.side(100).unwrap(), OrderSide::Ask);
+        assert!(matches!(
+            book.side(16_384),
+            Err(MemoryMapError::InvalidIndex)
+        ));
+
+        book.dealloc(16_383).unwrap();
+        assert_eq!(book.alloc_ask().unwrap(), 16_383);
+    }
+
+    #[test]
+    fn test_order_sides_skip_reserved_range() {
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Standard.required_size());
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Standard).unwrap();
+        map.reserve_range(0, 4).unwrap();
+        let MemoryMap::Standard(map) = map else {
+            unreachable!()
+        };
+        let mut book = OrderSideMemoryMap::new(map, 6).unwrap();
+        assert_eq!(book.alloc(OrderSide::Bid).unwrap(), 4);
+        assert_eq!(book.alloc(OrderSide::Bid).unwrap(), 5);
+        assert!(matches!(
+            book.alloc(OrderSide::Bid),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+
+        // Asks stop below a range at the top of the map
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + MapType::Standard.required_size());
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Standard).unwrap();
+        map.reserve_range(16_380, 16_384).unwrap();
+        let MemoryMap::Standard(map) = map else {
+            unreachable!()
+        };
+        let mut book = OrderSideMemoryMap::new(map, 16_378).unwrap();
+        assert_eq!(book.alloc(OrderSide::Ask).unwrap(), 16_379);
+        assert_eq!(book.alloc(OrderSide::Ask).unwrap(), 16_378);
+        assert!(matches!(
+            book.alloc(OrderSide::Ask),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        let map = book.into_inner();
+        assert!((16_380..16_384).all(|index| !map.is_allocated(index).unwrap()));
+    }
+
+    #[test]
+    fn test_order_side_exhaustion() {
+        let required_size = MapType::Standard.required_size();
+        let (mut data, ptr) = create_aligned_memory(required_size);
+        data.fill(0);
+        let map = StandardMemoryMap::new(ptr, data.len()).unwrap();
+        let mut book = OrderSideMemoryMap::new(map, 3).unwrap();
+
+        for expected in 0..3 {
+            assert_eq!(book.alloc_bid().unwrap(), expected);
+        }
+        // Bid side is full while the ask side is still empty
+        assert!(matches!(
+            book.alloc_bid(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+
+        for expected in (3..16_384).rev() {
+            assert_eq!(book.alloc_ask().unwrap(), expected);
+        }
+        assert!(matches!(
+            book.alloc_ask(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+
+        // Freed bid slots never leak to the ask side
+        book.dealloc(1).unwrap();
+        assert!(matches!(
+            book.alloc_ask(),
+            Err(MemoryMapError::NoAvailableSlots)
+        ));
+        assert_eq!(book.alloc_bid().unwrap(), 1);
+        assert!(OrderSideMemoryMap::new(book.into_inner(), 16_385).is_err());
+    }
 }
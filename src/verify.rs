@@ -0,0 +1,256 @@
+use crate::{
+    backend::Backend, hier_memory_map::HierMemoryMap, trace::OpSpan, MemoryMap, MemoryMapError,
+};
+
+/// Mismatches kept in a [`CorruptionReport`], later ones are only counted
+pub const REPORTED_MISMATCHES: usize = 4;
+
+/// Summary word that disagrees with the words below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WordMismatch {
+    /// Level of the word, 0 is the first level
+    pub level: u8,
+    /// Word index counted from the start of the bitmap
+    pub word: u32,
+    /// Value found in the bitmap
+    pub stored: u64,
+    /// Value derived from the level below
+    pub derived: u64,
+}
+
+impl WordMismatch {
+    /// Bits that differ between the stored and the derived word
+    pub const fn bits(&self) -> u64 {
+        self.stored ^ self.derived
+    }
+}
+
+/// Outcome of a failed [`MemoryMap::verify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CorruptionReport {
+    /// Summary words that disagree with the level below
+    pub mismatched_words: usize,
+    /// Words the backend failed to read
+    pub unreadable_words: usize,
+    mismatches: [WordMismatch; REPORTED_MISMATCHES],
+}
+
+impl CorruptionReport {
+    /// First mismatched words, in bitmap order
+    pub fn mismatches(&self) -> &[WordMismatch] {
+        &self.mismatches[..self.mismatched_words.min(REPORTED_MISMATCHES)]
+    }
+
+    fn push(&mut self, mismatch: WordMismatch) {
+        if let Some(slot) = self.mismatches.get_mut(self.mismatched_words) {
+            *slot = mismatch;
+        }
+        self.mismatched_words += 1;
+    }
+
+    const fn is_clean(&self) -> bool {
+        self.mismatched_words == 0 && self.unreadable_words == 0
+    }
+}
+
+impl<const L1_BITS: usize, const LEVELS: usize, B: Backend> HierMemoryMap<L1_BITS, LEVELS, B> {
+    /// Check every summary word against the fullness of the words below it
+    pub fn verify(&self) -> Result<(), CorruptionReport> {
+        let mut report = CorruptionReport::default();
+        for level in 0..LEVELS - 1 {
+            let start = Self::level_start(level);
+            for word in start..Self::level_start(level + 1) {
+                let Ok(derived) = self.derive_summary(level, word - start) else {
+                    report.unreadable_words += 1;
+                    continue;
+                };
+                let Ok(stored) = self.word_at(word) else {
+                    report.unreadable_words += 1;
+                    continue;
+                };
+                if stored != derived {
+                    report.push(WordMismatch {
+                        level: level as u8,
+                        word: word as u32,
+                        stored,
+                        derived,
+                    });
+                }
+            }
+        }
+
+        if report.is_clean() {
+            Ok(())
+        } else {
+            Err(report)
+        }
+    }
+
+    /// Rewrite every summary word from the leaf level up, returns the number
+    /// of words that changed
+    pub fn repair(&mut self) -> Result<usize, MemoryMapError> {
+        let mut repaired = 0;
+        for level in (0..LEVELS - 1).rev() {
+            let start = Self::level_start(level);
+            for word in start..Self::level_start(level + 1) {
+                let derived = self.derive_summary(level, word - start)?;
+                if self.word_at(word)? != derived {
+                    self.set_word_at(word, derived)?;
+                    repaired += 1;
+                }
+            }
+        }
+        self.recount()?;
+
+        Ok(repaired)
+    }
+
+    /// Word `index` of summary `level` as the level below says it should be
+    fn derive_summary(&self, level: usize, index: usize) -> Result<u64, MemoryMapError> {
+        let bits = if level == 0 { L1_BITS } else { 64 };
+        let children = Self::level_start(level + 1) + (index << 6);
+        (0..bits).try_fold(0, |word, bit| {
+            let child = self.word_at(children + bit)?;
+            Ok(word | (u64::from(child == u64::MAX) << bit))
+        })
+    }
+}
+
+impl MemoryMap {
+    /// Re-derive every summary level from the leaf words and report the words
+    /// that disagree
+    ///
+    /// Reads the whole bitmap once, cheap enough for an admin instruction on
+    /// the smaller map types.
+    pub fn verify(&self) -> Result<(), CorruptionReport> {
+        match self {
+            Self::Max(map) => map.verify(),
+            Self::Standard(map) => map.verify(),
+            Self::Small(map) => map.verify(),
+            Self::Huge(map) => map.verify(),
+            Self::Tiny(map) => map.verify(),
+            Self::Medium(map) => map.verify(),
+        }
+    }
+
+    /// Rebuild every summary level from the authoritative leaf words
+    ///
+    /// Fixes summary bits corrupted by an external write without touching any
+    /// allocation, then recounts the header counter. Returns the number of
+    /// summary words rewritten.
+    pub fn repair(&mut self) -> Result<usize, MemoryMapError> {
+        let span = OpSpan::enter("repair", self.map_type());
+        let result = match self {
+            Self::Max(map) => map.repair(),
+            Self::Standard(map) => map.repair(),
+            Self::Small(map) => map.repair(),
+            Self::Huge(map) => map.repair(),
+            Self::Tiny(map) => map.repair(),
+            Self::Medium(map) => map.repair(),
+        };
+        span.finish(&result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MapType, HEADER_SIZE};
+
+    #[test]
+    fn test_verify_clean_maps() {
+        for map_type in [MapType::Tiny, MapType::Medium, MapType::Standard] {
+            let mut buffer = create_aligned_buffer(map_type.required_size());
+            let mut map = MemoryMap::new_from_slice(&mut buffer, 0, map_type).unwrap();
+            assert_eq!(map.verify(), Ok(()));
+            while map.alloc().is_ok() {}
+            assert_eq!(map.verify(), Ok(()));
+            map.dealloc(100 % map_type.capacity()).unwrap();
+            assert_eq!(map.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_verify_reports_words() {
+        let mut buffer = create_aligned_buffer(MapType::Max.required_size());
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        for _ in 0..128 {
+            map.alloc().unwrap();
+        }
+        assert_eq!(map.verify(), Ok(()));
+
+        // Clear a full leaf word behind the map's back, its parent and the
+        // first level still claim it is full
+        let leaf = MapType::Max.level_start(2) + 1;
+        buffer[leaf * 8..leaf * 8 + 8].fill(0);
+        // And a stray bit in the second level
+        let second = MapType::Max.level_start(1) + 5;
+        buffer[second * 8] = 1;
+
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        let report = map.verify().unwrap_err();
+        assert_eq!(report.mismatched_words, 2);
+        assert_eq!(report.unreadable_words, 0);
+        let [parent, stray] = report.mismatches() else {
+            panic!("expected two mismatches: {report:?}");
+        };
+        assert_eq!(
+            (parent.level, parent.word, parent.bits()),
+            (1, MapType::Max.level_start(1) as u32, 0b10)
+        );
+        assert_eq!(
+            (stray.level, stray.word, stray.bits()),
+            (1, second as u32, 1)
+        );
+    }
+
+    #[test]
+    fn test_report_caps_mismatches() {
+        let mut buffer = create_aligned_buffer(MapType::Small.required_size());
+        let map_words = MapType::Small.required_size() / 8;
+        // Every first-level bit set with empty leaves
+        buffer[..8].fill(0xff);
+        let map =
+            MemoryMap::new_from_slice(&mut buffer[..map_words * 8], 0, MapType::Small).unwrap();
+        let report = map.verify().unwrap_err();
+        assert_eq!(report.mismatched_words, 1);
+        assert_eq!(report.mismatches()[0].bits(), u64::MAX);
+
+        let mut buffer = create_aligned_buffer(MapType::Max.required_size());
+        for word in 1..=20 {
+            buffer[word * 8] = 1;
+        }
+        let map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Max).unwrap();
+        let report = map.verify().unwrap_err();
+        assert_eq!(report.mismatched_words, 20);
+        assert_eq!(report.mismatches().len(), REPORTED_MISMATCHES);
+    }
+
+    #[test]
+    fn test_repair_restores_summaries() {
+        let size = HEADER_SIZE + MapType::Max.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer[..size], 0, MapType::Max).unwrap();
+        for _ in 0..200 {
+            map.alloc().unwrap();
+        }
+        map.dealloc(70).unwrap();
+
+        // Clear the bits of the full leaf words, mark a second-level word full
+        let bitmap = HEADER_SIZE;
+        buffer[bitmap + MapType::Max.level_start(1) * 8] = 0;
+        buffer[bitmap] = 0b1000;
+        buffer[bitmap + (MapType::Max.level_start(1) + 9) * 8] = 0x80;
+
+        let mut map = MemoryMap::load(&mut buffer[..size], 0, MapType::Max).unwrap();
+        assert_eq!(map.verify().unwrap_err().mismatched_words, 3);
+        assert_eq!(map.repair().unwrap(), 3);
+        assert_eq!(map.verify(), Ok(()));
+        assert_eq!(map.repair().unwrap(), 0);
+
+        assert_eq!(map.used_count().unwrap(), 199);
+        assert_eq!(map.alloc().unwrap(), 70);
+        assert_eq!(map.alloc().unwrap(), 200);
+    }
+}
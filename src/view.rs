@@ -0,0 +1,219 @@
+//! Read-only maps over immutable account snapshots
+
+use crate::{
+    bytes::{read_u16, read_u32, read_u64},
+    fragmentation::leaf_stats,
+    LayoutVersion, MapHeader, MapType, MemoryMapError, MemoryMapStats, SliceQuery, HEADER_MAGIC,
+    HEADER_SIZE,
+};
+use core::mem::offset_of;
+
+/// Read-only counterpart of [`MemoryMap`](crate::MemoryMap) for off-chain
+/// code holding an immutable account snapshot
+///
+/// Built on [`SliceQuery`], so the bitmap needs no alignment. There is no
+/// mutating method to call, rather than one that fails at runtime.
+#[derive(Clone, Copy)]
+pub struct MemoryMapView<'a> {
+    query: SliceQuery<'a>,
+    /// Slots below the tail reserved by `with_capacity`
+    slot_limit: usize,
+}
+
+impl<'a> MemoryMapView<'a> {
+    /// View the `map_type` bitmap at `data[offset..]`
+    ///
+    /// For maps created with `MemoryMap::init` the bitmap follows the header,
+    /// at `offset + HEADER_SIZE`.
+    pub fn new_from_slice(
+        data: &'a [u8],
+        offset: usize,
+        map_type: MapType,
+    ) -> Result<Self, MemoryMapError> {
+        SliceQuery::new(data, offset, map_type).map(Self::from)
+    }
+
+    /// View the map whose header is at `data[offset..]`
+    ///
+    /// Takes the map type and the tail reserved by `with_capacity` from the
+    /// header written by `MemoryMap::init`.
+    pub fn load(data: &'a [u8], offset: usize) -> Result<Self, MemoryMapError> {
+        let header = data.get(offset..).ok_or(MemoryMapError::InvalidOffset)?;
+        let header = header
+            .get(..HEADER_SIZE)
+            .ok_or(MemoryMapError::InsufficientMemory)?;
+        if read_u32(header, offset_of!(MapHeader, magic)) != HEADER_MAGIC {
+            return Err(MemoryMapError::InvalidHeader);
+        }
+        let version = read_u16(header, offset_of!(MapHeader, version));
+        if LayoutVersion::try_from(version)? != LayoutVersion::CURRENT {
+            return Err(MemoryMapError::UnsupportedVersion);
+        }
+        let map_type = MapType::try_from(header[offset_of!(MapHeader, map_type)])?;
+
+        let mut view = Self::new_from_slice(data, offset + HEADER_SIZE, map_type)?;
+        let slot_limit = read_u64(header, offset_of!(MapHeader, slot_limit));
+        if slot_limit != 0 {
+            view.slot_limit = usize::try_from(slot_limit)
+                .ok()
+                .filter(|&limit| limit <= view.capacity())
+                .ok_or(MemoryMapError::InvalidHeader)?;
+        }
+        Ok(view)
+    }
+
+    /// Type of the viewed map
+    pub const fn map_type(&self) -> MapType {
+        self.query.map_type()
+    }
+
+    /// Number of slots the map can hand out
+    pub const fn capacity(&self) -> usize {
+        self.map_type().capacity()
+    }
+
+    /// Check whether slot `index` is allocated
+    pub fn is_allocated(&self, index: usize) -> Result<bool, MemoryMapError> {
+        self.query.is_allocated(index)
+    }
+
+    /// Check whether every slot is allocated
+    pub fn is_full(&self) -> bool {
+        self.query.is_full()
+    }
+
+    /// Slot the next `alloc` would hand out on a map without hints
+    pub fn peek_next_free(&self) -> Option<usize> {
+        self.query.first_free()
+    }
+
+    /// Occupancy and fragmentation snapshot, read from the leaf words
+    ///
+    /// Like `MemoryMap::stats`, leaves out the reserved tail of a view
+    /// created with [`MemoryMapView::load`].
+    pub fn stats(&self) -> Result<MemoryMapStats, MemoryMapError> {
+        leaf_stats(
+            self.capacity() / 64,
+            self.query.leaf_mask(),
+            self.slot_limit,
+            |block| Ok(self.query.leaf_word(block)),
+        )
+    }
+
+    /// Allocated slots in ascending order
+    pub fn iter_allocated(&self) -> impl Iterator<Item = usize> + '_ {
+        self.query.iter_allocated()
+    }
+}
+
+impl<'a> From<SliceQuery<'a>> for MemoryMapView<'a> {
+    fn from(query: SliceQuery<'a>) -> Self {
+        Self {
+            slot_limit: query.map_type().capacity(),
+            query,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_aligned_buffer, MemoryMap, HEADER_SIZE};
+
+    #[test]
+    fn test_view_matches_map() {
+        for map_type in [MapType::Tiny, MapType::Medium, MapType::Max] {
+            let size = HEADER_SIZE + map_type.required_size();
+            let mut buffer = create_aligned_buffer(size);
+            let mut map = MemoryMap::init(&mut buffer, 0, map_type).unwrap();
+            for _ in 0..50 {
+                map.alloc().unwrap();
+            }
+            map.dealloc(9).unwrap();
+            let stats = map.stats().unwrap();
+
+            let view = MemoryMapView::new_from_slice(&buffer, HEADER_SIZE, map_type).unwrap();
+            assert_eq!(view.map_type(), map_type);
+            assert!(view.is_allocated(8).unwrap());
+            assert!(!view.is_allocated(9).unwrap());
+            assert_eq!(view.peek_next_free(), Some(9));
+            assert!(!view.is_full());
+            assert_eq!(view.stats().unwrap(), stats);
+            assert!(view
+                .iter_allocated()
+                .eq((0..50).filter(|&index| index != 9)));
+        }
+    }
+
+    #[test]
+    fn test_view_stats_across_leaf_words() {
+        let size = HEADER_SIZE + MapType::Small.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::init(&mut buffer, 0, MapType::Small).unwrap();
+        for _ in 0..200 {
+            map.alloc().unwrap();
+        }
+        for index in (60..140).chain([3, 150]) {
+            map.dealloc(index).unwrap();
+        }
+        let stats = map.stats().unwrap();
+
+        let view = MemoryMapView::new_from_slice(&buffer, HEADER_SIZE, MapType::Small).unwrap();
+        assert_eq!(view.stats().unwrap(), stats);
+        assert_eq!(
+            view.stats().unwrap().largest_free_run,
+            map.largest_free_run()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_view_load_leaves_out_reserved_tail() {
+        let map_type = MapType::for_slots(100).unwrap();
+        let mut buffer = create_aligned_buffer(HEADER_SIZE + map_type.required_size());
+        let mut map = MemoryMap::with_capacity(&mut buffer, 0, 100).unwrap();
+        for _ in 0..40 {
+            map.alloc().unwrap();
+        }
+        map.dealloc_batch(&[1, 2]).unwrap();
+        let stats = map.stats().unwrap();
+
+        let view = MemoryMapView::load(&buffer, 0).unwrap();
+        assert_eq!(view.map_type(), map_type);
+        assert_eq!(view.stats().unwrap(), stats);
+        assert_eq!(stats.free, map.free_count().unwrap());
+
+        assert!(matches!(
+            MemoryMapView::load(&buffer, 8),
+            Err(MemoryMapError::InvalidHeader)
+        ));
+        assert!(matches!(
+            MemoryMapView::load(&buffer[..HEADER_SIZE - 1], 0),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+    }
+
+    #[test]
+    fn test_view_unaligned_and_full() {
+        let size = MapType::Tiny.required_size();
+        let mut buffer = create_aligned_buffer(size);
+        let mut map = MemoryMap::new_from_slice(&mut buffer, 0, MapType::Tiny).unwrap();
+        while map.alloc().is_ok() {}
+
+        let mut shifted = vec![0; 3];
+        shifted.extend_from_slice(&buffer[..size]);
+        let view = MemoryMapView::new_from_slice(&shifted, 3, MapType::Tiny).unwrap();
+        assert!(view.is_full());
+        assert_eq!(view.peek_next_free(), None);
+        assert_eq!(view.iter_allocated().count(), 64);
+
+        assert!(matches!(
+            MemoryMapView::new_from_slice(&shifted, 4, MapType::Tiny),
+            Err(MemoryMapError::InsufficientMemory)
+        ));
+        assert!(matches!(
+            MemoryMapView::new_from_slice(&shifted, 12, MapType::Tiny),
+            Err(MemoryMapError::InvalidOffset)
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+//! Read-only occupancy queries for browser code, built from RPC snapshots
+
+use crate::{MapType, MemoryMapError, SliceQuery};
+use wasm_bindgen::prelude::*;
+
+/// Read-only map over a copy of account data, e.g. a `Uint8Array` from
+/// `getAccountInfo`
+#[wasm_bindgen]
+pub struct WasmMemoryMap {
+    data: Vec<u8>,
+    offset: usize,
+    map_type: MapType,
+}
+
+#[wasm_bindgen]
+impl WasmMemoryMap {
+    /// Snapshot of the bitmap of a `map_type` map at `data[offset..]`
+    ///
+    /// `map_type` is the `MapType` discriminant. For maps created with
+    /// `MemoryMap::init` the bitmap starts at `offset + HEADER_SIZE`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: Vec<u8>, offset: usize, map_type: u8) -> Result<Self, JsError> {
+        let map_type = MapType::try_from(map_type).map_err(js_error)?;
+        SliceQuery::new(&data, offset, map_type).map_err(js_error)?;
+        Ok(Self {
+            data,
+            offset,
+            map_type,
+        })
+    }
+
+    #[wasm_bindgen(js_name = isAllocated)]
+    pub fn is_allocated(&self, index: usize) -> Result<bool, JsError> {
+        self.query()?.is_allocated(index).map_err(js_error)
+    }
+
+    #[wasm_bindgen(js_name = usedCount)]
+    pub fn used_count(&self) -> Result<usize, JsError> {
+        Ok(self.query()?.used_count())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map_type.capacity()
+    }
+
+    /// Allocated slots in ascending order
+    #[wasm_bindgen(js_name = iterAllocated)]
+    pub fn iter_allocated(&self) -> Result<Vec<u32>, JsError> {
+        Ok(self
+            .query()?
+            .iter_allocated()
+            .map(|index| index as u32)
+            .collect())
+    }
+}
+
+impl WasmMemoryMap {
+    fn query(&self) -> Result<SliceQuery<'_>, JsError> {
+        SliceQuery::new(&self.data, self.offset, self.map_type).map_err(js_error)
+    }
+}
+
+fn js_error(err: MemoryMapError) -> JsError {
+    JsError::new(&format!("{err:?}"))
+}